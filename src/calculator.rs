@@ -1,18 +1,37 @@
 use crate::config::*;
+use crate::source::Corpus;
+use crate::ngrams::*;
 use crate::parser::Position;
 use crate::geometry::{Key};
 use crate::keyboard::*;
 
-use hashbrown::{HashSet};
-use once_cell::sync::Lazy;
+use std::sync::Arc;
+use hashbrown::{HashMap,HashSet};
+
+// the count tables have no text order, so the share of pairs that come after a
+// roll gets guessed from the rolls and played again, this many times over
+const ROLL_ROUNDS: usize = 10;
 
 #[derive(Debug)]
 pub struct Calculator<'a> {
   keyboard: &'a Keyboard<'a>,
+  penalties: &'a PenaltiesConfig,
   bad_starters: &'a HashSet<Position>,
   rolling_pairs_map: &'a HashSet<(Position, Position)>
 }
 
+struct Costs {
+  overheads: usize,
+  awkwardness: usize,
+  rolls: usize
+}
+
+struct Transition {
+  overheads: usize,
+  awkwardness: usize,
+  rolling: bool
+}
+
 #[derive(Debug,PartialEq)]
 pub struct Result {
   pub effort: f64,
@@ -21,27 +40,29 @@ pub struct Result {
   pub rollingness: f64
 }
 
-pub fn process(keyboard: &Keyboard) -> Result {
-  let calculator = Calculator::from(keyboard);
-  calculator.run(&CONFIG.data)
+pub fn process(keyboard: &Keyboard, config: &Config, corpus: &Corpus) -> Result {
+  let calculator = Calculator::from(keyboard, &config.penalties);
+  calculator.aggregate(&ngrams_for(keyboard, corpus))
 }
 
-struct MappingsSet {
-  bad_starters: HashSet<Position>,
-  rolling_pairs: HashSet<(Position, Position)>
-}
+/// The n-grams of the corpus symbols the keyboard has keys for
+pub fn ngrams_for(keyboard: &Keyboard, corpus: &Corpus) -> Arc<NGrams> {
+  let mut typeable: Vec<char> = corpus.frequencies().iter()
+    .map(|(symbol, _)| *symbol)
+    .filter(|symbol| keyboard.key_for(symbol).is_some())
+    .collect();
+  typeable.sort();
 
-static MAPPINGS_CACHE: Lazy<MappingsSet> = Lazy::new(|| MappingsSet {
-  bad_starters: CONFIG.geometry.bad_starting_positions(),
-  rolling_pairs: CONFIG.geometry.rolling_position_pairs()
-});
+  corpus.ngrams(&typeable)
+}
 
 impl Calculator<'_> {
-  pub fn from<'a>(keyboard: &'a Keyboard) -> Calculator<'a> {
-    let bad_starters = &MAPPINGS_CACHE.bad_starters;
-    let rolling_pairs_map = &MAPPINGS_CACHE.rolling_pairs;
+  pub fn from<'a>(keyboard: &'a Keyboard, penalties: &'a PenaltiesConfig) -> Calculator<'a> {
+    let mappings = keyboard.geometry.mappings();
+    let bad_starters = &mappings.bad_starters;
+    let rolling_pairs_map = &mappings.rolling_pairs;
     
-    Calculator { keyboard, bad_starters, rolling_pairs_map }
+    Calculator { keyboard, penalties, bad_starters, rolling_pairs_map }
   }
 
   pub fn run(self: &Self, text: &String) -> Result {
@@ -54,60 +75,230 @@ impl Calculator<'_> {
 
     let mut previous_key = space_key;
     let mut rolling = false;
-    let rolling_discount = 100 - CONFIG.penalties.rolling_discount;
 
     for symbol in text.chars() {
       if let Some(key) = self.keyboard.key_for(&symbol) {
         effort += key.effort;
 
-        if 
-          previous_key.hand == key.hand && // same hand
-          key != previous_key &&           // different key
-          key != space_key &&              // not space
-          previous_key != space_key        // not from space
-        {
-          let mut same_hand_penalties = self.same_hand_penalties(previous_key, key);
-          let mut awkwardness_penalty = self.awkward_penalty(previous_key, key);
-
-          // only allowing two rolling symbols in a row to awoid counting the change of direction as a roll
-          rolling = if rolling { false } else { self.is_rolling_combo(previous_key, key) };
-        
+        if let Some(transition) = self.transition(previous_key, key, space_key, rolling) {
+          rolling = transition.rolling;
+
           if rolling {
             rollingness += 1;
-
-            same_hand_penalties = (same_hand_penalties * rolling_discount) / 100;
-            awkwardness_penalty = (awkwardness_penalty  * rolling_discount) / 100;
           }
 
-
-          effort += same_hand_penalties + awkwardness_penalty;
-          overheads += same_hand_penalties + awkwardness_penalty;
-          awkwardness += awkwardness_penalty;
+          effort += transition.overheads;
+          overheads += transition.overheads;
+          awkwardness += transition.awkwardness;
         }
 
         previous_key = key;
       }
     }
 
-    // turning everything into coefficents against the text length
+    // turning everything into coefficents against the text length in symbols, not bytes
+    let length = text.chars().count() as f64;
+
     Result { 
-      effort: (effort as f64) / (text.len() as f64), 
-      overheads: (overheads as f64) / (text.len() as f64), 
-      awkwardness: (awkwardness as f64) / (text.len() as f64), 
-      rollingness: (rollingness as f64) / (text.len() as f64) 
+      effort: (effort as f64) / length, 
+      overheads: (overheads as f64) / length, 
+      awkwardness: (awkwardness as f64) / length, 
+      rollingness: (rollingness as f64) / length 
+    }
+  }
+
+  /// The same as `run` on the text the n-grams were counted over, and close
+  /// to it for the count tables. The costs add up over the distinct pairs,
+  /// only the rolls get played, over the distinct runs and the order they
+  /// come in, which still goes as long as the text has words, or over the
+  /// longest n-grams a few rounds
+  pub fn aggregate(self: &Self, ngrams: &NGrams) -> Result {
+    let (keys, costs) = self.costs(ngrams);
+
+    let mut effort: usize = ngrams.unigrams.iter().zip(&keys).map(|(count, key)| count * key.effort).sum();
+    let mut overheads: usize = 0;
+    let mut awkwardness: usize = 0;
+    let mut rollingness: usize = 0;
+
+    for cost in costs.iter().flatten() {
+      overheads += cost.overheads;
+      awkwardness += cost.awkwardness;
+      rollingness += cost.rolls;
+    }
+
+    effort += overheads;
+
+    Result {
+      effort: (effort as f64) / (ngrams.length as f64),
+      overheads: (overheads as f64) / (ngrams.length as f64),
+      awkwardness: (awkwardness as f64) / (ngrams.length as f64),
+      rollingness: (rollingness as f64) / (ngrams.length as f64)
+    }
+  }
+
+  /// The effort spent on each key over the text, the overheads go to the key pressed next
+  pub fn efforts(&self, ngrams: &NGrams) -> HashMap<Position, usize> {
+    let (keys, costs) = self.costs(ngrams);
+    let mut efforts = HashMap::new();
+
+    for (count, key) in ngrams.unigrams.iter().zip(&keys).filter(|(count, _)| **count > 0) {
+      *efforts.entry(key.position).or_insert(0) += count * key.effort;
+    }
+
+    for (((_, second), _), cost) in ngrams.bigrams.iter().zip(&costs) {
+      if let Some(cost) = cost {
+        *efforts.entry(keys[*second as usize].position).or_insert(0) += cost.overheads;
+      }
+    }
+
+    efforts
+  }
+
+  // the keys of the n-gram symbols, and what every pair costs all the times it comes up
+  fn costs(&self, ngrams: &NGrams) -> (Vec<&Key>, Vec<Option<Costs>>) {
+    let space_key = self.keyboard.key_for(&' ').unwrap();
+    let keys: Vec<&Key> = ngrams.symbols.iter()
+      .map(|symbol| self.keyboard.key_for(symbol).unwrap_or(space_key))
+      .collect();
+
+    // every pair either leaves the roll as it is, breaks it or flips it, and costs
+    // differently depending on whether the pair before it rolled
+    let transitions: Vec<Option<(Transition, Transition)>> = ngrams.bigrams.iter().map(|((first, second), _)| {
+      let (previous_key, key) = (keys[*first as usize], keys[*second as usize]);
+
+      self.transition(previous_key, key, space_key, false)
+        .map(|fresh| (fresh, self.transition(previous_key, key, space_key, true).unwrap()))
+    })
+    .collect();
+    let rolls_fresh = |index: u32| transitions[index as usize].as_ref().is_some_and(|(fresh, _)| fresh.rolling);
+    // whether the roll is on after the pairs, starting off it on or off,
+    // a pair rolls only when the same hand pair before it didn't
+    let replay = |pairs: &[u32], rolling: bool| pairs.iter().fold(rolling, |rolling, index| {
+      if transitions[*index as usize].is_some() { rolls_fresh(*index) && !rolling } else { rolling }
+    });
+
+    let rolls = match &ngrams.rolls {
+      // the run ends get chained in the text order, then every run plays once per start state
+      Rolls::Chain { runs, order } => {
+        let ends: Vec<(bool, bool)> = runs.iter().map(|run| (replay(run, false), replay(run, true))).collect();
+        let mut starts = vec![(0, 0); runs.len()];
+        let mut rolling = false;
+
+        for run in order {
+          let (start, (off, on)) = (&mut starts[*run as usize], ends[*run as usize]);
+
+          if rolling { start.1 += 1; } else { start.0 += 1; }
+          rolling = if rolling { on } else { off };
+        }
+
+        let mut rolls = vec![0; ngrams.bigrams.len()];
+
+        for (run, (off, on)) in runs.iter().zip(starts) {
+          for (rolling, times) in [(false, off), (true, on)].iter().filter(|(_, times)| *times > 0) {
+            run.iter().fold(*rolling, |rolling, index| {
+              if transitions[*index as usize].is_none() { return rolling; }
+
+              let rolled = rolls_fresh(*index) && !rolling;
+              if rolled { rolls[*index as usize] += times; }
+
+              rolled
+            });
+          }
+        }
+
+        rolls
+      },
+      // the n-grams play from a roll that's on as often as the last round said
+      Rolls::Contexts(contexts) => {
+        let same_hand: usize = ngrams.bigrams.iter().zip(&transitions)
+          .filter(|(_, transition)| transition.is_some())
+          .map(|((_, count), _)| count)
+          .sum();
+
+        let mut rolls: Vec<usize> = vec![];
+        let mut share = 0.0;
+
+        for _ in 0..ROLL_ROUNDS {
+          let mut broken = vec![0.0; ngrams.bigrams.len()];
+
+          for (pairs, count) in contexts {
+            let last = pairs[pairs.len() - 1];
+
+            if rolls_fresh(last) {
+              let (from_off, from_on) = (replay(pairs, false), replay(pairs, true));
+              let rolled = (1.0 - share) * (from_off as usize as f64) + share * (from_on as usize as f64);
+
+              broken[last as usize] += (1.0 - rolled) * *count as f64;
+            }
+          }
+
+          rolls = ngrams.bigrams.iter().zip(&broken).enumerate()
+            .map(|(index, ((_, count), broken))| if rolls_fresh(index as u32) { count.saturating_sub(broken.round() as usize) } else { 0 })
+            .collect();
+          share = rolls.iter().sum::<usize>() as f64 / same_hand.max(1) as f64;
+        }
+
+        rolls
+      }
+    };
+
+    let costs = ngrams.bigrams.iter().zip(&transitions).zip(rolls).map(|(((_, count), transitions), rolls)| {
+      transitions.as_ref().map(|(fresh, after_roll)| {
+        let (rolls, rest) = if fresh.rolling { (rolls, count - rolls) } else { (0, *count) };
+
+        Costs {
+          overheads: fresh.overheads * rolls + after_roll.overheads * rest,
+          awkwardness: fresh.awkwardness * rolls + after_roll.awkwardness * rest,
+          rolls
+        }
+      })
+    })
+    .collect();
+
+    (keys, costs)
+  }
+
+  // the penalties for going from one key to the next with the same hand
+  fn transition(&self, previous_key: &Key, key: &Key, space_key: &Key, rolling: bool) -> Option<Transition> {
+    if 
+      previous_key.hand == key.hand && // same hand
+      key != previous_key &&           // different key
+      key != space_key &&              // not space
+      previous_key != space_key        // not from space
+    {
+      let mut same_hand_penalties = self.same_hand_penalties(previous_key, key);
+      let mut awkwardness_penalty = self.awkward_penalty(previous_key, key);
+
+      // only allowing two rolling symbols in a row to awoid counting the change of direction as a roll
+      let rolling = if rolling { false } else { self.is_rolling_combo(previous_key, key) };
+    
+      if rolling {
+        let rolling_discount = 100 - self.penalties.rolling_discount;
+
+        same_hand_penalties = (same_hand_penalties * rolling_discount) / 100;
+        awkwardness_penalty = (awkwardness_penalty  * rolling_discount) / 100;
+      }
+
+      Some(Transition {
+        overheads: same_hand_penalties + awkwardness_penalty,
+        awkwardness: awkwardness_penalty,
+        rolling
+      })
+    } else {
+      None
     }
   }
 
   fn same_hand_penalties(self: &Self, last_key: &Key, next_key: &Key) -> usize {
-    let mut penalties = CONFIG.penalties.same_hand;
+    let mut penalties = self.penalties.same_hand;
 
     if last_key.finger == next_key.finger {
-      penalties += CONFIG.penalties.same_finger;
+      penalties += self.penalties.same_finger;
     }
     
     match self.row_distance(last_key, next_key) {
-      2 => penalties + CONFIG.penalties.row_skip,
-      1 => penalties + CONFIG.penalties.row_jump,
+      2 => penalties + self.penalties.row_skip,
+      1 => penalties + self.penalties.row_jump,
       _ => penalties
     }
   }
@@ -125,7 +316,7 @@ impl Calculator<'_> {
 
   fn awkward_penalty(self: &Self, last_key: &Key, _next_key: &Key) -> usize {
     if self.bad_starters.contains(&last_key.position) {
-      CONFIG.penalties.bad_starter
+      self.penalties.bad_starter
     } else {
       0
     }
@@ -144,12 +335,22 @@ mod test {
   use crate::layout::QWERTY;
   use crate::geometry::US_PC_KEYBOARD;
 
+  fn penalties() -> PenaltiesConfig {
+    ExternalConfig::default().penalties
+  }
+
   fn run_text(text: &'static str) -> Result {
     let layout = QWERTY.clone();
     let keyboard = Keyboard::from(&layout, &US_PC_KEYBOARD);
-    let calculator = Calculator::from(&keyboard);
-  
-    calculator.run(&text.to_string())
+    let penalties = penalties();
+    let calculator = Calculator::from(&keyboard, &penalties);
+    let result = calculator.run(&text.to_string());
+
+    // the n-grams should add up to the same numbers as the scan
+    let typeable: Vec<char> = text.chars().filter(|symbol| keyboard.key_for(symbol).is_some()).collect();
+    assert_eq!(calculator.aggregate(&NGrams::from(text, &typeable)), result);
+
+    result
   }
 
   #[test]
@@ -164,7 +365,7 @@ mod test {
 
   #[test]
   fn penalises_same_finger_usage() {
-    let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.same_finger + CONFIG.penalties.row_jump;
+    let penalty = penalties().same_hand + penalties().same_finger + penalties().row_jump;
 
     assert_eq!(run_text("fr"), Result {
       effort: ((penalty + 6) as f64) / 2.0,
@@ -186,7 +387,7 @@ mod test {
 
   #[test]
   fn penalises_row_jumps() {
-    let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.row_jump;
+    let penalty = penalties().same_hand + penalties().row_jump;
 
     assert_eq!(run_text("at"), Result {
       effort: ((penalty + 1 + 11) as f64) / 2.0,
@@ -198,7 +399,7 @@ mod test {
 
   #[test]
   fn penalises_row_skips() {
-    let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.row_skip;
+    let penalty = penalties().same_hand + penalties().row_skip;
 
     assert_eq!(run_text("vq"), Result {
       effort: ((penalty + 6 + 6) as f64) / 2.0,
@@ -210,12 +411,12 @@ mod test {
 
   #[test]
   fn penalises_bad_starters() {
-    let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.bad_starter;
+    let penalty = penalties().same_hand + penalties().bad_starter;
 
     assert_eq!(run_text("qw"), Result {
       effort: ((penalty + 6 + 2) as f64) / 2.0,
       overheads: (penalty as f64) / 2.0,
-      awkwardness: (CONFIG.penalties.bad_starter as f64) / 2.0,
+      awkwardness: (penalties().bad_starter as f64) / 2.0,
       rollingness: 0.0
     });
   }
@@ -232,43 +433,43 @@ mod test {
 
   #[test]
   fn adds_extra_penalty_on_bad_starters_and_row_jump() {
-    let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.bad_starter + CONFIG.penalties.row_jump;
+    let penalty = penalties().same_hand + penalties().bad_starter + penalties().row_jump;
 
     assert_eq!(run_text("qs"), Result {
       effort: ((penalty + 6 + 0) as f64) / 2.0,
       overheads: (penalty as f64) / 2.0,
-      awkwardness: (CONFIG.penalties.bad_starter as f64) / 2.0,
+      awkwardness: (penalties().bad_starter as f64) / 2.0,
       rollingness: 0.0
     });
   }
 
   #[test]
   fn adds_extra_penalty_on_bad_starters_and_skip_jump() {
-    let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.bad_starter + CONFIG.penalties.row_skip;
+    let penalty = penalties().same_hand + penalties().bad_starter + penalties().row_skip;
 
     assert_eq!(run_text("qv"), Result {
       effort: ((penalty + 6 + 6) as f64) / 2.0,
       overheads: (penalty as f64) / 2.0,
-      awkwardness: (CONFIG.penalties.bad_starter as f64) / 2.0,
+      awkwardness: (penalties().bad_starter as f64) / 2.0,
       rollingness: 0.0
     });
   }
 
   #[test]
   fn adds_extra_penalty_on_bad_starters_and_same_finger() {
-    let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.bad_starter + CONFIG.penalties.row_skip + CONFIG.penalties.same_finger;
+    let penalty = penalties().same_hand + penalties().bad_starter + penalties().row_skip + penalties().same_finger;
 
     assert_eq!(run_text("qz"), Result {
       effort: ((penalty + 6 + 7) as f64) / 2.0,
       overheads: (penalty as f64) / 2.0,
-      awkwardness: (CONFIG.penalties.bad_starter as f64) / 2.0,
+      awkwardness: (penalties().bad_starter as f64) / 2.0,
       rollingness: 0.0
     });
   }
 
   #[test]
   fn doesnt_penalise_rolling_pairs_for_row_jumps() {
-    // let penalty = CONFIG.penalties.same_hand + CONFIG.penalties.same_hand;
+    // let penalty = penalties().same_hand + penalties().same_hand;
 
     assert_eq!(run_text("wfli"), Result {
       effort: 11.25, 
@@ -276,5 +477,43 @@ mod test {
       awkwardness: 0.0, 
       rollingness: 0.25
     });
-  } 
+  }
+
+  #[test]
+  fn splits_the_effort_by_key() {
+    let layout = QWERTY.clone();
+    let keyboard = Keyboard::from(&layout, &US_PC_KEYBOARD);
+    let penalties = penalties();
+    let calculator = Calculator::from(&keyboard, &penalties);
+    let penalty = penalties.same_hand + penalties.bad_starter;
+
+    let efforts = calculator.efforts(&NGrams::from("qwq", &['q', 'w']));
+
+    assert_eq!(efforts.len(), 2);
+    assert_eq!(efforts[&(1, 1)], 6 + 6 + penalties.same_hand);
+    assert_eq!(efforts[&(1, 2)], 2 + penalty);
+  }
+
+  #[test]
+  fn scores_the_count_tables_like_their_text() {
+    let layout = QWERTY.clone();
+    let keyboard = Keyboard::from(&layout, &US_PC_KEYBOARD);
+    let penalties = penalties();
+    let calculator = Calculator::from(&keyboard, &penalties);
+
+    for text in ["QUwiEOrp", "wfli", "the quick brown fox jumps over the lazy dog"].iter() {
+      let symbols: Vec<char> = text.chars().collect();
+      let mut counts: HashMap<String, usize> = HashMap::new();
+
+      for length in 1..=4 {
+        for ngram in symbols.windows(length) {
+          *counts.entry(ngram.iter().collect()).or_insert(0) += 1;
+        }
+      }
+
+      let counts: Vec<(String, usize)> = counts.into_iter().collect();
+
+      assert_eq!(calculator.aggregate(&NGrams::from_counts(&counts, &symbols)), calculator.run(&text.to_string()));
+    }
+  }
 }
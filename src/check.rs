@@ -0,0 +1,55 @@
+/**
+ * Lints layout files against the configured text and geometry
+ */
+use std::fs;
+use rusty_key::config::Config;
+use rusty_key::layout::*;
+use rusty_key::lint::{self,Problem,Severity};
+
+use crate::compare::layout_files;
+
+pub fn run(paths: &[String], config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+  let filenames = layout_files(paths)?;
+  let mut broken = 0;
+
+  for filename in &filenames {
+    // not `Layout::read`, that one stops at the first broken row
+    let layout = Layout::new(fs::read_to_string(filename)?);
+    let problems = lint::check(&layout, config);
+
+    println!("{}", report(&filename.to_string_lossy(), &problems));
+
+    if problems.iter().any(|problem| problem.severity == Severity::Error) { broken += 1; }
+  }
+
+  if broken > 0 {
+    return Err(format!("{} of {} layout files have errors", broken, filenames.len()).into());
+  }
+
+  Ok(())
+}
+
+pub fn report(filename: &str, problems: &[Problem]) -> String {
+  if problems.is_empty() {
+    return format!("{}: ok", filename);
+  }
+
+  problems.iter().map(|problem| format!("{}: {}", filename, problem)).collect::<Vec<String>>().join("\n")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::evaluate::test::config;
+
+  #[test]
+  fn reports_every_problem() {
+    let config = config();
+
+    assert_eq!(report("colemak", &lint::check(&COLEMAK, &config)), "colemak: ok");
+    assert_eq!(report("broken", &lint::check(&Layout::new("Q W\nq w q".to_string()), &config)).lines().take(2).collect::<Vec<&str>>(), vec![
+      "broken: error: line 1 has 2 shifted symbols, but line 2 has 3 normal ones",
+      "broken: warning: line 2 repeats 'q' from line 2"
+    ]);
+  }
+}
@@ -0,0 +1,105 @@
+/**
+ * Saving and restoring the evolution state, so that long runs
+ * could survive crashes and restarts
+ */
+use std::fs;
+use std::io;
+
+use toml;
+use serde::{Serialize,Deserialize};
+
+use crate::generation::*;
+
+#[derive(Debug,PartialEq,Serialize,Deserialize)]
+pub struct Checkpoint {
+  pub generation: Snapshot,
+  pub history: Vec<Outcome>
+}
+
+impl Checkpoint {
+  pub fn load(filename: &str) -> Result<Checkpoint, Box<dyn std::error::Error>> {
+    let data = fs::read_to_string(filename)?;
+    let checkpoint = toml::from_str(&data)
+      .map_err(|err| format!("{}: {}", filename, err))?;
+
+    Ok(checkpoint)
+  }
+
+  // writes into a temporary file first so a crash mid-way won't corrupt the previous checkpoint
+  pub fn save(&self, filename: &str) -> Result<(), io::Error> {
+    // going through `toml::Value` puts plain values before tables, which toml insists on
+    let data = toml::Value::try_from(self).and_then(|value| toml::to_string(&value))
+      .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let tmp_filename = format!("{}.tmp", filename);
+
+    fs::write(&tmp_filename, data)?;
+    fs::rename(&tmp_filename, filename)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::layout::*;
+  use crate::summary::*;
+
+  fn summary() -> Summary {
+    Summary { effort: 1.5, overheads: 2.5, awkwardness: 3.5, rollingness: 4.5, fitness: 5.5 }
+  }
+
+  fn checkpoint() -> Checkpoint {
+    Checkpoint {
+      generation: Snapshot {
+        number: 3,
+        seed: u64::MAX,
+        successor: Some(COLEMAK.clone()),
+        best: None,
+        members: vec![COLEMAK.clone(), WORKMAN.clone()]
+      },
+      history: vec![Outcome {
+        generation_number: 2,
+        winner: QWERTY.clone(),
+        winner_summary: summary(),
+        best: DVORAK.clone(),
+        best_summary: summary()
+      }]
+    }
+  }
+
+  #[test]
+  fn saves_and_loads_checkpoints() {
+    let filename = std::env::temp_dir().join("rusty-key-checkpoint-test.toml");
+    let filename = filename.to_str().unwrap();
+
+    checkpoint().save(filename).unwrap();
+
+    assert_eq!(Checkpoint::load(filename).unwrap(), checkpoint());
+
+    fs::remove_file(filename).unwrap();
+  }
+
+  #[test]
+  fn saves_empty_history() {
+    let filename = std::env::temp_dir().join("rusty-key-empty-checkpoint-test.toml");
+    let filename = filename.to_str().unwrap();
+    let checkpoint = Checkpoint { history: vec![], ..checkpoint() };
+
+    checkpoint.save(filename).unwrap();
+
+    assert_eq!(Checkpoint::load(filename).unwrap(), checkpoint);
+
+    fs::remove_file(filename).unwrap();
+  }
+
+  #[test]
+  fn fails_on_broken_files() {
+    let filename = std::env::temp_dir().join("rusty-key-broken-checkpoint-test.toml");
+    let filename = filename.to_str().unwrap();
+
+    fs::write(filename, "generation = 1").unwrap();
+
+    assert!(Checkpoint::load(filename).is_err());
+
+    fs::remove_file(filename).unwrap();
+  }
+}
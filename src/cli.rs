@@ -0,0 +1,449 @@
+/**
+ * Command line arguments parsing
+ *
+ *   rusty-key [options]         runs the evolution with the TUI
+ *   rusty-key evaluate <layout> scores a single layout file
+ *   rusty-key compare [paths]   ranks a bunch of layouts against each other
+ *   rusty-key export <format> <layout>
+ *                               writes a layout file out for an OS to use
+ *   rusty-key render <layout>   draws a layout as an SVG heatmap
+ *   rusty-key import <file>     turns an XKB, KLC or KLE file into a layout file,
+ *                               or a KLE file into a geometry file
+ *   rusty-key check [paths]     lints layout files
+ *
+ * `--config <file>` goes with any of them
+ */
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug,PartialEq)]
+pub enum Command {
+  Evolve(Evolving),
+  Evaluate(String),
+  Compare(Comparison),
+  Export(Exporting),
+  Render(Rendering),
+  Import(Importing),
+  Check(Vec<String>)
+}
+
+#[derive(Debug,PartialEq)]
+pub struct Evolving {
+  pub start: Vec<String>,
+  pub resume: Option<String>,
+  pub headless: bool,
+  pub output: Option<String>,
+  pub seed: Option<u64>,
+  pub max_generations: Option<usize>,
+  pub time_limit: Option<u64>,
+  pub stagnation: Option<usize>
+}
+
+#[derive(Debug,PartialEq)]
+pub struct Comparison {
+  pub paths: Vec<String>,
+  pub geometries: Vec<String>,
+  pub baseline: Option<String>,
+  pub sort_by: String
+}
+
+#[derive(Debug,PartialEq)]
+pub struct Exporting {
+  pub format: String,
+  pub path: String
+}
+
+#[derive(Debug,PartialEq)]
+pub struct Rendering {
+  pub path: String,
+  pub tint: String
+}
+
+#[derive(Debug,PartialEq)]
+pub struct Importing {
+  pub path: String,
+  pub format: Option<String>,
+  pub geometry: bool
+}
+
+#[derive(Debug,PartialEq)]
+pub struct Error {
+  pub message: String
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}\n\n{}", self.message, USAGE)
+  }
+}
+
+impl std::error::Error for Error {}
+
+pub const USAGE: &str = "Usage:
+  rusty-key --config <file> ...  use another config file instead of ./config.toml
+  rusty-key [options]          run the evolution
+    --start <layout|random>    layout file to start from, can be repeated to mix several
+    --resume [checkpoint]      continue from a saved checkpoint (the configured one by default)
+    --headless                 no TUI, print every generation outcome as a line of JSON
+    --output <file>            write the headless JSON lines into a file instead of stdout
+    --seed <number>            seed the random numbers to make the run reproducible
+    --max-generations <number> stop after this many generations
+    --time-limit <seconds>     stop after running for this long
+    --stagnation <number>      stop after this many generations without the best score improving
+  rusty-key evaluate <layout>  print the summary and score of a layout file
+  rusty-key compare [options] [paths...]
+                               rank layout files (or directories of them, assets/layouts by default)
+    --geometry <name>          geometry to score on, can be repeated (US-PC, ORTHO or a geometry file)
+    --baseline <layout>        list the per-metric deltas against this layout
+    --sort <column>            effort, overheads, awkwardness, rollingness, fitness or score (default)
+  rusty-key export <format> <layout>
+                               print a layout file in a format an OS can load (xkb, keylayout, klc)
+                               or as a keymap for the ORTHO geometry (qmk, zmk)
+  rusty-key render [options] <layout>
+                               print an SVG picture of the layout on the configured geometry
+    --tint <usage|effort>      tint the keys by how often they're used (default) or their effort
+  rusty-key import [options] <file>
+                               print an XKB symbols, Windows .klc or keyboard-layout-editor JSON file as a layout
+    --format <xkb|klc|kle>     the file format, by default guessed from the file extension
+    --geometry                 print a keyboard-layout-editor JSON file as a geometry, the efforts
+                               go by the configured [distances]
+  rusty-key check [paths...]   list the mistakes in layout files (or directories of them, assets/layouts by default)
+                               and the symbols the text needs but they have no keys for";
+
+pub fn parse(args: &[String]) -> Result<Command, Error> {
+  match args.first().map(String::as_str) {
+    None => parse_evolving(args),
+    Some(flag) if flag.starts_with("--") => parse_evolving(args),
+    Some("evaluate") => match &args[1..] {
+      [path] => Ok(Command::Evaluate(path.to_string())),
+      [] => Err(error("evaluate: missing layout file")),
+      _ => Err(error("evaluate: expects a single layout file"))
+    },
+    Some("compare") => parse_comparison(&args[1..]),
+    Some("export") => match &args[1..] {
+      [format, path] => Ok(Command::Export(Exporting { format: format.to_string(), path: path.to_string() })),
+      _ => Err(error("export: expects a format and a layout file"))
+    },
+    Some("render") => parse_rendering(&args[1..]),
+    Some("import") => parse_importing(&args[1..]),
+    Some("check") => match &args[1..] {
+      [] => Ok(Command::Check(vec!["assets/layouts".to_string()])),
+      paths => match paths.iter().find(|path| path.starts_with("--")) {
+        Some(flag) => Err(error(&format!("check: unknown option '{}'", flag))),
+        None => Ok(Command::Check(paths.to_vec()))
+      }
+    },
+    Some(other) => Err(error(&format!("unknown command '{}'", other)))
+  }
+}
+
+// pulls the `--config <file>` option out, as it goes with any command
+pub fn take_config(args: &[String]) -> Result<(Option<String>, Vec<String>), Error> {
+  let mut config = None;
+  let mut rest = vec![];
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    if arg == "--config" {
+      config = Some(value_for(arg, args.next())?);
+    } else {
+      rest.push(arg.to_string());
+    }
+  }
+
+  Ok((config, rest))
+}
+
+fn parse_evolving(args: &[String]) -> Result<Command, Error> {
+  let mut evolving = Evolving {
+    start: vec![], resume: None, headless: false, output: None, seed: None,
+    max_generations: None, time_limit: None, stagnation: None
+  };
+  let mut args = args.iter().peekable();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--resume" => {
+        let path = args.next_if(|value| !value.starts_with("--"));
+        evolving.resume = Some(path.cloned().unwrap_or_default());
+      },
+      "--start" => evolving.start.push(value_for(arg, args.next())?),
+      "--headless" => evolving.headless = true,
+      "--output" => evolving.output = Some(value_for(arg, args.next())?),
+      "--seed" => evolving.seed = Some(number_for(arg, args.next())?),
+      "--max-generations" => evolving.max_generations = Some(number_for(arg, args.next())?),
+      "--time-limit" => evolving.time_limit = Some(number_for(arg, args.next())?),
+      "--stagnation" => evolving.stagnation = Some(number_for(arg, args.next())?),
+      other => return Err(error(&format!("unknown option '{}'", other)))
+    }
+  }
+
+  if evolving.output.is_some() && !evolving.headless {
+    return Err(error("--output works only with --headless"));
+  }
+
+  if evolving.resume.is_some() && !evolving.start.is_empty() {
+    return Err(error("--start cannot be used with --resume"));
+  }
+
+  if evolving.resume.is_some() && evolving.seed.is_some() {
+    return Err(error("--seed cannot be used with --resume"));
+  }
+
+  Ok(Command::Evolve(evolving))
+}
+
+fn parse_comparison(args: &[String]) -> Result<Command, Error> {
+  let mut comparison = Comparison {
+    paths: vec![],
+    geometries: vec![],
+    baseline: None,
+    sort_by: "score".to_string()
+  };
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--geometry" => comparison.geometries.push(value_for(arg, args.next())?),
+      "--baseline" => comparison.baseline = Some(value_for(arg, args.next())?),
+      "--sort" => comparison.sort_by = value_for(arg, args.next())?,
+      flag if flag.starts_with("--") => return Err(error(&format!("compare: unknown option '{}'", flag))),
+      path => comparison.paths.push(path.to_string())
+    }
+  }
+
+  if comparison.paths.is_empty() {
+    comparison.paths.push("assets/layouts".to_string());
+  }
+
+  Ok(Command::Compare(comparison))
+}
+
+fn parse_rendering(args: &[String]) -> Result<Command, Error> {
+  let mut tint = "usage".to_string();
+  let mut paths = vec![];
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--tint" => tint = value_for(arg, args.next())?,
+      flag if flag.starts_with("--") => return Err(error(&format!("render: unknown option '{}'", flag))),
+      path => paths.push(path.to_string())
+    }
+  }
+
+  match paths.as_slice() {
+    [path] => Ok(Command::Render(Rendering { path: path.to_string(), tint })),
+    [] => Err(error("render: missing layout file")),
+    _ => Err(error("render: expects a single layout file"))
+  }
+}
+
+fn parse_importing(args: &[String]) -> Result<Command, Error> {
+  let mut format = None;
+  let mut geometry = false;
+  let mut paths = vec![];
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--format" => format = Some(value_for(arg, args.next())?),
+      "--geometry" => geometry = true,
+      flag if flag.starts_with("--") => return Err(error(&format!("import: unknown option '{}'", flag))),
+      path => paths.push(path.to_string())
+    }
+  }
+
+  match paths.as_slice() {
+    [path] => Ok(Command::Import(Importing { path: path.to_string(), format, geometry })),
+    [] => Err(error("import: missing file")),
+    _ => Err(error("import: expects a single file"))
+  }
+}
+
+fn value_for(flag: &str, value: Option<&String>) -> Result<String, Error> {
+  match value {
+    Some(value) if !value.starts_with("--") => Ok(value.to_string()),
+    _ => Err(error(&format!("{} expects a value", flag)))
+  }
+}
+
+fn number_for<T: FromStr>(flag: &str, value: Option<&String>) -> Result<T, Error> {
+  let value = value_for(flag, value)?;
+  value.parse().map_err(|_| error(&format!("{} expects a number, got '{}'", flag, value)))
+}
+
+fn error(message: &str) -> Error {
+  Error { message: message.to_string() }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+  }
+
+  fn evolving() -> Evolving {
+    Evolving {
+      start: vec![], resume: None, headless: false, output: None, seed: None,
+      max_generations: None, time_limit: None, stagnation: None
+    }
+  }
+
+  #[test]
+  fn evolves_by_default() {
+    assert_eq!(parse(&args(&[])), Ok(Command::Evolve(evolving())));
+  }
+
+  #[test]
+  fn parses_resume() {
+    assert_eq!(parse(&args(&["--resume", "saved.toml"])), Ok(Command::Evolve(Evolving { resume: Some("saved.toml".to_string()), ..evolving() })));
+    assert_eq!(parse(&args(&["--resume"])), Ok(Command::Evolve(Evolving { resume: Some("".to_string()), ..evolving() })));
+    assert_eq!(parse(&args(&["--blah"])), Err(error("unknown option '--blah'")));
+  }
+
+  #[test]
+  fn parses_headless() {
+    assert_eq!(parse(&args(&["--headless"])), Ok(Command::Evolve(Evolving { headless: true, ..evolving() })));
+    assert_eq!(parse(&args(&["--headless", "--output", "out.jsonl", "--resume"])), Ok(Command::Evolve(Evolving {
+      headless: true,
+      output: Some("out.jsonl".to_string()),
+      resume: Some("".to_string()),
+      ..evolving()
+    })));
+    assert_eq!(parse(&args(&["--output", "out.jsonl"])), Err(error("--output works only with --headless")));
+  }
+
+  #[test]
+  fn parses_start() {
+    assert_eq!(parse(&args(&["--start", "a", "--start", "random"])), Ok(Command::Evolve(Evolving {
+      start: vec!["a".to_string(), "random".to_string()],
+      ..evolving()
+    })));
+    assert_eq!(parse(&args(&["--start"])), Err(error("--start expects a value")));
+    assert_eq!(parse(&args(&["--start", "a", "--resume"])), Err(error("--start cannot be used with --resume")));
+  }
+
+  #[test]
+  fn parses_seed() {
+    assert_eq!(parse(&args(&["--seed", "42"])), Ok(Command::Evolve(Evolving { seed: Some(42), ..evolving() })));
+    assert_eq!(parse(&args(&["--seed", "blah"])), Err(error("--seed expects a number, got 'blah'")));
+    assert_eq!(parse(&args(&["--seed", "42", "--resume"])), Err(error("--seed cannot be used with --resume")));
+  }
+
+  #[test]
+  fn parses_stopping_criteria() {
+    assert_eq!(parse(&args(&["--max-generations", "100", "--time-limit", "60", "--stagnation", "20", "--resume"])), Ok(Command::Evolve(Evolving {
+      max_generations: Some(100),
+      time_limit: Some(60),
+      stagnation: Some(20),
+      resume: Some("".to_string()),
+      ..evolving()
+    })));
+    assert_eq!(parse(&args(&["--time-limit", "-1"])), Err(error("--time-limit expects a number, got '-1'")));
+    assert_eq!(parse(&args(&["--stagnation"])), Err(error("--stagnation expects a value")));
+  }
+
+  #[test]
+  fn parses_evaluate() {
+    assert_eq!(parse(&args(&["evaluate", "assets/layouts/colemak"])), Ok(Command::Evaluate("assets/layouts/colemak".to_string())));
+    assert_eq!(parse(&args(&["evaluate"])), Err(error("evaluate: missing layout file")));
+    assert_eq!(parse(&args(&["evaluate", "a", "b"])), Err(error("evaluate: expects a single layout file")));
+  }
+
+  #[test]
+  fn parses_compare_with_defaults() {
+    assert_eq!(parse(&args(&["compare"])), Ok(Command::Compare(Comparison {
+      paths: vec!["assets/layouts".to_string()],
+      geometries: vec![],
+      baseline: None,
+      sort_by: "score".to_string()
+    })));
+  }
+
+  #[test]
+  fn parses_compare_options() {
+    let command = parse(&args(&[
+      "compare", "--geometry", "US-PC", "a", "--geometry", "ORTHO", "--baseline", "qwerty", "--sort", "effort", "b"
+    ]));
+
+    assert_eq!(command, Ok(Command::Compare(Comparison {
+      paths: vec!["a".to_string(), "b".to_string()],
+      geometries: vec!["US-PC".to_string(), "ORTHO".to_string()],
+      baseline: Some("qwerty".to_string()),
+      sort_by: "effort".to_string()
+    })));
+  }
+
+  #[test]
+  fn fails_on_bad_compare_options() {
+    assert_eq!(parse(&args(&["compare", "--sort"])), Err(error("--sort expects a value")));
+    assert_eq!(parse(&args(&["compare", "--geometry", "--sort", "score"])), Err(error("--geometry expects a value")));
+    assert_eq!(parse(&args(&["compare", "--blah"])), Err(error("compare: unknown option '--blah'")));
+  }
+
+  #[test]
+  fn parses_export() {
+    assert_eq!(parse(&args(&["export", "xkb", "assets/layouts/colemak"])), Ok(Command::Export(Exporting {
+      format: "xkb".to_string(),
+      path: "assets/layouts/colemak".to_string()
+    })));
+    assert_eq!(parse(&args(&["export", "xkb"])), Err(error("export: expects a format and a layout file")));
+  }
+
+  #[test]
+  fn parses_render() {
+    assert_eq!(parse(&args(&["render", "colemak"])), Ok(Command::Render(Rendering {
+      path: "colemak".to_string(),
+      tint: "usage".to_string()
+    })));
+    assert_eq!(parse(&args(&["render", "--tint", "effort", "colemak"])), Ok(Command::Render(Rendering {
+      path: "colemak".to_string(),
+      tint: "effort".to_string()
+    })));
+    assert_eq!(parse(&args(&["render"])), Err(error("render: missing layout file")));
+    assert_eq!(parse(&args(&["render", "--blah", "colemak"])), Err(error("render: unknown option '--blah'")));
+  }
+
+  #[test]
+  fn parses_import() {
+    assert_eq!(parse(&args(&["import", "colemak.klc"])), Ok(Command::Import(Importing {
+      path: "colemak.klc".to_string(),
+      format: None,
+      geometry: false
+    })));
+    assert_eq!(parse(&args(&["import", "--format", "xkb", "colemak"])), Ok(Command::Import(Importing {
+      path: "colemak".to_string(),
+      format: Some("xkb".to_string()),
+      geometry: false
+    })));
+    assert_eq!(parse(&args(&["import", "--geometry", "ergo.json"])), Ok(Command::Import(Importing {
+      path: "ergo.json".to_string(),
+      format: None,
+      geometry: true
+    })));
+    assert_eq!(parse(&args(&["import"])), Err(error("import: missing file")));
+    assert_eq!(parse(&args(&["import", "a", "b"])), Err(error("import: expects a single file")));
+  }
+
+  #[test]
+  fn parses_check() {
+    assert_eq!(parse(&args(&["check", "a", "b"])), Ok(Command::Check(args(&["a", "b"]))));
+    assert_eq!(parse(&args(&["check"])), Ok(Command::Check(args(&["assets/layouts"]))));
+    assert_eq!(parse(&args(&["check", "--blah"])), Err(error("check: unknown option '--blah'")));
+  }
+
+  #[test]
+  fn takes_the_config_out() {
+    assert_eq!(take_config(&args(&["compare", "--config", "my.toml", "a"])), Ok((Some("my.toml".to_string()), args(&["compare", "a"]))));
+    assert_eq!(take_config(&args(&["--headless"])), Ok((None, args(&["--headless"]))));
+    assert_eq!(take_config(&args(&["--config"])), Err(error("--config expects a value")));
+  }
+
+  #[test]
+  fn fails_on_unknown_commands() {
+    assert_eq!(parse(&args(&["blah"])), Err(error("unknown command 'blah'")));
+  }
+}
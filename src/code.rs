@@ -0,0 +1,203 @@
+/**
+ * Reads a source code tree as a corpus, so the layouts get scored against
+ * the brackets, underscores and operators people actually type. The license
+ * headers, the generated and minified files are mostly never typed, so they
+ * get dropped, and so can the indentation editors put in on their own
+ */
+use std::io;
+use std::fs;
+use std::path::{Path,PathBuf};
+
+use crate::config::CodeConfig;
+
+// the lines the generated files get marked in
+const MARKER_LINES: usize = 5;
+const GENERATED_MARKERS: [&str; 5] = [
+  "@generated", "do not edit", "<auto-generated>", "this file is auto-generated", "this file was automatically generated"
+];
+const LICENSE_MARKERS: [&str; 3] = ["license", "copyright", "spdx-license-identifier"];
+// minified files come in a few very long lines
+const MINIFIED_LINE_LENGTH: usize = 200;
+
+/// All the files under the path one after another, the same way `source::read` joins them
+pub fn read(path: &str, config: &CodeConfig) -> Result<String, io::Error> {
+  let mut filenames = vec![];
+  walk(Path::new(path), config, &mut filenames)?;
+
+  let mut contents = vec![];
+
+  for filename in filenames {
+    // the binary files in the tree aren't code either
+    let content = match fs::read_to_string(&filename) {
+      Ok(content) => content,
+      Err(err) if err.kind() == io::ErrorKind::InvalidData => continue,
+      Err(err) => return Err(err)
+    };
+
+    if config.drop_generated && (is_generated(&content) || is_minified(&filename, &content)) { continue; }
+
+    let content = if config.drop_license_headers { drop_license_header(&content) } else { content };
+    let content = if config.collapse_indentation { collapse_indentation(&content) } else { content };
+
+    contents.push(content);
+  }
+
+  Ok(contents.join("\n\n"))
+}
+
+// the files with the extensions, the hidden and the skipped directories are left
+// out, and the symlinked directories aren't followed so they can't loop back
+fn walk(path: &Path, config: &CodeConfig, filenames: &mut Vec<PathBuf>) -> Result<(), io::Error> {
+  if !path.is_dir() {
+    filenames.push(path.to_path_buf());
+    return Ok(());
+  }
+
+  let mut entries = vec![];
+
+  for entry in fs::read_dir(path)? {
+    entries.push(entry?.path());
+  }
+
+  entries.sort();
+
+  for entry in entries {
+    let name = entry.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = entry.extension().map(|extension| extension.to_string_lossy().to_string()).unwrap_or_default();
+
+    let file_type = fs::symlink_metadata(&entry)?.file_type();
+
+    if name.starts_with('.') { continue; }
+
+    if file_type.is_dir() {
+      if !config.skip_directories.contains(&name) { walk(&entry, config, filenames)?; }
+    } else if file_type.is_symlink() && entry.is_dir() {
+      continue;
+    } else if config.extensions.is_empty() || config.extensions.contains(&extension) {
+      filenames.push(entry);
+    }
+  }
+
+  Ok(())
+}
+
+fn is_generated(content: &str) -> bool {
+  content.lines().take(MARKER_LINES).any(|line| {
+    let line = line.to_lowercase();
+    GENERATED_MARKERS.iter().any(|marker| line.contains(marker))
+  })
+}
+
+fn is_minified(filename: &Path, content: &str) -> bool {
+  let name = filename.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+  let lines = content.lines().count().max(1);
+
+  name.contains(".min.") || content.chars().count() / lines > MINIFIED_LINE_LENGTH
+}
+
+/// Drops the comment at the top of the file when it's a license one, the
+/// `/* */` and `<!-- -->` blocks, or the lines of `//`, `#`, `--` and `;` comments
+pub fn drop_license_header(content: &str) -> String {
+  let lines: Vec<&str> = content.lines().collect();
+  let start = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(lines.len());
+  let first = lines.get(start).map(|line| line.trim()).unwrap_or("");
+
+  let block = [("/*", "*/"), ("<!--", "-->"), ("(*", "*)")].iter().find(|(opening, _)| first.starts_with(opening));
+  let length = match block {
+    Some((_, closing)) => lines[start..].iter().position(|line| line.contains(closing)).map(|end| end + 1),
+    None => ["//", "#", "--", ";"].iter()
+      .find(|prefix| first.starts_with(*prefix) && !first.starts_with("#!") && !first.starts_with("#["))
+      .map(|prefix| lines[start..].iter().take_while(|line| line.trim().starts_with(prefix)).count())
+  };
+
+  let end = match length {
+    Some(length) if is_license(&lines[start..start + length]) => start + length,
+    _ => return content.to_string()
+  };
+
+  let rest = lines[end..].iter().skip_while(|line| line.trim().is_empty()).copied().collect::<Vec<&str>>();
+
+  lines[..start].iter().chain(rest.iter()).copied().collect::<Vec<&str>>().join("\n")
+}
+
+fn is_license(lines: &[&str]) -> bool {
+  lines.iter().any(|line| {
+    let line = line.to_lowercase();
+    LICENSE_MARKERS.iter().any(|marker| line.contains(marker))
+  })
+}
+
+/// Takes the leading spaces and tabs off every line
+pub fn collapse_indentation(content: &str) -> String {
+  content.lines().map(|line| line.trim_start_matches([' ', '\t'])).collect::<Vec<&str>>().join("\n")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn config() -> CodeConfig {
+    CodeConfig { extensions: vec!["rs".to_string()], drop_license_headers: true, drop_generated: true, collapse_indentation: false,
+      skip_directories: vec!["target".to_string()]
+    }
+  }
+
+  #[test]
+  fn drops_license_headers() {
+    let block = "/*\n * Copyright 2020 Someone\n * MIT license\n */\n\nfn main() {}";
+    let lines = "#!/bin/sh\n# SPDX-License-Identifier: MIT\n# more of it\necho hi";
+    let comment = "// the entry point\nfn main() {}";
+
+    assert_eq!(drop_license_header(block), "fn main() {}");
+    assert_eq!(drop_license_header(lines), lines);
+    assert_eq!(drop_license_header(&lines[10..]), "echo hi");
+    assert_eq!(drop_license_header(comment), comment);
+  }
+
+  #[test]
+  fn collapses_the_indentation() {
+    assert_eq!(collapse_indentation("fn main() {\n    if x {\n\t\ty();\n    }\n}"), "fn main() {\nif x {\ny();\n}\n}");
+  }
+
+  #[test]
+  fn finds_generated_and_minified_files() {
+    assert!(is_generated("// @generated by a tool\nfn main() {}"));
+    assert!(is_generated("# Code generated by protoc. DO NOT EDIT.\n"));
+    assert!(is_generated("// <auto-generated>\n//   This code was generated by a tool.\n"));
+    assert!(!is_generated("fn main() {}\n"));
+    assert!(!is_generated("// the ids get generated by the database\nfn main() {}\n"));
+
+    assert!(is_minified(Path::new("app.min.js"), "var a;"));
+    assert!(is_minified(Path::new("app.js"), &"var a=1;".repeat(100)));
+    assert!(!is_minified(Path::new("app.js"), "var a = 1;\nvar b = 2;\n"));
+  }
+
+  #[test]
+  fn walks_the_tree() {
+    let directory = std::env::temp_dir().join("rusty-key-code-test");
+    fs::remove_dir_all(&directory).ok();
+    fs::create_dir_all(directory.join("src/nested")).unwrap();
+    fs::create_dir_all(directory.join(".git")).unwrap();
+    fs::create_dir_all(directory.join("target")).unwrap();
+    fs::write(directory.join("src/main.rs"), "// MIT license\n\nfn main() {\n  run();\n}").unwrap();
+    fs::write(directory.join("src/nested/lib.rs"), "pub fn run() {}").unwrap();
+    fs::write(directory.join("src/generated.rs"), "// @generated\npub const X: u8 = 1;").unwrap();
+    fs::write(directory.join("src/notes.md"), "# notes").unwrap();
+    fs::write(directory.join(".git/hook.rs"), "fn hook() {}").unwrap();
+    fs::write(directory.join("src/data.rs"), [0xff, 0xfe, 0x00]).unwrap();
+    fs::write(directory.join("target/build.rs"), "fn build() {}").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&directory, directory.join("src/nested/loop")).unwrap();
+
+    let path = directory.to_str().unwrap();
+
+    assert_eq!(read(path, &config()).unwrap(), "fn main() {\n  run();\n}\n\npub fn run() {}");
+    assert_eq!(
+      read(path, &CodeConfig { collapse_indentation: true, drop_license_headers: false, ..config() }).unwrap(),
+      "// MIT license\n\nfn main() {\nrun();\n}\n\npub fn run() {}"
+    );
+    assert!(read(path, &CodeConfig { extensions: vec![], ..config() }).unwrap().contains("# notes"));
+
+    fs::remove_dir_all(directory).unwrap();
+  }
+}
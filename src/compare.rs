@@ -0,0 +1,336 @@
+/**
+ * Ranks a bunch of layout files against each other on one or more geometries
+ */
+use std::fs;
+use std::io;
+use std::path::{Path,PathBuf};
+use rayon::prelude::*;
+use core::cmp::Ordering::Equal;
+
+use rusty_key::config::Config;
+use rusty_key::geometry::*;
+use rusty_key::layout::*;
+use rusty_key::keyboard::*;
+use rusty_key::summary::*;
+
+use crate::cli::Comparison;
+use crate::evaluate::warn_about;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Column {
+  Effort,
+  Overheads,
+  Awkwardness,
+  Rollingness,
+  Fitness,
+  Score
+}
+
+const COLUMNS: [Column; 6] = [
+  Column::Effort, Column::Overheads, Column::Awkwardness, Column::Rollingness, Column::Fitness, Column::Score
+];
+
+#[derive(Debug,PartialEq)]
+pub struct Row {
+  pub name: String,
+  pub summary: Summary,
+  pub score: f64
+}
+
+impl Column {
+  pub fn parse(name: &str) -> Option<Column> {
+    COLUMNS.iter().find(|column| column.title() == name).copied()
+  }
+
+  pub fn title(&self) -> &'static str {
+    match self {
+      Column::Effort => "effort",
+      Column::Overheads => "overheads",
+      Column::Awkwardness => "awkwardness",
+      Column::Rollingness => "rollingness",
+      Column::Fitness => "fitness",
+      Column::Score => "score"
+    }
+  }
+
+  pub fn value(&self, row: &Row) -> f64 {
+    match self {
+      Column::Effort => row.summary.effort,
+      Column::Overheads => row.summary.overheads,
+      Column::Awkwardness => row.summary.awkwardness,
+      Column::Rollingness => row.summary.rollingness,
+      Column::Fitness => row.summary.fitness,
+      Column::Score => row.score
+    }
+  }
+
+  // effort, overheads and awkwardness are penalties, the less the better
+  fn higher_is_better(&self) -> bool {
+    matches!(self, Column::Rollingness | Column::Fitness | Column::Score)
+  }
+}
+
+pub fn run(comparison: &Comparison, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+  let sort_by = Column::parse(&comparison.sort_by)
+    .ok_or_else(|| format!("unknown sort column '{}'", comparison.sort_by))?;
+  let geometries = geometries_for(&comparison.geometries, config)?;
+  let layouts = load_layouts(&comparison.paths)?;
+
+  for (name, layout) in &layouts {
+    warn_about(name, layout, config);
+  }
+
+  for (name, geometry) in &geometries {
+    // the configured geometry got its warnings above already
+    for (layout_name, layout) in layouts.iter().filter(|_| *geometry != config.geometry) {
+      if let Some(warning) = shape_warning(layout, geometry) {
+        eprintln!("{}: warning: {} on {}", layout_name, warning, name);
+      }
+    }
+
+    let rows = rank(&layouts, geometry, sort_by, config);
+
+    println!("{}\n\n{}\n", name, table(&rows));
+
+    if let Some(baseline) = &comparison.baseline {
+      let base = find_baseline(&layouts, baseline).and_then(|label| rows.iter().find(|row| row.name == label))
+        .ok_or_else(|| format!("baseline layout '{}' is not in the list", baseline))?;
+
+      println!("against {}\n\n{}\n", baseline, deltas(&rows, base));
+    }
+  }
+
+  Ok(())
+}
+
+pub fn rank(layouts: &[(String, Layout)], geometry: &Geometry, sort_by: Column, config: &Config) -> Vec<Row> {
+  let mut rows: Vec<Row> = layouts.par_iter()
+    .map(|(name, layout)| {
+      let keyboard = Keyboard::from(layout, geometry);
+      let summary = Summary::calculate(&keyboard, config);
+      let score = summary.score(&config.weights);
+
+      Row { name: label_for(name, layout), summary, score }
+    })
+    .collect();
+
+  rows.sort_by(|a, b| {
+    let ordering = sort_by.value(a).partial_cmp(&sort_by.value(b)).unwrap_or(Equal);
+    if sort_by.higher_is_better() { ordering.reverse() } else { ordering }
+  });
+
+  rows
+}
+
+pub fn table(rows: &[Row]) -> String {
+  let width = name_width(rows);
+  let mut lines = vec![format!("{:>3}  {:<width$}{}", "#", "layout", headers(), width = width)];
+
+  for (i, row) in rows.iter().enumerate() {
+    let values = COLUMNS.iter()
+      .map(|column| format!("{:>13.3}", column.value(row)))
+      .collect::<String>();
+
+    lines.push(format!("{:>3}  {:<width$}{}", i + 1, row.name, values, width = width));
+  }
+
+  lines.join("\n")
+}
+
+pub fn deltas(rows: &[Row], baseline: &Row) -> String {
+  let width = name_width(rows);
+  let mut lines = vec![format!("     {:<width$}{}", "layout", headers(), width = width)];
+
+  for row in rows.iter().filter(|row| row.name != baseline.name) {
+    let values = COLUMNS.iter()
+      .map(|column| format!("{:>+13.3}", column.value(row) - column.value(baseline)))
+      .collect::<String>();
+
+    lines.push(format!("     {:<width$}{}", row.name, values, width = width));
+  }
+
+  lines.join("\n")
+}
+
+// the header name if the layout file has one, the file name otherwise
+fn label_for(name: &str, layout: &Layout) -> String {
+  layout.metadata.name.clone().unwrap_or_else(|| name.to_string())
+}
+
+// the baseline can be given by its file name or by its header name
+fn find_baseline(layouts: &[(String, Layout)], baseline: &str) -> Option<String> {
+  layouts.iter()
+    .find(|(name, layout)| name == baseline || layout.metadata.name.as_deref() == Some(baseline))
+    .map(|(name, layout)| label_for(name, layout))
+}
+
+fn headers() -> String {
+  COLUMNS.iter().map(|column| format!("{:>13}", column.title())).collect()
+}
+
+fn name_width(rows: &[Row]) -> usize {
+  rows.iter().map(|row| row.name.chars().count()).max().unwrap_or(0).max(6) + 2
+}
+
+// the built-in geometries go by their names, the others by their files
+fn geometries_for(names: &[String], config: &Config) -> Result<Vec<(String, Geometry)>, String> {
+  if names.is_empty() {
+    let name = NAMES.iter().find(|name| Geometry::by_name(name) == Some(&config.geometry))
+      .unwrap_or(&"custom");
+
+    return Ok(vec![(name.to_string(), config.geometry.clone())]);
+  }
+
+  names.iter().map(|name| Geometry::find(name).map(|geometry| (name.to_string(), geometry))).collect()
+}
+
+// loads layout files, directories are expanded into the files they contain
+pub fn load_layouts(paths: &[String]) -> Result<Vec<(String, Layout)>, io::Error> {
+  layout_files(paths)?.iter().map(|filename| {
+    let name = filename.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let layout = Layout::read(&filename.to_string_lossy())
+      .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", filename.display(), err)))?;
+
+    Ok((name, layout))
+  })
+  .collect()
+}
+
+// the keys that don't fit the geometry get left out of the scores
+fn shape_warning(layout: &Layout, geometry: &Geometry) -> Option<String> {
+  let (shape, expected) = (layout.shape(), geometry.shape());
+
+  if shape == expected {
+    return None;
+  }
+
+  Some(format!("the rows have {:?} symbols, but the geometry has {:?} keys", shape, expected))
+}
+
+/// The layout files on the paths, with the directories listed in order
+pub fn layout_files(paths: &[String]) -> Result<Vec<PathBuf>, io::Error> {
+  let mut filenames = vec![];
+
+  for path in paths {
+    if Path::new(path).is_dir() {
+      let mut files = vec![];
+
+      for entry in fs::read_dir(path)? {
+        let file = entry?.path();
+        if file.is_file() { files.push(file); }
+      }
+
+      files.sort();
+      filenames.append(&mut files);
+    } else {
+      filenames.push(Path::new(path).to_path_buf());
+    }
+  }
+
+  Ok(filenames)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::evaluate::test::config;
+
+  fn summary(effort: f64, fitness: f64) -> Summary {
+    Summary { effort, overheads: 1.0, awkwardness: 1.0, rollingness: 1.0, fitness }
+  }
+
+  #[test]
+  fn parses_columns() {
+    assert_eq!(Column::parse("effort"), Some(Column::Effort));
+    assert_eq!(Column::parse("score"), Some(Column::Score));
+    assert_eq!(Column::parse("blah"), None);
+  }
+
+  #[test]
+  fn loads_layouts_from_directories_and_files() {
+    let layouts = load_layouts(&["assets/layouts".to_string(), "assets/layouts/qwerty".to_string()]).unwrap();
+    let names: Vec<&str> = layouts.iter().map(|(name, _)| name.as_str()).collect();
+
+    assert_eq!(names, vec!["beakl_15", "colemak", "dvorak", "halmak_21", "qwerty", "the_1", "workman", "qwerty"]);
+    assert_eq!(layouts[1].1, COLEMAK.clone());
+  }
+
+  #[test]
+  fn ranks_layouts() {
+    let layouts = vec![
+      ("qwerty".to_string(), QWERTY.clone()),
+      ("colemak".to_string(), COLEMAK.clone()),
+      ("workman".to_string(), WORKMAN.clone())
+    ];
+
+    let config = config();
+
+    let by_score = rank(&layouts, &US_PC_KEYBOARD, Column::Score, &config);
+    let scores: Vec<f64> = by_score.iter().map(|row| row.score).collect();
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    let by_effort = rank(&layouts, &US_PC_KEYBOARD, Column::Effort, &config);
+    let efforts: Vec<f64> = by_effort.iter().map(|row| row.summary.effort).collect();
+    assert!(efforts.windows(2).all(|pair| pair[0] <= pair[1]));
+  }
+
+  #[test]
+  fn labels_layouts_by_their_header_names() {
+    let layouts = vec![
+      ("qwerty".to_string(), QWERTY.clone()),
+      ("colemak_dh".to_string(), Layout::new(format!("// name: Colemak-DH\n{}", COLEMAK.template)))
+    ];
+
+    let config = config();
+    let mut names: Vec<String> = rank(&layouts, &US_PC_KEYBOARD, Column::Score, &config).into_iter().map(|row| row.name).collect();
+    names.sort();
+
+    assert_eq!(names, vec!["Colemak-DH", "qwerty"]);
+    assert_eq!(find_baseline(&layouts, "colemak_dh"), Some("Colemak-DH".to_string()));
+    assert_eq!(find_baseline(&layouts, "Colemak-DH"), Some("Colemak-DH".to_string()));
+    assert_eq!(find_baseline(&layouts, "qwerty"), Some("qwerty".to_string()));
+    assert_eq!(find_baseline(&layouts, "blah"), None);
+  }
+
+  #[test]
+  fn warns_about_shape_mismatches() {
+    let small = QWERTY.reshaped(&[10, 10, 10]).unwrap();
+
+    assert_eq!(shape_warning(&COLEMAK, &US_PC_KEYBOARD), None);
+    assert_eq!(
+      shape_warning(&small, &US_PC_KEYBOARD),
+      Some("the rows have [10, 10, 10] symbols, but the geometry has [13, 13, 11, 10] keys".to_string())
+    );
+  }
+
+  #[test]
+  fn finds_the_geometries() {
+    let names = vec!["ORTHO".to_string(), "assets/geometries/us-pc.toml".to_string()];
+    let geometries = geometries_for(&names, &config()).unwrap();
+
+    assert_eq!(geometries[0], ("ORTHO".to_string(), FULL_ORTHO.clone()));
+    assert_eq!(geometries[1].0, "assets/geometries/us-pc.toml");
+    assert_eq!(geometries[1].1.shape(), US_PC_KEYBOARD.shape());
+    assert_eq!(geometries_for(&[], &config()).unwrap(), vec![("US-PC".to_string(), US_PC_KEYBOARD.clone())]);
+    assert!(geometries_for(&["blah".to_string()], &config()).is_err());
+  }
+
+  #[test]
+  fn prints_tables() {
+    let rows = vec![
+      Row { name: "colemak".to_string(), summary: summary(1.5, 2.0), score: 3.0 },
+      Row { name: "qwerty".to_string(), summary: summary(2.0, 1.0), score: 2.0 }
+    ];
+
+    assert_eq!(table(&rows), [
+      "  #  layout          effort    overheads  awkwardness  rollingness      fitness        score",
+      "  1  colemak          1.500        1.000        1.000        1.000        2.000        3.000",
+      "  2  qwerty           2.000        1.000        1.000        1.000        1.000        2.000"
+    ].join("\n"));
+
+    assert_eq!(deltas(&rows, &rows[1]), [
+      "     layout          effort    overheads  awkwardness  rollingness      fitness        score",
+      "     colemak         -0.500       +0.000       +0.000       +0.000       +1.000       +1.000"
+    ].join("\n"));
+  }
+}
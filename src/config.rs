@@ -1,37 +1,65 @@
+/**
+ * The settings everything runs with: the config file values, plus the
+ * geometry, the preserved positions and the corpora to score layouts against
+ */
 use std::fs;
+use std::path::Path;
 
 use toml;
 use serde::Deserialize;
-use once_cell::sync::Lazy;
 
-use crate::source;
+use crate::source::{self,Corpus,Text};
+use crate::code;
 use crate::geometry::*;
 use crate::preservative::*;
+use crate::symbols::Classes;
 
-pub static CONFIG: Lazy<Config> = Lazy::new(||{ Config::defaults() });
+pub const DEFAULT_FILE: &str = "./config.toml";
+// the template of the keys that stay put
+const PRESERVE_FILE: &str = "./preserve.txt";
 
-pub struct Config<'a> {
-  pub geometry: &'a Geometry,
+pub struct Config {
+  pub geometry: Geometry,
   pub preserve: Preservative,
-  pub data: String,
+  pub corpora: Vec<Corpus>,
+  pub classes: Classes,
   pub progress_window_size: usize,
+  pub seed: Option<u64>,
   pub population: PopulationConfig,
   pub selection: SelectionConfig,
   pub weights: WeightsConfig,
-  pub penalties: PenaltiesConfig
+  pub penalties: PenaltiesConfig,
+  pub checkpoint: CheckpointConfig,
+  pub stopping: StoppingConfig,
+  pub distances: DistanceConfig
 }
 
+/// The contents of a config file
 #[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
 pub struct ExternalConfig {
   pub geometry: String,
   pub progress_window_size: usize,
+  #[serde(default)]
+  pub seed: Option<u64>,
   pub population: PopulationConfig,
   pub selection: SelectionConfig,
   pub weights: WeightsConfig,
-  pub penalties: PenaltiesConfig
+  pub penalties: PenaltiesConfig,
+  #[serde(default)]
+  pub checkpoint: CheckpointConfig,
+  #[serde(default)]
+  pub stopping: StoppingConfig,
+  #[serde(default)]
+  pub distances: DistanceConfig,
+  #[serde(default = "default_corpora")]
+  pub corpora: Vec<CorpusConfig>,
+  #[serde(default)]
+  pub classes: Classes
 }
 
-#[derive(Deserialize,Debug)]
+#[derive(Deserialize,Debug,Clone)]
+#[serde(deny_unknown_fields)]
 pub struct WeightsConfig {
   pub effort: usize,
   pub overheads: usize,
@@ -40,21 +68,30 @@ pub struct WeightsConfig {
   pub fitness: usize
 }
 
-#[derive(Deserialize,Debug)]
+#[derive(Deserialize,Debug,Clone,Copy)]
+#[serde(deny_unknown_fields)]
 pub struct SelectionConfig {
   pub rank_space_cut_off: usize,
   pub diversity_bias: f64
 }
 
 #[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PopulationConfig {
   pub size: usize,
   pub steps: usize,
   pub elites: usize,
-  pub symbols: bool
+  pub symbols: bool,
+  #[serde(default = "default_start")]
+  pub start: Vec<String>
+}
+
+fn default_start() -> Vec<String> {
+  vec!["assets/layouts/qwerty".to_string()]
 }
 
 #[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PenaltiesConfig {
   pub same_hand: usize,
   pub same_finger: usize,
@@ -64,43 +101,262 @@ pub struct PenaltiesConfig {
   pub rolling_discount: usize
 }
 
-impl Config<'_> {
-  pub fn defaults() -> Config<'static> {
-    let config = load_external_config();
-    let geometry = if config.geometry == String::from("ORTHO") { &*FULL_ORTHO } else { &*US_PC_KEYBOARD };
-    let preserve = Preservative::from(load_preserve_template());
-    let data = load_text();
+#[derive(Deserialize,Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CheckpointConfig {
+  pub path: String,
+  pub every: usize
+}
+
+// zeros switch the criteria off, the evolution runs until it's quit then
+#[derive(Deserialize,Debug,Clone,Copy,Default,PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StoppingConfig {
+  pub max_generations: usize,
+  pub time_limit: u64, // seconds
+  pub stagnation: usize // generations without the best score improving
+}
+
+// the efforts of imported geometries, a key costs its finger's effort
+// plus `unit` for every key width it sits away from the finger's home key
+#[derive(Deserialize,Debug,Clone,Copy,PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DistanceConfig {
+  pub unit: usize,
+  pub pinky: usize,
+  pub ring: usize,
+  pub middle: usize,
+  pub pointy: usize,
+  pub thumb: usize
+}
+
+// a text to score layouts against, a file or a directory of them, the
+// weight is its share in the total against the other corpora
+#[derive(Deserialize,Debug,Clone,PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CorpusConfig {
+  pub name: String,
+  pub path: String,
+  pub weight: usize,
+  #[serde(default)]
+  pub code: Option<CodeConfig>
+}
+
+// reads the corpus path as a source code tree, the files with the given
+// extensions all the way down, or every text file with no extensions given
+#[derive(Deserialize,Debug,Clone,PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CodeConfig {
+  #[serde(default)]
+  pub extensions: Vec<String>,
+  #[serde(default = "enabled")]
+  pub drop_license_headers: bool,
+  #[serde(default = "enabled")]
+  pub drop_generated: bool,
+  #[serde(default)]
+  pub collapse_indentation: bool, // editors indent the lines, nobody types that
+  #[serde(default = "build_directories")]
+  pub skip_directories: Vec<String> // the build outputs and the dependencies, nobody typed those either
+}
+
+fn enabled() -> bool {
+  true
+}
+
+fn build_directories() -> Vec<String> {
+  ["target", "node_modules", "vendor"].iter().map(|name| name.to_string()).collect()
+}
+
+fn default_corpora() -> Vec<CorpusConfig> {
+  vec![CorpusConfig { name: "text".to_string(), path: "assets/sources/text".to_string(), weight: 1, code: None }]
+}
+
+impl Default for DistanceConfig {
+  fn default() -> DistanceConfig {
+    DistanceConfig { unit: 6, pinky: 2, ring: 1, middle: 0, pointy: 0, thumb: 0 }
+  }
+}
+
+impl Default for CheckpointConfig {
+  fn default() -> CheckpointConfig {
+    CheckpointConfig {
+      path: "checkpoint.toml".to_string(),
+      every: 10
+    }
+  }
+}
+
+impl Config {
+  /// The built-in settings with the text from `assets/sources`, no keys are kept in place
+  pub fn defaults() -> Result<Config, String> {
+    let config = default_config();
+    let corpora = load_corpora(&config.corpora)?;
+
+    Config::from(config, corpora, "")
+  }
+
+  /// Reads and checks a config file, falling back to the built-in settings
+  /// when there is no file given and no `./config.toml` either
+  pub fn load(filename: Option<&str>) -> Result<Config, String> {
+    let config = match filename {
+      Some(filename) => read_config(filename)?,
+      None if Path::new(DEFAULT_FILE).exists() => read_config(DEFAULT_FILE)?,
+      None => default_config()
+    };
+
+    let corpora = load_corpora(&config.corpora)?;
+    let preserve = load_preserve_template();
+
+    Preservative::check(&preserve).map_err(|err| format!("{}: {}", PRESERVE_FILE, err))?;
+
+    Config::from(config, corpora, &preserve)
+  }
 
-    Config { 
+  /// Builds a config out of the given settings and the text to score layouts
+  /// against, the configured corpora are left out and no keys are kept in place
+  pub fn new(config: ExternalConfig, data: String) -> Result<Config, String> {
+    validate(&config, "")?;
+
+    Config::from(config, vec![Corpus::new("text", 1, data)], "")
+  }
+
+  /// Builds a config out of the given settings and corpora, no keys are kept in place
+  pub fn with_corpora(config: ExternalConfig, corpora: Vec<Corpus>) -> Result<Config, String> {
+    validate(&config, "")?;
+
+    Config::from(config, corpora, "")
+  }
+
+  fn from(config: ExternalConfig, corpora: Vec<Corpus>, preserve: &str) -> Result<Config, String> {
+    let geometry = Geometry::find(&config.geometry)?;
+    let preserve = Preservative::from(preserve.to_string());
+
+    Ok(Config { 
       geometry, 
       preserve, 
-      data,
+      corpora,
+      classes: config.classes,
       progress_window_size: config.progress_window_size,
+      seed: config.seed,
       population: config.population,
       selection: config.selection,
       weights: config.weights,
-      penalties: config.penalties
+      penalties: config.penalties,
+      checkpoint: config.checkpoint,
+      stopping: config.stopping,
+      distances: config.distances
+    })
+  }
+}
+
+impl Default for ExternalConfig {
+  fn default() -> ExternalConfig {
+    default_config()
+  }
+}
+
+fn read_config(filename: &str) -> Result<ExternalConfig, String> {
+  let data = fs::read_to_string(filename).map_err(|err| format!("{}: {}", filename, err))?;
+  parse_config(&data).map_err(|err| format!("{}: {}", filename, err))
+}
+
+/// Parses the config file contents, pointing at the offending lines on errors
+pub fn parse_config(data: &str) -> Result<ExternalConfig, String> {
+  let config: ExternalConfig = toml::from_str(data).map_err(|err| pinpoint(err.to_string(), data))?;
+  validate(&config, data)?;
+
+  Ok(config)
+}
+
+// toml points unknown fields at their table, this points them at the line the field is on
+fn pinpoint(error: String, data: &str) -> String {
+  let quoted = |text: &str, prefix: &str| {
+    let rest = &text[text.find(prefix)? + prefix.len()..];
+    Some(rest[..rest.find('`')?].to_string())
+  };
+
+  let field = quoted(&error, "unknown field `");
+  let section = quoted(&error, " for key `").unwrap_or_default();
+
+  match field.and_then(|field| Some((line_of(data, &section, &field, 0)?, field))) {
+    None => error,
+    Some((line, field)) => {
+      let end = error.find(" for key `").or_else(|| error.find(" at line ")).unwrap_or(error.len());
+      let message = &error[..end];
+      let name = if section.is_empty() { field } else { format!("{}.{}", section, field) };
+
+      format!("{} for key `{}` at line {}", message, name, line)
     }
   }
 }
 
-fn load_external_config() -> ExternalConfig {
-  if cfg!(test) { default_config() }
-  else {
-    let data = fs::read_to_string("./config.toml").unwrap_or(String::from(""));
-    toml::from_str(&data).unwrap_or(default_config())
+fn validate(config: &ExternalConfig, data: &str) -> Result<(), String> {
+  let geometry = Geometry::find(&config.geometry);
+  let repeated = config.corpora.iter().enumerate()
+    .find(|(i, corpus)| config.corpora[..*i].iter().any(|other| other.name == corpus.name));
+  let (repeated_block, repeated_name) = repeated.map(|(i, corpus)| (i, corpus.name.as_str())).unwrap_or_default();
+
+  let checks = [
+    ("", "geometry", 0, geometry.is_ok(), geometry.err().unwrap_or_default()),
+    ("population", "steps", 0, config.population.steps > 0,
+      "steps should be above 0".to_string()),
+    ("population", "elites", 0, config.population.elites <= 100,
+      format!("elites should be 100 or less, got {}", config.population.elites)),
+    ("selection", "rank_space_cut_off", 0, config.selection.rank_space_cut_off <= 100,
+      format!("rank_space_cut_off should be 100 or less, got {}", config.selection.rank_space_cut_off)),
+    ("penalties", "rolling_discount", 0, config.penalties.rolling_discount <= 100,
+      format!("rolling_discount should be 100 or less, got {}", config.penalties.rolling_discount)),
+    ("corpora", "weight", 0, config.corpora.iter().any(|corpus| corpus.weight > 0),
+      "at least one of the corpora should weigh above 0".to_string()),
+    ("corpora", "name", repeated_block, repeated.is_none(),
+      format!("the corpora names should be different, '{}' repeats", repeated_name))
+  ];
+
+  match checks.iter().find(|(_, _, _, valid, _)| !valid) {
+    None => Ok(()),
+    Some((section, key, block, _, message)) => {
+      let name = if section.is_empty() { key.to_string() } else { format!("{}.{}", section, key) };
+
+      Err(match line_of(data, section, key, *block) {
+        Some(line) => format!("{} for key `{}` at line {}", message, name, line),
+        None => format!("{} for key `{}`", message, name)
+      })
+    }
   }
 }
 
+// finds the line a key is set at, so the errors could point at it the way toml does,
+// the block is which one of the `[[section]]` tables it's in
+fn line_of(data: &str, section: &str, key: &str, block: usize) -> Option<usize> {
+  let mut current = "";
+  let mut blocks = 0;
+
+  for (i, line) in data.lines().enumerate() {
+    let line = line.trim();
+
+    if let Some(header) = line.strip_prefix('[') {
+      let header = header.trim_start_matches('[');
+      current = header.split(']').next().unwrap_or("").trim();
+      if current == section { blocks += 1; }
+    } else if current == section && blocks.max(1) - 1 == block && line.split('=').next().map(str::trim) == Some(key) {
+      return Some(i + 1);
+    }
+  }
+
+  None
+}
+
 fn default_config() -> ExternalConfig {
   ExternalConfig {
     geometry: "US-PC".to_string(),
     progress_window_size: 200,
+    seed: None,
     population: PopulationConfig {
       size: 30,
       steps: 3,
       elites: 30,
-      symbols: true
+      symbols: true,
+      start: default_start()
     },
     selection: SelectionConfig {
       rank_space_cut_off: 50,
@@ -120,20 +376,35 @@ fn default_config() -> ExternalConfig {
       row_skip: 50,
       row_jump: 30,
       rolling_discount: 80
-    }
+    },
+    checkpoint: CheckpointConfig::default(),
+    stopping: StoppingConfig::default(),
+    distances: DistanceConfig::default(),
+    corpora: default_corpora(),
+    classes: Classes::default()
   }
 }
 
 fn load_preserve_template() -> String {
   if cfg!(test) { String::from("") }
   else {
-    fs::read_to_string("./preserve.txt").unwrap_or(String::from(""))
+    fs::read_to_string(PRESERVE_FILE).unwrap_or(String::from(""))
   }
 }
 
-fn load_text() -> String {
-  if cfg!(test) { lorem_ipsum() }
-  else { source::load_english_text() }
+fn load_corpora(corpora: &[CorpusConfig]) -> Result<Vec<Corpus>, String> {
+  if cfg!(test) { return Ok(vec![Corpus::new("text", 1, lorem_ipsum())]); }
+
+  corpora.iter().map(|corpus| {
+    let text = match &corpus.code {
+      Some(code) => code::read(&corpus.path, code).map(Text::Plain),
+      None => source::read(&corpus.path)
+    };
+    let text = text.map_err(|err| format!("corpus '{}', {}: {}", corpus.name, corpus.path, err))?;
+
+    Ok(Corpus::from(&corpus.name, corpus.weight, text))
+  })
+  .collect()
 }
 
 fn lorem_ipsum() -> String {
@@ -147,4 +418,81 @@ fn lorem_ipsum() -> String {
   passaggi del Lorem Ipsum, e più recentemente da software di impaginazione come Aldus PageMaker, 
   che includeva versioni del Lorem Ipsum.
   ".to_string()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn parses_the_shipped_config() {
+    let data = fs::read_to_string(DEFAULT_FILE).unwrap();
+    assert!(parse_config(&data).is_ok());
+  }
+
+  #[test]
+  fn points_at_unknown_keys() {
+    let data = fs::read_to_string(DEFAULT_FILE).unwrap()
+      .replace("steps = 5", "stpes = 5");
+    let error = parse_config(&data).unwrap_err();
+
+    let line = data.lines().position(|line| line.starts_with("stpes")).unwrap() + 1;
+
+    assert!(error.starts_with("unknown field `stpes`, expected one of `size`"), "{}", error);
+    assert!(error.ends_with(&format!("for key `population.stpes` at line {}", line)), "{}", error);
+  }
+
+  #[test]
+  fn checks_the_ranges() {
+    let data = fs::read_to_string(DEFAULT_FILE).unwrap();
+    let line = data.lines().position(|line| line.starts_with("rolling_discount")).unwrap() + 1;
+
+    assert_eq!(
+      parse_config(&data.replace("rolling_discount = 80", "rolling_discount = 120")).unwrap_err(),
+      format!("rolling_discount should be 100 or less, got 120 for key `penalties.rolling_discount` at line {}", line)
+    );
+    assert!(parse_config(&data.replace("elites = 50", "elites = 101")).is_err());
+    assert!(parse_config(&data.replace("rank_space_cut_off = 96", "rank_space_cut_off = 200")).is_err());
+    assert!(parse_config(&data.replace("steps = 5", "steps = 0")).is_err());
+  }
+
+  #[test]
+  fn checks_the_geometry() {
+    let data = fs::read_to_string(DEFAULT_FILE).unwrap()
+      .replace("geometry = \"US-PC\"", "geometry = \"AZERTY\"");
+
+    assert_eq!(
+      parse_config(&data).unwrap_err(),
+      "unknown geometry 'AZERTY', expected one of: US-PC, ORTHO, or a geometry file for key `geometry` at line 1"
+    );
+  }
+
+  #[test]
+  fn checks_the_corpora() {
+    let data = fs::read_to_string(DEFAULT_FILE).unwrap();
+    let line = data.lines().position(|line| line.starts_with("weight = 1")).unwrap() + 1;
+
+    assert_eq!(parse_config(&data).unwrap().corpora, vec![
+      CorpusConfig { name: "text".to_string(), path: "assets/sources/text".to_string(), weight: 1, code: None }
+    ]);
+    assert_eq!(
+      parse_config(&data.replace("weight = 1 #", "weight = 0 #")).unwrap_err(),
+      format!("at least one of the corpora should weigh above 0 for key `corpora.weight` at line {}", line)
+    );
+
+    let repeated = data.replace("# [[corpora]]\n# name = \"rust\"\n# path = \"src\"", "[[corpora]]\nname = \"text\"\npath = \"src\"")
+      .replace("# weight = 1", "weight = 1");
+    assert_eq!(
+      parse_config(&repeated).unwrap_err(),
+      format!("the corpora names should be different, 'text' repeats for key `corpora.name` at line {}", line + 3)
+    );
+
+    let code = data.replace("# [[corpora]]\n# name = \"rust\"\n# path = \"src\"\n# weight = 1\n# [corpora.code]", "[[corpora]]\nname = \"rust\"\npath = \"src\"\nweight = 1\n[corpora.code]")
+      .replace("# extensions", "extensions")
+      .replace("# collapse_indentation", "collapse_indentation");
+    assert_eq!(parse_config(&code).unwrap().corpora[1].code, Some(CodeConfig {
+      extensions: vec!["rs".to_string()], drop_license_headers: true, drop_generated: true, collapse_indentation: true,
+      skip_directories: build_directories()
+    }));
+  }
+}
@@ -4,9 +4,11 @@ use crate::parser::*;
 pub type Pair = (String, String);
 pub type Sequence = Vec<Pair>;
 
+// the layout keys in a row, with the row sizes to put them back the way they were
 #[derive(Debug,PartialEq,Eq)]
 pub struct DNA {
-  pub sequence: Sequence
+  pub sequence: Sequence,
+  pub shape: Vec<usize>
 }
 
 impl DNA {
@@ -17,34 +19,20 @@ impl DNA {
       sequence.push((entry.shifted.to_owned(), entry.normal.to_owned()));
     }
 
-    DNA { sequence }
+    DNA { sequence, shape: layout.shape() }
   }
 
   pub fn to_layout(self: &Self) -> Layout {
-    let mut shifts = "".to_string();
-    let mut normals = "".to_string();
-    
-    for (i, (shifted, normal)) in self.sequence.iter().enumerate() {
-      shifts = format!("{} {}", shifts, shifted);
-      normals = format!("{} {}", normals, normal);
-  
-      match i {
-        12 | 25 | 36 => {
-          let spacer = if i == 36 { "   " } else { " " };
-
-          shifts = format!("{}\n{}", shifts.trim(), spacer);
-          normals = format!("{}\n{}", normals.trim(), spacer);
-        },
-        _ => {}
+    let mut pairs = self.sequence.iter();
+    let mut entries = vec![];
+
+    for (row, size) in self.shape.iter().enumerate() {
+      for (col, (shifted, normal)) in pairs.by_ref().take(*size).enumerate() {
+        entries.push(Entry { shifted: shifted.to_string(), normal: normal.to_string(), position: (row, col) });
       }
     }
 
-    let template = shifts.lines().zip(normals.lines())
-      .map(|(s,n)| [s,n].join("\n"))
-      .collect::<Vec<String>>()
-      .join("\n");
-
-    Layout { template: template }
+    Layout::from_entries(&entries)
   }
 
   pub fn swap_keys(self: &Self, pos1: usize, pos2: usize) -> DNA {
@@ -77,7 +65,7 @@ impl DNA {
       *entry = new_entry2;
     }
     
-    DNA { sequence }
+    DNA { sequence, shape: self.shape.clone() }
   }
 
   pub fn len(self: &Self) -> usize {
@@ -107,14 +95,23 @@ mod test {
 
   #[test]
   fn test_to_layout() {
-    let dna = DNA { sequence: qwerty_sequence() };
+    let dna = DNA { sequence: qwerty_sequence(), shape: ROW_SIZES.to_vec() };
 
     assert_eq!(dna.to_layout(), QWERTY.clone());
   }
 
+  #[test]
+  fn keeps_the_shape() {
+    let layout = Layout::new("Q W F\nq w f\nA R\na r".to_string());
+    let dna = DNA::from(&layout);
+
+    assert_eq!(dna.shape, vec![3, 2]);
+    assert_eq!(dna.swap_keys(0, 4).to_layout(), Layout::new("R W F\nr w f\nA Q\na q".to_string()));
+  }
+
   #[test]
   fn test_swapping_keys() {
-    let original = DNA { sequence: qwerty_sequence() };
+    let original = DNA { sequence: qwerty_sequence(), shape: ROW_SIZES.to_vec() };
     let new_dna = original.swap_keys(2, 5);
 
     assert_eq!(original.sequence, qwerty_sequence()); // should not change
@@ -132,7 +129,7 @@ mod test {
 
   #[test]
   fn test_swapping_symbols() {
-    let original = DNA { sequence: qwerty_sequence() };
+    let original = DNA { sequence: qwerty_sequence(), shape: ROW_SIZES.to_vec() };
     let new_dna1 = original.swap_symbols((2,0), (3,0));
     let new_dna2 = original.swap_symbols((2,0), (3,1));
     let new_dna3 = original.swap_symbols((2,1), (3,0));
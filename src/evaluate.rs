@@ -0,0 +1,115 @@
+/**
+ * Scores a single layout against the configured geometry
+ */
+use rusty_key::config::Config;
+use rusty_key::layout::*;
+use rusty_key::lint;
+use rusty_key::keyboard::*;
+use rusty_key::summary::*;
+use rusty_key::source::Corpus;
+
+pub fn run(filename: &str, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+  let layout = Layout::read(filename)?;
+
+  warn_about(filename, &layout, config);
+
+  println!("{}", report(&layout, config));
+
+  Ok(())
+}
+
+/// Prints what `check` would say about the layout, the rows that don't fit
+/// the geometry and the symbols the text needs but it has no keys for
+pub fn warn_about(filename: &str, layout: &Layout, config: &Config) {
+  for problem in lint::check(layout, config) {
+    eprintln!("{}: {}", filename, problem);
+  }
+}
+
+pub fn report(layout: &Layout, config: &Config) -> String {
+  let keyboard = Keyboard::from(layout, &config.geometry);
+  let summaries = Summary::per_corpus(&keyboard, config);
+  let summary = Summary::combine(&summaries);
+
+  let report = format!(
+    "{}\n\n{}\n\neffort:      {:>8.4}\noverheads:   {:>8.4}\nawkwardness: {:>8.4}\nrollingness: {:>8.4}\nfitness:     {:>8.4}\n\nscore:       {:>8.4}",
+    keyboard.name,
+    keyboard,
+    summary.effort,
+    summary.overheads,
+    summary.awkwardness,
+    summary.rollingness,
+    summary.fitness,
+    summary.score(&config.weights)
+  );
+
+  if summaries.len() < 2 {
+    return report;
+  }
+
+  format!("{}\n\n{}", report, corpora_table(&summaries, config))
+}
+
+// the metrics on every corpus, to see which one the layout does worse on
+fn corpora_table(summaries: &[(&Corpus, Summary)], config: &Config) -> String {
+  let width = summaries.iter().map(|(corpus, _)| corpus.name.chars().count()).max().unwrap_or(0).max(6) + 2;
+  let mut lines = vec![format!(
+    "{:<width$}{:>8}{:>13}{:>13}{:>13}{:>13}{:>13}{:>13}",
+    "corpus", "weight", "effort", "overheads", "awkwardness", "rollingness", "fitness", "score", width = width
+  )];
+
+  for (corpus, summary) in summaries {
+    lines.push(format!(
+      "{:<width$}{:>8}{:>13.4}{:>13.4}{:>13.4}{:>13.4}{:>13.4}{:>13.4}",
+      corpus.name, corpus.weight, summary.effort, summary.overheads, summary.awkwardness,
+      summary.rollingness, summary.fitness, summary.score(&config.weights), width = width
+    ));
+  }
+
+  lines.join("\n")
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+  use rusty_key::config::ExternalConfig;
+
+  pub fn config() -> Config {
+    Config::new(ExternalConfig::default(), "The quick brown fox jumps over the lazy dog.".to_string()).unwrap()
+  }
+
+  #[test]
+  fn reports_all_the_metrics() {
+    let report = report(&COLEMAK, &config());
+    let lines: Vec<&str> = report.lines().collect();
+
+    assert_eq!(lines[0], "QWFPGJ");
+    assert_eq!(lines[2], "` 1 2 3 4 5 6 7 8 9 0 - =");
+
+    for metric in ["effort:", "overheads:", "awkwardness:", "rollingness:", "fitness:", "score:"].iter() {
+      assert!(lines.iter().any(|line| line.starts_with(metric)), "missing {}", metric);
+    }
+  }
+
+  #[test]
+  fn reports_every_corpus() {
+    let config = Config::with_corpora(ExternalConfig::default(), vec![
+      Corpus::new("prose", 70, "The quick brown fox jumps over the lazy dog.".to_string()),
+      Corpus::new("rust", 30, "fn main() { let x = vec![1, 2]; }".to_string())
+    ]).unwrap();
+
+    let text = report(&COLEMAK, &config);
+    let lines: Vec<&str> = text.lines().collect();
+    let table = &lines[lines.len() - 3..];
+
+    assert!(table[0].starts_with("corpus    weight       effort    overheads"));
+    assert!(table[1].starts_with("prose         70"));
+    assert!(table[2].starts_with("rust          30"));
+    assert!(!report(&COLEMAK, &self::config()).contains("corpus"));
+  }
+
+  #[test]
+  fn fails_on_missing_files() {
+    assert!(run("assets/layouts/nonexistent", &config()).is_err());
+  }
+}
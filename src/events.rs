@@ -9,7 +9,8 @@ use termion::input::TermRead;
 
 use once_cell::sync::OnceCell;
 
-use crate::generation::Outcome;
+use rusty_key::generation::Outcome;
+use rusty_key::stopping::Finish;
 
 pub fn inst() -> &'static Events {
   static EVENTS: OnceCell<Events> = OnceCell::new();
@@ -18,12 +19,15 @@ pub fn inst() -> &'static Events {
 
 pub enum Event {
   Result(Outcome),
+  Finished(Finish),
+  Warning(String),
   Input(Key),
   Tick,
 }
 
 /// A small event handler that wrap termion input and tick events. Each event
-/// type is handled in its own thread and returned to a common `Receiver`
+/// type is handled in its own thread and returned to a common `Receiver`.
+/// The input threads are started on demand, so that headless runs don't touch the terminal
 pub struct Events {
   tx: Mutex<mpsc::Sender<Event>>,
   rx: Mutex<mpsc::Receiver<Event>>
@@ -44,13 +48,16 @@ impl Default for Config {
 
 impl Events {
   pub fn new() -> Events {
-    Events::with_config(Config::default())
+    let (tx, rx) = mpsc::channel();
+
+    Events {
+      rx: Mutex::new(rx),
+      tx: Mutex::new(tx)
+    }
   }
 
-  pub fn with_config(config: Config) -> Events {
-    let (tx, rx) = mpsc::channel();
-    
-    let txl = tx.clone();
+  pub fn listen_to_terminal(&self, config: Config) {
+    let txl = self.tx.lock().unwrap().clone();
     thread::spawn(move || {
       let stdin = io::stdin();
       for evt in stdin.keys() {
@@ -63,7 +70,7 @@ impl Events {
       }
     });
 
-    let txn = tx.clone();
+    let txn = self.tx.lock().unwrap().clone();
     thread::spawn(move || loop {
       if let Err(err) = txn.send(Event::Tick) {
         eprintln!("{}", err);
@@ -71,11 +78,6 @@ impl Events {
       }
       thread::sleep(config.tick_rate);
     });
-
-    Events {
-      rx: Mutex::new(rx),
-      tx: Mutex::new(tx)
-    }
   }
 
   pub fn send_result(&self, outcome: &Outcome) {
@@ -86,6 +88,20 @@ impl Events {
     }
   }
 
+  pub fn send_finish(&self, finish: &Finish) {
+    let tx = self.tx.lock().unwrap();
+    if let Err(err) = tx.send(Event::Finished(finish.clone())) {
+      eprintln!("{}", err);
+    }
+  }
+
+  pub fn send_warning(&self, message: &str) {
+    let tx = self.tx.lock().unwrap();
+    if let Err(err) = tx.send(Event::Warning(message.to_string())) {
+      eprintln!("{}", err);
+    }
+  }
+
   pub fn next(&self) -> Result<Event, mpsc::RecvError> {
     let rx = self.rx.lock().unwrap();
     rx.recv()
@@ -3,59 +3,132 @@
  */
 use std::cell::RefCell;
 use std::sync::{Arc,Mutex};
+use std::thread::JoinHandle;
 
+use crate::config::Config;
+use crate::layout::Layout;
 use crate::generation::*;
-use crate::events;
+use crate::checkpoint::*;
+use crate::stopping::{Tracker,Finish};
 
-type Generations = Vec<Generation>;
+type History = Vec<Outcome>;
+
+/// What a running evolution reports back to its listener
+#[derive(Debug,Clone,PartialEq)]
+pub enum Update {
+  Result(Outcome),
+  Finished(Finish),
+  /// Something went wrong on the way, a checkpoint that could not be saved say, the run goes on
+  Warning(String)
+}
 
 pub struct Evolution {
   in_progress: Arc<Mutex<RefCell<bool>>>,
+  worker: Mutex<Option<JoinHandle<()>>>,
   current_generation: Arc<Mutex<RefCell<Generation>>>,
-  pub past_generations: Arc<Mutex<Generations>>
+  pub history: Arc<Mutex<History>>,
+  pub config: Arc<Config>
 }
 
 impl Evolution {
-  pub fn new() -> Evolution {
+  /// Starts from the layouts and the seed in the config
+  pub fn new(config: Arc<Config>) -> Result<Evolution, String> {
+    Ok(Evolution::from(Generation::zero(&config)?, History::new(), config))
+  }
+
+  /// Starts from the given layouts, mixing them up when there are several
+  pub fn starting_with(layouts: &[Layout], seed: u64, config: Arc<Config>) -> Evolution {
+    Evolution::from(Generation::first(layouts, seed, &config), History::new(), config)
+  }
+
+  /// Picks up where a saved checkpoint left off
+  pub fn resume(checkpoint: Checkpoint, config: Arc<Config>) -> Evolution {
+    Evolution::from(Generation::restore(checkpoint.generation, &config), checkpoint.history, config)
+  }
+
+  fn from(generation: Generation, history: History, config: Arc<Config>) -> Evolution {
     let in_progress = Arc::new(Mutex::new(RefCell::new(false)));
-    let current_generation = Arc::new(Mutex::new(RefCell::new(Generation::zero())));
-    let past_generations = Arc::new(Mutex::new(Generations::new()));
+    let current_generation = Arc::new(Mutex::new(RefCell::new(generation)));
+    let history = Arc::new(Mutex::new(history));
 
-    Evolution { in_progress, current_generation, past_generations }
+    Evolution { in_progress, worker: Mutex::new(None), current_generation, history, config }
   }
 
-  pub fn start(&self) {
+  /// Runs the generations in a background thread, passing every outcome to the listener
+  pub fn start<F>(&self, listener: F) where F: Fn(Update) + Send + 'static {
     let flag = &mut *self.in_progress.lock().unwrap();
     flag.replace(true);
-  
-    Evolution::start_thread(
+
+    // replaying the resumed results for the listener to pick up
+    for outcome in self.history.lock().unwrap().iter() {
+      listener(Update::Result(outcome.clone()));
+    }
+
+    let tracker = Tracker::new(&self.config, &self.history.lock().unwrap());
+
+    let worker = Evolution::start_thread(
       self.in_progress.clone(),
       self.current_generation.clone(),
-      self.past_generations.clone()
+      self.history.clone(),
+      self.config.clone(),
+      tracker,
+      listener
     );
+
+    self.worker.lock().unwrap().replace(worker);
   }
 
-  #[allow(dead_code)]
+  /// Stops the run and waits for the generation in the works, so a checkpoint
+  /// taken after it doesn't race the one the background thread might be saving
   pub fn stop(&self) {
-    let flag = &mut *self.in_progress.lock().unwrap();
-    flag.replace(false);
+    self.in_progress.lock().unwrap().replace(false);
+
+    if let Some(worker) = self.worker.lock().unwrap().take() {
+      // a panicked thread has already said so
+      let _ = worker.join();
+    }
   }
 
-  fn start_thread(
+  pub fn checkpoint(&self) -> Checkpoint {
+    Evolution::make_checkpoint(&self.current_generation, &self.history, &self.config)
+  }
+
+  fn start_thread<F>(
     flag: Arc<Mutex<RefCell<bool>>>,
     current: Arc<Mutex<RefCell<Generation>>>,
-    past: Arc<Mutex<Generations>>
-  ) {
+    history: Arc<Mutex<History>>,
+    config: Arc<Config>,
+    mut tracker: Tracker,
+    listener: F
+  ) -> JoinHandle<()> where F: Fn(Update) + Send + 'static {
     std::thread::spawn(move || {
       loop {
-        if Evolution::fetch_status(&flag) {            
+        if Evolution::fetch_status(&flag) {
           let next_generation = Evolution::get_next(&current);
-          Evolution::swap(&current, &past, next_generation);
+          let outcome = Evolution::swap(&current, &history, next_generation);
+          let number = outcome.generation_number;
+          let every = config.checkpoint.every;
+
+          listener(Update::Result(outcome.clone()));
+
+          if every > 0 && number.is_multiple_of(every) {
+            let checkpoint = Evolution::make_checkpoint(&current, &history, &config);
+
+            if let Err(err) = checkpoint.save(&config.checkpoint.path) {
+              listener(Update::Warning(format!("could not save the checkpoint: {}", err)));
+            }
+          }
+
+          if let Some(finish) = tracker.check(&outcome) {
+            flag.lock().unwrap().replace(false);
+            listener(Update::Finished(finish));
+            break;
+          }
         } else {
           break;
         }
       }
-    });
+    })
   }
 
   fn fetch_status(mutex: &Arc<Mutex<RefCell<bool>>>) -> bool {
@@ -70,19 +143,36 @@ impl Evolution {
     next_gen
   }
 
+  // returns the outcome of the generation that was just finished
   fn swap(
     current: &Arc<Mutex<RefCell<Generation>>>,
-    past: &Arc<Mutex<Generations>>,
+    history: &Arc<Mutex<History>>,
     next_generation: Generation
-  ) {
+  ) -> Outcome {
     let guard = &mut *current.lock().unwrap();
     let current_generation = guard.replace(next_generation);
+    let outcome = current_generation.outcome();
+
+    let past_outcomes = &mut *history.lock().unwrap();
+    past_outcomes.push(outcome.clone());
+
+    outcome
+  }
 
-    // sending the result for the UI to consume
-    events::inst().send_result(&current_generation.outcome());
+  // only the recent history goes in, as much as the progress chart shows,
+  // or the stagnation criteria looks back at when that's longer
+  fn make_checkpoint(
+    current: &Arc<Mutex<RefCell<Generation>>>,
+    history: &Arc<Mutex<History>>,
+    config: &Config
+  ) -> Checkpoint {
+    let guard = &*current.lock().unwrap();
+    let generation = guard.borrow().snapshot();
+    let history = history.lock().unwrap();
+    let kept = config.progress_window_size.max(config.stopping.stagnation);
+    let history = history[history.len().saturating_sub(kept)..].to_vec();
 
-    let past_gens = &mut *past.lock().unwrap();
-    past_gens.push(current_generation);
+    Checkpoint { generation, history }
   }
 }
 
@@ -90,11 +180,21 @@ impl Evolution {
 mod test {
   use super::*;
   use std::time;
+  use std::sync::mpsc;
   use crate::layout::*;
+  use crate::config::StoppingConfig;
+  use crate::stopping::Reason;
+
+  // no checkpoint files off the test runs
+  fn config() -> Arc<Config> {
+    let mut config = Config::defaults().unwrap();
+    config.checkpoint.every = 0;
+    Arc::new(config)
+  }
 
   #[test]
   fn test_new() {
-    let evolution = Evolution::new();
+    let evolution = Evolution::new(config()).unwrap();
     assert_eq!(Evolution::fetch_status(&evolution.in_progress), false);
 
     let guard = &*evolution.current_generation.lock().unwrap();
@@ -105,10 +205,10 @@ mod test {
 
   #[test]
   fn test_start() {
-    let evolution = Evolution::new();
+    let evolution = Evolution::new(config()).unwrap();
     assert_eq!(Evolution::fetch_status(&evolution.in_progress), false);
 
-    evolution.start();
+    evolution.start(|_| {});
     assert_eq!(Evolution::fetch_status(&evolution.in_progress), true);
 
     std::thread::sleep(time::Duration::from_millis(200));
@@ -116,4 +216,76 @@ mod test {
     assert_eq!(Evolution::fetch_status(&evolution.in_progress), false);
   }
 
-}
\ No newline at end of file
+  #[test]
+  fn test_stopping() {
+    let mut config = Config::defaults().unwrap();
+    config.stopping = StoppingConfig { max_generations: 2, time_limit: 0, stagnation: 0 };
+
+    let evolution = Evolution::starting_with(&[QWERTY.clone()], 42, Arc::new(config));
+    let (tx, rx) = mpsc::channel();
+
+    evolution.start(move |update| tx.send(update).unwrap());
+
+    let updates: Vec<Update> = rx.iter().take(3).collect();
+
+    assert!(matches!(&updates[0], Update::Result(outcome) if outcome.generation_number == 1));
+    assert!(matches!(&updates[1], Update::Result(outcome) if outcome.generation_number == 2));
+    assert!(matches!(&updates[2], Update::Finished(finish) if finish.reason == Reason::MaxGenerations));
+
+    assert_eq!(Evolution::fetch_status(&evolution.in_progress), false);
+    assert_eq!(evolution.history.lock().unwrap().len(), 2);
+  }
+
+  #[test]
+  fn test_checkpoint_saving() {
+    let mut config = Config::defaults().unwrap();
+    config.progress_window_size = 1;
+    config.stopping = StoppingConfig { max_generations: 2, time_limit: 0, stagnation: 0 };
+    config.checkpoint.every = 1;
+    config.checkpoint.path = "nonexistent/checkpoint.toml".to_string();
+
+    let evolution = Evolution::starting_with(&[QWERTY.clone()], 42, Arc::new(config));
+    let (tx, rx) = mpsc::channel();
+
+    evolution.start(move |update| tx.send(update).unwrap());
+
+    let updates: Vec<Update> = rx.iter().take(5).collect();
+    evolution.stop();
+
+    assert!(matches!(&updates[1], Update::Warning(message) if message.starts_with("could not save the checkpoint")));
+    assert!(matches!(&updates[4], Update::Finished(_)));
+
+    let checkpoint = evolution.checkpoint();
+    assert_eq!(checkpoint.history.len(), 1);
+    assert_eq!(checkpoint.history[0].generation_number, 2);
+  }
+
+  #[test]
+  fn test_starting_with() {
+    let evolution = Evolution::starting_with(&[HALMAK_21.clone()], 42, config());
+
+    let guard = &*evolution.current_generation.lock().unwrap();
+    let current_generation = guard.borrow();
+
+    assert_eq!(current_generation.number, 1);
+    assert_eq!(current_generation.population.members[0], HALMAK_21.clone());
+  }
+
+  #[test]
+  fn test_checkpoint_and_resume() {
+    let evolution = Evolution::new(config()).unwrap();
+    let checkpoint = evolution.checkpoint();
+
+    assert_eq!(checkpoint.generation.number, 1);
+    assert_eq!(checkpoint.history, vec![]);
+
+    let resumed = Evolution::resume(Checkpoint {
+      generation: Snapshot { number: 42, ..checkpoint.generation },
+      history: vec![]
+    }, config());
+
+    let guard = &*resumed.current_generation.lock().unwrap();
+    assert_eq!(guard.borrow().number, 42);
+    assert_eq!(guard.borrow().population.members[0], QWERTY.clone());
+  }
+}
@@ -0,0 +1,101 @@
+/**
+ * Writes a layout file out in a format an OS can load
+ */
+use std::path::Path;
+use rusty_key::layout::*;
+use rusty_key::{xkb,keylayout,klc,keymap};
+use rusty_key::geometry::{Geometry,FULL_ORTHO};
+use crate::cli::Exporting;
+
+pub const FORMATS: [&str; 5] = ["xkb", "keylayout", "klc", "qmk", "zmk"];
+
+pub fn run(exporting: &Exporting) -> Result<(), Box<dyn std::error::Error>> {
+  let layout = Layout::read(&exporting.path)?;
+  let name = match &layout.metadata.name {
+    Some(name) => name.clone(),
+    None => Path::new(&exporting.path).file_name().map_or(layout.long_name(), |name| name.to_string_lossy().to_string())
+  };
+
+  let (source, warnings) = export(&layout, &name, &exporting.format)?;
+
+  for warning in warnings {
+    eprintln!("warning: {}", warning);
+  }
+
+  print!("{}", source);
+
+  Ok(())
+}
+
+/// The exported file, and the warnings about what didn't make it in
+pub fn export(layout: &Layout, name: &str, format: &str) -> Result<(String, Vec<String>), String> {
+  let parts = |keymap: keymap::Keymap| (keymap.source, keymap.warnings);
+
+  match format {
+    "xkb" => standard_shape(layout, format).map(|_| (xkb::export(layout, name), vec![])),
+    "keylayout" => standard_shape(layout, format).map(|_| (keylayout::export(layout, name), vec![])),
+    "klc" => standard_shape(layout, format).map(|_| (klc::export(layout, name), vec![])),
+    "qmk" => Ok(parts(keymap::qmk(layout, &keymap_geometry_for(layout)?, name))),
+    "zmk" => Ok(parts(keymap::zmk(layout, &keymap_geometry_for(layout)?, name))),
+    _ => Err(format!("unknown export format '{}', expected one of {}", format, FORMATS.join(", ")))
+  }
+}
+
+// the OS layouts map the symbols onto the keys of a standard keyboard,
+// the other shapes only go into the firmware keymaps
+fn standard_shape(layout: &Layout, format: &str) -> Result<(), String> {
+  let shape = layout.shape();
+
+  if shape == ROW_SIZES {
+    return Ok(());
+  }
+
+  Err(format!("{} needs the {:?} rows of a standard keyboard, the layout has {:?}, export it as qmk or zmk instead", format, ROW_SIZES, shape))
+}
+
+// the firmware keymaps are for the split/ortho boards, unless the layout says otherwise
+fn keymap_geometry_for(layout: &Layout) -> Result<Geometry, String> {
+  match &layout.metadata.geometry {
+    Some(name) => Geometry::find(name),
+    None => Ok(FULL_ORTHO.clone())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn exports_known_formats() {
+    assert!(export(&COLEMAK, "colemak", "xkb").unwrap().0.contains("key <AD03> { [ f, F ] };"));
+    assert!(export(&COLEMAK, "colemak", "keylayout").unwrap().0.contains("<key code=\"14\" output=\"f\"/>"));
+    assert!(export(&COLEMAK, "colemak", "klc").unwrap().0.contains("12\tE\t\t1\t0066\t0046\t// f, F"));
+    assert!(export(&COLEMAK, "colemak", "qmk").unwrap().0.contains("KC_GRV, KC_LSFT, KC_SPC, KC_ENT, KC_RSFT, KC_TAB"));
+    assert!(export(&COLEMAK, "colemak", "zmk").unwrap().0.contains("&kp GRAVE &kp LSHIFT &kp SPACE &kp RETURN &kp RSHIFT &kp TAB"));
+    assert_eq!(export(&COLEMAK, "colemak", "blah"), Err("unknown export format 'blah', expected one of xkb, keylayout, klc, qmk, zmk".to_string()));
+  }
+
+  #[test]
+  fn exports_the_header() {
+    let layout = Layout::new(format!("// name: Colemak\n// description: the classic\n// geometry: US-PC\n{}", COLEMAK.template));
+    let (keymap, warnings) = export(&layout, "Colemak", "qmk").unwrap();
+
+    assert!(keymap.starts_with("// Colemak, generated by rusty-key\n// the classic\n"));
+    assert_eq!(warnings, Vec::<String>::new());
+    assert!(keymap.contains("KC_LSFT, KC_Z, KC_X, KC_C, KC_V, KC_B, KC_K, KC_M, KC_COMM, KC_DOT, KC_SLSH, KC_RSFT"));
+    assert!(export(&layout, "Colemak", "keylayout").unwrap().0.contains("<!-- the classic -->\n<keyboard"));
+
+    let layout = Layout::new(format!("// geometry: blah\n{}", COLEMAK.template));
+    assert_eq!(export(&layout, "colemak", "zmk"), Err("unknown geometry 'blah', expected one of: US-PC, ORTHO, or a geometry file".to_string()));
+  }
+
+  #[test]
+  fn keeps_the_os_layouts_on_the_standard_shape() {
+    let small = COLEMAK.reshaped(&[10, 10, 10, 1]).unwrap();
+    let error = "xkb needs the [13, 13, 11, 10] rows of a standard keyboard, the layout has [10, 10, 10, 1], export it as qmk or zmk instead";
+
+    assert_eq!(export(&small, "colemak", "xkb"), Err(error.to_string()));
+    assert!(export(&small, "colemak", "keylayout").is_err());
+    assert!(export(&small, "colemak", "klc").is_err());
+  }
+}
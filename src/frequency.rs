@@ -1,17 +1,8 @@
 use hashbrown::HashMap;
-use once_cell::sync::Lazy;
-
-use crate::config::CONFIG;
 
 type UsageFrequencies = HashMap<String, usize>;
 pub type SymbolFrequencies = Vec<(char, usize)>;
 
-pub const CURRENT_FREQUENCIES: Lazy<SymbolFrequencies> = Lazy::new(|| {
-  calculate_frequencies(&CONFIG.data).into_iter()
-    .map(|(symbol, count)| (symbol.chars().next().unwrap(), count))
-    .collect::<SymbolFrequencies>()
-});
-
 pub fn calculate_frequencies(text: &String) -> UsageFrequencies {
   let mut usage = UsageFrequencies::new();
 
@@ -1,6 +1,10 @@
+use std::sync::Arc;
 use rayon::prelude::*;
 use once_cell::sync::OnceCell;
 use core::cmp::Ordering::Less;
+use serde::{Serialize,Deserialize};
+use rand::{Rng,SeedableRng};
+use rand::rngs::StdRng;
 
 use crate::config::*;
 use crate::layout::*;
@@ -8,10 +12,13 @@ use crate::keyboard::*;
 use crate::population::*;
 use crate::selection::*;
 use crate::summary::*;
+use crate::mutator::*;
 
 pub struct Generation {
   pub number: usize,
   pub population: Population,
+  pub seed: u64,
+  pub config: Arc<Config>,
 
   successor_cache: OnceCell<Layout>,
   best_cache: OnceCell<Layout>,
@@ -25,7 +32,7 @@ struct Result {
   deviation: f64
 }
 
-#[derive(Debug,PartialEq,Clone)]
+#[derive(Debug,PartialEq,Clone,Serialize,Deserialize)]
 pub struct Outcome {
   pub generation_number: usize,
   pub winner: Layout,
@@ -34,18 +41,50 @@ pub struct Outcome {
   pub best_summary: Summary
 }
 
+// the bits of a generation that are needed to rebuild it later
+#[derive(Debug,PartialEq,Serialize,Deserialize)]
+pub struct Snapshot {
+  pub number: usize,
+  #[serde(with = "signed_seed")]
+  pub seed: u64,
+  pub successor: Option<Layout>,
+  pub best: Option<Layout>,
+  pub members: Vec<Layout>
+}
+
+// every generation derives its own random streams from its seed, so that
+// the same seed always breeds and selects the same way
+const SELECTION: u64 = 1;
+const BREEDING: u64 = 2;
+
 impl Generation {
-  pub fn zero() -> Generation {
-    Generation::new(1, &QWERTY)
+  // the first generation out of the configured starting layouts and seed
+  pub fn zero(config: &Arc<Config>) -> std::result::Result<Generation, String> {
+    let mut rng = StdRng::seed_from_u64(config.seed.unwrap_or_else(rand::random));
+    let layouts = starting_layouts(&config.population.start, config, &mut rng)?;
+
+    Ok(Generation::first(&layouts, rng.gen(), config))
   }
 
-  pub fn new(number: usize, layout: &Layout) -> Generation {
-    Generation::spawn(number, layout, layout)
+  // the very first generation, bred from one or several starting layouts
+  pub fn first(layouts: &[Layout], seed: u64, config: &Arc<Config>) -> Generation {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    match layouts {
+      [layout] => Generation::spawn(1, layout, layout, config, &mut rng),
+      _ => {
+        let population = Population::mixed(layouts, config, &mut rng);
+        Generation::with_population(1, population, rng.gen(), config)
+      }
+    }
   }
 
-  fn spawn(number: usize, mom: &Layout, dad: &Layout) -> Generation {
-    let population = Population::new(mom, dad);
+  fn spawn(number: usize, mom: &Layout, dad: &Layout, config: &Arc<Config>, rng: &mut StdRng) -> Generation {
+    let population = Population::new(mom, dad, config, rng);
+    Generation::with_population(number, population, rng.gen(), config)
+  }
 
+  fn with_population(number: usize, population: Population, seed: u64, config: &Arc<Config>) -> Generation {
     let successor_cache: OnceCell<Layout> = OnceCell::new();
     let best_cache: OnceCell<Layout> = OnceCell::new();
     let selection_cache: OnceCell<Selection> = OnceCell::new();
@@ -54,6 +93,8 @@ impl Generation {
     Generation { 
       number, 
       population,
+      seed,
+      config: config.clone(),
 
       successor_cache,
       best_cache,
@@ -62,17 +103,47 @@ impl Generation {
     }
   }
 
+  pub fn restore(snapshot: Snapshot, config: &Arc<Config>) -> Generation {
+    let successor_cache: OnceCell<Layout> = OnceCell::new();
+    let best_cache: OnceCell<Layout> = OnceCell::new();
+
+    if let Some(layout) = snapshot.successor { successor_cache.set(layout).unwrap(); }
+    if let Some(layout) = snapshot.best { best_cache.set(layout).unwrap(); }
+
+    Generation {
+      number: snapshot.number,
+      population: Population { members: snapshot.members },
+      seed: snapshot.seed,
+      config: config.clone(),
+
+      successor_cache,
+      best_cache,
+      selection_cache: OnceCell::new(),
+      results_cache: OnceCell::new()
+    }
+  }
+
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      number: self.number,
+      seed: self.seed,
+      successor: self.successor_cache.get().cloned(),
+      best: self.best_cache.get().cloned(),
+      members: self.population.members.clone()
+    }
+  }
+
   pub fn next(self: &Self) -> Generation {
     let dad = self.successor();
-    let mom = if CONFIG.population.elites > 0 { self.best() } else { dad };
+    let mom = if self.config.population.elites > 0 { self.best() } else { dad };
 
-    Generation::spawn(self.number + 1, &dad, &mom)
+    Generation::spawn(self.number + 1, &dad, &mom, &self.config, &mut self.rng(BREEDING))
   }
 
   pub fn successor(self: &Self) -> &Layout {
     self.successor_cache.get_or_init(|| {
       let selection = self.fetch_selection();
-      let score = selection.lucky_draw();
+      let score = selection.lucky_draw(&mut self.rng(SELECTION));
       let index = selection.scores.iter()
         .position(|s| s == score).unwrap();
 
@@ -83,7 +154,7 @@ impl Generation {
   pub fn best(&self) -> &Layout {
     self.best_cache.get_or_init(|| {
       let mut ratings: Vec<(usize, f64)> = self.population.members.iter().enumerate()
-        .map(|(i, layout)| (i, self.summary_for(&layout).score())).collect();
+        .map(|(i, layout)| (i, self.summary_for(&layout).score(&self.config.weights))).collect();
       
       ratings.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Less));
       let best_rating = *ratings.first().unwrap();
@@ -109,15 +180,19 @@ impl Generation {
     result.summary.clone()
   }
 
+  fn rng(&self, stream: u64) -> StdRng {
+    StdRng::seed_from_u64(self.seed.wrapping_add(stream))
+  }
+
   fn fetch_selection(&self) -> &Selection {
     self.selection_cache.get_or_init(|| {
       let scores: Vec<Score> = self.calculate_results().iter()
           .map(|result| Score {
             deviation: result.deviation,
-            performance: result.summary.score()
+            performance: result.summary.score(&self.config.weights)
           })
           .collect();
-        Selection { scores }
+        Selection { scores, config: self.config.selection }
     })
   }
 
@@ -131,28 +206,119 @@ impl Generation {
 
   fn rate_layout(self: &Self, layout: &Layout) -> Result {
     let deviation = self.population.deviation_for(layout);
-    let keyboard = Keyboard::from(&layout, &CONFIG.geometry);
-    let summary = Summary::calculate(&keyboard);
+    let keyboard = Keyboard::from(&layout, &self.config.geometry);
+    let summary = Summary::calculate(&keyboard, &self.config);
 
     Result { deviation, summary }
   }
 }
 
+// toml integers are signed, so the seeds are stored bit for bit as i64
+mod signed_seed {
+  use serde::{Serialize,Serializer,Deserialize,Deserializer};
+
+  pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    (*seed as i64).serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    i64::deserialize(deserializer).map(|seed| seed as u64)
+  }
+}
+
+// resolves the `start` option entries, either layout filenames or `random`,
+// all of them in the geometry shape so the mutations line up with its keys
+pub fn starting_layouts(sources: &[String], config: &Config, rng: &mut StdRng) -> std::result::Result<Vec<Layout>, String> {
+  if sources.is_empty() {
+    return Err("no starting layouts given".to_string());
+  }
+
+  sources.iter().map(|source| match source.as_str() {
+    "random" => Ok(Mutator::new(&config.preserve, &config.classes, rng.gen()).shuffle(&QWERTY.reshaped(&config.geometry.shape())?)),
+    filename => Layout::read(filename).map_err(|err| err.to_string())
+      .and_then(|layout| layout.reshaped(&config.geometry.shape()))
+      .map_err(|err| format!("{}: {}", filename, err))
+  })
+  .collect()
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
 
+  fn config() -> Arc<Config> {
+    Arc::new(Config::defaults().unwrap())
+  }
+
   #[test]
   fn test_gen_zero() {
-    let generation = Generation::zero();
+    let generation = Generation::zero(&config()).unwrap();
 
     assert_eq!(generation.number, 1);
     assert_eq!(generation.population.members[0].name(), "QWERTY");
   }
 
+  #[test]
+  fn test_first_from_several_layouts() {
+    let generation = Generation::first(&[COLEMAK.clone(), WORKMAN.clone()], 42, &config());
+
+    assert_eq!(generation.number, 1);
+    assert_eq!(generation.population.members[0], COLEMAK.clone());
+    assert_eq!(generation.population.members[1], WORKMAN.clone());
+  }
+
+  #[test]
+  fn test_starting_layouts() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let config = config();
+    let layouts = starting_layouts(&["assets/layouts/colemak".to_string(), "random".to_string()], &config, &mut rng).unwrap();
+
+    assert_eq!(layouts[0], COLEMAK.clone());
+    assert_ne!(layouts[1], QWERTY.clone());
+
+    assert_eq!(starting_layouts(&[], &config, &mut rng), Err("no starting layouts given".to_string()));
+    assert!(starting_layouts(&["assets/layouts/nonexistent".to_string()], &config, &mut rng).is_err());
+  }
+
+  #[test]
+  fn reshapes_the_starting_layouts() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let config = config();
+    let directory = std::env::temp_dir().join("rusty-key-starting-layouts-test");
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let uneven = directory.join("uneven").to_string_lossy().to_string();
+    let small = directory.join("small").to_string_lossy().to_string();
+    std::fs::write(&uneven, QWERTY.reshaped(&[10, 10, 10, 10, 7]).unwrap().template).unwrap();
+    std::fs::write(&small, QWERTY.reshaped(&[10, 10, 10]).unwrap().template).unwrap();
+
+    let layouts = starting_layouts(&[uneven], &config, &mut rng).unwrap();
+    assert_eq!(layouts[0].shape(), config.geometry.shape());
+    assert_eq!(
+      starting_layouts(&[small.clone()], &config, &mut rng),
+      Err(format!("{}: QWERTY has 30 keys, the geometry needs 47", small))
+    );
+
+    std::fs::remove_dir_all(directory).unwrap();
+  }
+
+  #[test]
+  fn test_same_seed_same_evolution() {
+    let config = config();
+    let run = |seed| {
+      let generation = Generation::first(&[QWERTY.clone()], seed, &config);
+      let next_generation = generation.next();
+
+      (generation.outcome(), next_generation.outcome())
+    };
+
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+  }
+
   #[test]
   fn test_next() {
-    let generation = Generation::zero();
+    let generation = Generation::zero(&config()).unwrap();
     let next_generation = generation.next();
 
     println!("{:}", next_generation.population.members[0].template);
@@ -163,7 +329,7 @@ mod test {
 
   #[test]
   fn test_successor() {
-    let generation = Generation::zero();
+    let generation = Generation::zero(&config()).unwrap();
     let succ1 = generation.successor();
     let succ2 = generation.successor();
 
@@ -172,16 +338,42 @@ mod test {
 
   #[test]
   fn test_best() {
-    let generation = Generation::zero();
+    let generation = Generation::zero(&config()).unwrap();
     let best1 = generation.best();
     let best2 = generation.best();
 
     assert_eq!(best1, best2);
   }
 
+  #[test]
+  fn test_snapshot() {
+    let generation = Generation::zero(&config()).unwrap();
+    let snapshot = generation.snapshot();
+
+    assert_eq!(snapshot.number, 1);
+    assert_eq!(snapshot.successor, None);
+    assert_eq!(snapshot.members, generation.population.members);
+
+    let best = generation.best().clone();
+    assert_eq!(generation.snapshot().best, Some(best));
+  }
+
+  #[test]
+  fn test_restore() {
+    let generation = Generation::zero(&config()).unwrap();
+    let successor = generation.successor().clone();
+    let restored = Generation::restore(generation.snapshot(), &generation.config);
+
+    assert_eq!(restored.number, generation.number);
+    assert_eq!(restored.seed, generation.seed);
+    assert_eq!(restored.population.members, generation.population.members);
+    assert_eq!(restored.successor(), &successor);
+    assert_eq!(restored.next().number, 2);
+  }
+
   #[test]
   fn test_summary_for() {
-    let generation = Generation::zero();
+    let generation = Generation::zero(&config()).unwrap();
     let layout1 = &generation.population.members[0];
     let layout2 = &generation.population.members[29].clone();
 
@@ -192,7 +384,7 @@ mod test {
   
   // #[test]
   // fn test_outcomes() {
-  //   let generation = Generation::zero();
+  //   let generation = Generation::zero(&config()).unwrap();
   //   let outcomes = generation.outcome();
 
   //   // QWERTY is rarely the best now
@@ -1,8 +1,9 @@
 use std::fs;
+use std::path::Path;
 
 use toml;
 use serde::Deserialize;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy,OnceCell};
 
 use strum::IntoEnumIterator; // 0.17.1
 use strum_macros::EnumIter; // 0.17.1
@@ -14,16 +15,37 @@ use hashbrown::{HashMap,HashSet};
 pub static US_PC_KEYBOARD: Lazy<Geometry> = Lazy::new(||{ Geometry::load("./assets/geometries/us-pc.toml") });
 pub static FULL_ORTHO: Lazy<Geometry> = Lazy::new(||{ Geometry::load("./assets/geometries/full-ortho.toml") });
 
-#[derive(Deserialize,Debug,PartialEq,Eq)]
+pub const NAMES: [&str; 2] = ["US-PC", "ORTHO"];
+
+#[derive(Deserialize,Debug,Clone)]
 pub struct Geometry {
   template: String,
   fingers: String,
   hands: String,
   efforts: String,
   rolling_pairs: String,
-  bad_starters: String
+  bad_starters: String,
+
+  #[serde(skip)]
+  mappings_cache: OnceCell<Mappings>
+}
+
+// pre-parsed positions the calculator looks up on every keystroke
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Mappings {
+  pub bad_starters: HashSet<Position>,
+  pub rolling_pairs: HashSet<(Position, Position)>,
+  pub layout_keys: Vec<Vec<Position>>
 }
 
+// the standard layout, the templates with all of its keys name them after it
+const QWERTY_TEMPLATE: &str = "
+  ` 1 2 3 4 5 6 7 8 9 0 - =
+    q w e r t y u i o p [ ] \\
+    a s d f g h j k l ; '
+     z x c v b n m , . /
+";
+
 #[derive(EnumIter)]
 #[derive(Copy, Clone)]
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -62,10 +84,122 @@ pub struct Key {
 
 pub type SpecialsMapping = HashMap<SpecialSymbol, Key>;
 
+// a grid field name, its contents, what goes in its cells and how to say that
+type Grid<'a> = (&'a str, &'a String, fn(&str) -> bool, &'a str);
+
+// the same file makes the same geometry, whatever got cached already
+impl PartialEq for Geometry {
+  fn eq(&self, other: &Geometry) -> bool {
+    (&self.template, &self.fingers, &self.hands, &self.efforts, &self.rolling_pairs, &self.bad_starters) ==
+      (&other.template, &other.fingers, &other.hands, &other.efforts, &other.rolling_pairs, &other.bad_starters)
+  }
+}
+
+impl Eq for Geometry {}
+
 impl Geometry {
+  /// Reads a geometry file, panics if it's not there or broken
   pub fn load(filename: &str) -> Geometry {
-    let data = fs::read_to_string(filename).unwrap();
-    toml::from_str(&data).unwrap()
+    Geometry::read(filename).unwrap()
+  }
+
+  /// Reads and validates a geometry file
+  pub fn read(filename: &str) -> Result<Geometry, String> {
+    let data = fs::read_to_string(filename).map_err(|err| format!("{}: {}", filename, err))?;
+    let geometry: Geometry = toml::from_str(&data).map_err(|err| format!("{}: {}", filename, err))?;
+
+    geometry.validate().map_err(|err| format!("{}: {}", filename, err))?;
+
+    Ok(geometry)
+  }
+
+  /// Cross-checks the hands, fingers and efforts grids against the template
+  /// cell by cell, and looks up the rolling pairs, the bad starters and the
+  /// special keys on the template
+  pub fn validate(&self) -> Result<(), String> {
+    let template = parser::mapping_for(&self.template);
+    let on_template = |symbol: char| template.values().any(|value| *value == symbol.to_string());
+    let mut errors = vec![];
+
+    let grids: [Grid; 3] = [
+      ("hands", &self.hands, |value| value == "l" || value == "r", "l or r"),
+      ("fingers", &self.fingers, |value| ["1", "2", "3", "4", "5"].contains(&value), "1 to 5"),
+      ("efforts", &self.efforts, |value| value.parse::<usize>().is_ok(), "a whole number")
+    ];
+
+    for (field, grid, is_valid, expected) in grids.iter() {
+      let values = parser::mapping_for(grid);
+      let mut positions: Vec<&Position> = template.keys().chain(values.keys()).collect::<HashSet<_>>().into_iter().collect();
+      positions.sort();
+
+      for &(row, col) in positions {
+        let (row_number, col_number) = (row + 1, col + 1);
+
+        match (template.get(&(row, col)), values.get(&(row, col))) {
+          (Some(symbol), None) =>
+            errors.push(format!("{}: row {}, column {} has nothing for the {} key", field, row_number, col_number, symbol)),
+          (None, Some(value)) =>
+            errors.push(format!("{}: row {}, column {} has '{}', but the template has no key there", field, row_number, col_number, value)),
+          (Some(symbol), Some(value)) if !is_valid(value) =>
+            errors.push(format!("{}: row {}, column {} has '{}' for the {} key, expected {}", field, row_number, col_number, value, symbol, expected)),
+          _ => {}
+        }
+      }
+    }
+
+    for (line, word, pair) in words(&self.rolling_pairs) {
+      let chars: Vec<char> = pair.chars().take(2).collect();
+
+      if chars.len() < 2 {
+        errors.push(format!("rolling_pairs: line {}, word {} has '{}', which is not a pair of keys", line, word, pair));
+      }
+
+      for char in chars.into_iter().filter(|char| !on_template(*char)) {
+        errors.push(format!("rolling_pairs: line {}, word {} has '{}' in '{}', which is not on the template", line, word, char, pair));
+      }
+    }
+
+    for (line, word, symbol) in words(&self.bad_starters) {
+      let char = symbol.chars().next().unwrap_or_default();
+
+      if !on_template(char) {
+        errors.push(format!("bad_starters: line {}, word {} has '{}', which is not on the template", line, word, char));
+      }
+    }
+
+    // the right shift is optional, the left one stands in for it
+    for special in SpecialSymbol::iter().filter(|special| *special != SpecialSymbol::RightShift) {
+      let symbol = self.special_symbol_to_string(special);
+
+      if !template.values().any(|value| *value == symbol) {
+        errors.push(format!("template: there is no {} key, every geometry needs ⇥ ︺ ↵ and ⇧", symbol));
+      }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) }
+  }
+
+  /// One of the built-in geometries, or else a geometry file
+  pub fn find(name: &str) -> Result<Geometry, String> {
+    match Geometry::by_name(name) {
+      Some(geometry) => Ok(geometry.clone()),
+      None if Path::new(name).is_file() => Geometry::read(name),
+      None => Err(format!("unknown geometry '{}', expected one of: {}, or a geometry file", name, NAMES.join(", ")))
+    }
+  }
+
+  /// One of the built-in geometries, see `NAMES`
+  pub fn by_name(name: &str) -> Option<&'static Geometry> {
+    match name {
+      "US-PC" => Some(&*US_PC_KEYBOARD),
+      "ORTHO" => Some(&*FULL_ORTHO),
+      _ => None
+    }
+  }
+
+  /// How many layout keys there are on every row
+  pub fn shape(&self) -> Vec<usize> {
+    self.mappings().layout_keys.iter().map(Vec::len).collect()
   }
 
   pub fn key_for_layout(&self, position: Position) -> Option<Key> {
@@ -83,6 +217,40 @@ impl Geometry {
     Key { hand, finger, effort, position }
   }
 
+  /// The template symbols with their positions, row by row
+  pub fn positions(&self) -> Vec<(Position, String)> {
+    let mut positions: Vec<_> = parser::mapping_for(&self.template).into_iter().collect();
+    positions.sort();
+    positions
+  }
+
+  /// How far each template key sits from the left edge, in template characters
+  pub fn offsets(&self) -> HashMap<Position, usize> {
+    let mut offsets = HashMap::new();
+
+    for (row, line) in self.template.trim().lines().enumerate() {
+      let mut col = 0;
+      let mut previous = ' ';
+
+      for (offset, char) in line.chars().enumerate() {
+        if !char.is_whitespace() && previous.is_whitespace() {
+          offsets.insert((row, col), offset);
+          col += 1;
+        }
+
+        previous = char;
+      }
+    }
+
+    offsets
+  }
+
+  pub fn special_at(&self, position: Position) -> Option<SpecialSymbol> {
+    let symbol = parser::value_for(&self.template, position)?;
+
+    SpecialSymbol::iter().find(|special| self.special_symbol_to_string(*special) == symbol)
+  }
+
   pub fn special_keys(&self) -> SpecialsMapping {
     let mut specials: SpecialsMapping = SpecialsMapping::new();
 
@@ -105,6 +273,14 @@ impl Geometry {
     }
   }
 
+  pub fn mappings(&self) -> &Mappings {
+    self.mappings_cache.get_or_init(|| Mappings {
+      bad_starters: self.bad_starting_positions(),
+      rolling_pairs: self.rolling_position_pairs(),
+      layout_keys: self.layout_positions()
+    })
+  }
+
   pub fn bad_starting_positions(&self) -> HashSet<Position> {
     let mut positions = HashSet::new();
 
@@ -157,19 +333,35 @@ impl Geometry {
     str.to_string()
   }
 
-  // remaps a standard QUERTY layout position to the geometry template position
-  fn layout_to_geometry(&self, position_in_querty: Position) -> Option<Position> {
-    let querty = "
-      ` 1 2 3 4 5 6 7 8 9 0 - =
-        q w e r t y u i o p [ ] \\
-        a s d f g h j k l ; '
-         z x c v b n m , . /
-    ".to_string();
+  // remaps a layout position to the geometry template position
+  fn layout_to_geometry(&self, position: Position) -> Option<Position> {
+    self.mappings().layout_keys.get(position.0)?.get(position.1).copied()
+  }
+
+  // the template positions of the layout keys row by row. The templates that
+  // name all the QWERTY keys take the standard layouts, any other template
+  // has its own layout rows, made of the keys that aren't special
+  fn layout_positions(&self) -> Vec<Vec<Position>> {
+    let qwerty: Vec<Vec<Option<Position>>> = QWERTY_TEMPLATE.trim().lines()
+      .map(|line| line.split_whitespace().map(|symbol| parser::position_for(&self.template, symbol.to_string())).collect())
+      .collect();
 
-    match parser::value_for(&querty, position_in_querty) {
-      Some(letter) => parser::position_for(&self.template, letter),
-      _ => None
+    if qwerty.iter().flatten().all(Option::is_some) {
+      return qwerty.into_iter().map(|row| row.into_iter().flatten().collect()).collect();
     }
+
+    let mut rows: Vec<Vec<Position>> = vec![];
+
+    for (position, _) in self.positions() {
+      if self.special_at(position).is_some() { continue; }
+
+      match rows.last_mut() {
+        Some(row) if row[0].0 == position.0 => row.push(position),
+        _ => rows.push(vec![position])
+      }
+    }
+
+    rows
   }
 
   fn effort_for(&self, position: Position) -> usize {
@@ -199,6 +391,13 @@ impl Geometry {
   }
 }
 
+// the words of a multiline field with their line and word numbers, counted the way the grids count rows
+fn words(field: &str) -> Vec<(usize, usize, &str)> {
+  field.trim().lines().enumerate()
+    .flat_map(|(line, words)| words.split_whitespace().enumerate().map(move |(word, text)| (line + 1, word + 1, text)))
+    .collect()
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -243,6 +442,102 @@ mod test {
     }));
   }
 
+  const SPLIT_36: &str = r#"
+template = """
+q w e r t   y u i o p
+a s d f g   h j k l ;
+z x c v b   n m , . /
+    ⇥ ⇧ ︺   ↵ ⇪ '
+"""
+hands = """
+l l l l l   r r r r r
+l l l l l   r r r r r
+l l l l l   r r r r r
+    l l l   r r r
+"""
+fingers = """
+1 2 3 4 4   4 4 3 2 1
+1 2 3 4 4   4 4 3 2 1
+1 2 3 4 4   4 4 3 2 1
+    5 5 5   5 5 5
+"""
+efforts = """
+07 02 01 06 12   12 06 01 01 07
+01 00 00 00 07   07 00 00 00 01
+07 08 10 04 08   08 04 10 08 07
+      00 00 00   00 00 00
+"""
+rolling_pairs = "as"
+bad_starters = "t y"
+"#;
+
+  #[test]
+  fn takes_the_standard_shape_off_qwerty_templates() {
+    assert_eq!(US_PC_KEYBOARD.shape(), vec![13, 13, 11, 10]);
+    assert_eq!(FULL_ORTHO.shape(), vec![13, 13, 11, 10]);
+    assert_eq!(FULL_ORTHO.key_for_layout((0, 0)).map(|key| key.position), Some((4, 0)));
+  }
+
+  #[test]
+  fn takes_the_shape_off_other_templates() {
+    let geometry: Geometry = toml::from_str(SPLIT_36).unwrap();
+
+    assert_eq!(geometry.shape(), vec![10, 10, 10, 1]);
+    assert_eq!(geometry.key_for_layout((1, 5)).map(|key| key.position), Some((1, 5)));
+    assert_eq!(geometry.key_for_layout((3, 0)), Some(Key { position: (3, 5), hand: Hand::Right, finger: Finger::Thumb, effort: 0 }));
+    assert_eq!(geometry.key_for_layout((3, 1)), None);
+  }
+
+  #[test]
+  fn validates_the_grids() {
+    assert_eq!(US_PC_KEYBOARD.validate(), Ok(()));
+    assert_eq!(FULL_ORTHO.validate(), Ok(()));
+    assert_eq!(toml::from_str::<Geometry>(SPLIT_36).unwrap().validate(), Ok(()));
+
+    let broken = SPLIT_36
+      .replace("    l l l   r r r\n", "    l l x   r r r r\n")
+      .replace("    5 5 5   5 5 5\n", "    5 5 5   5 5\n")
+      .replace("07 08 10 04 08", "07 08 10 04 eight");
+    let geometry: Geometry = toml::from_str(&broken).unwrap();
+
+    assert_eq!(geometry.validate(), Err([
+      "hands: row 4, column 3 has 'x' for the ︺ key, expected l or r",
+      "hands: row 4, column 7 has 'r', but the template has no key there",
+      "fingers: row 4, column 6 has nothing for the ' key",
+      "efforts: row 3, column 5 has 'eight' for the b key, expected a whole number"
+    ].join("; ")));
+  }
+
+  #[test]
+  fn validates_the_symbols() {
+    let broken = SPLIT_36
+      .replace("rolling_pairs = \"as\"", "rolling_pairs = \"as a ñs\"")
+      .replace("bad_starters = \"t y\"", "bad_starters = \"t y é\"")
+      .replace("↵", "⏎");
+    let geometry: Geometry = toml::from_str(&broken).unwrap();
+
+    assert_eq!(geometry.validate(), Err([
+      "rolling_pairs: line 1, word 2 has 'a', which is not a pair of keys",
+      "rolling_pairs: line 1, word 3 has 'ñ' in 'ñs', which is not on the template",
+      "bad_starters: line 1, word 3 has 'é', which is not on the template",
+      "template: there is no ↵ key, every geometry needs ⇥ ︺ ↵ and ⇧"
+    ].join("; ")));
+  }
+
+  #[test]
+  fn finds_geometry_files() {
+    assert_eq!(Geometry::find("ORTHO").as_ref(), Ok(&*FULL_ORTHO));
+    assert_eq!(Geometry::find("./assets/geometries/us-pc.toml").as_ref(), Ok(&*US_PC_KEYBOARD));
+    assert_eq!(Geometry::find("blah"), Err("unknown geometry 'blah', expected one of: US-PC, ORTHO, or a geometry file".to_string()));
+  }
+
+  #[test]
+  fn finds_geometries_by_name() {
+    assert_eq!(Geometry::by_name("US-PC"), Some(&*US_PC_KEYBOARD));
+    assert_eq!(Geometry::by_name("ORTHO"), Some(&*FULL_ORTHO));
+    assert_eq!(Geometry::by_name("blah"), None);
+  }
+
   #[test]
   fn returns_none_if_not_found() {
     assert_eq!(US_PC_KEYBOARD.key_for_layout((9,9)), None);
@@ -270,6 +565,30 @@ mod test {
     });
   }
 
+  #[test]
+  fn walks_the_template() {
+    let positions = FULL_ORTHO.positions();
+
+    assert_eq!(positions.len(), 52);
+    assert_eq!(positions[0], ((0, 0), "1".to_string()));
+    assert_eq!(positions[51], ((4, 5), "⇥".to_string()));
+
+    assert_eq!(FULL_ORTHO.special_at((4, 2)), Some(SpecialSymbol::Space));
+    assert_eq!(FULL_ORTHO.special_at((4, 0)), None);
+    assert_eq!(FULL_ORTHO.special_at((9, 9)), None);
+  }
+
+  #[test]
+  fn finds_the_offsets() {
+    let offsets = FULL_ORTHO.offsets();
+
+    assert_eq!(offsets.len(), 52);
+    assert_eq!(offsets[&(0, 4)], 8);
+    assert_eq!(offsets[&(0, 5)], 12);
+    assert_eq!(offsets[&(4, 0)], 3);
+    assert_eq!(US_PC_KEYBOARD.offsets()[&(2, 0)], 2);
+  }
+
   #[test]
   fn calculate_bad_startes() {
     assert_eq!(US_PC_KEYBOARD.bad_starting_positions(), set! [
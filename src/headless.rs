@@ -0,0 +1,91 @@
+/**
+ * Runs without the TUI, streaming each generation outcome as a line of JSON.
+ * Once the evolution stops, the last line carries the reason and the best layout.
+ * A resumed run starts the stream after the outcomes it replays from the checkpoint
+ */
+use std::fs::File;
+use std::io::{self,Write};
+
+use serde_json;
+
+use crate::events::{self,Event};
+use rusty_key::generation::Outcome;
+use rusty_key::stopping::Finish;
+
+pub fn run(output: &Option<String>, replayed: usize) -> Result<(), Box<dyn std::error::Error>> {
+  let mut writer: Box<dyn Write> = match output {
+    Some(filename) => Box::new(File::create(filename)?),
+    None => Box::new(io::stdout())
+  };
+  let mut skipped = 0;
+
+  loop {
+    match events::inst().next()? {
+      Event::Result(_) if skipped < replayed => skipped += 1,
+      Event::Result(outcome) => {
+        writeln!(writer, "{}", to_json(&outcome)?)?;
+        writer.flush()?;
+      },
+      Event::Finished(finish) => {
+        writeln!(writer, "{}", finish_to_json(&finish)?)?;
+        writer.flush()?;
+        eprintln!("stopped at generation {}: {}", finish.generation_number, finish.reason);
+        return Ok(());
+      },
+      Event::Warning(message) => eprintln!("{}", message),
+      _ => {}
+    }
+  }
+}
+
+pub fn to_json(outcome: &Outcome) -> Result<String, serde_json::Error> {
+  serde_json::to_string(outcome)
+}
+
+pub fn finish_to_json(finish: &Finish) -> Result<String, serde_json::Error> {
+  serde_json::to_string(finish)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use rusty_key::layout::*;
+  use rusty_key::summary::*;
+  use rusty_key::stopping::Reason;
+
+  #[test]
+  fn formats_outcomes_as_json() {
+    let summary = Summary { effort: 1.5, overheads: 2.5, awkwardness: 3.5, rollingness: 4.5, fitness: 5.5 };
+    let outcome = Outcome {
+      generation_number: 7,
+      winner: Layout::new("Q W\nq w".to_string()),
+      winner_summary: summary.clone(),
+      best: Layout::new("A S\na s".to_string()),
+      best_summary: summary
+    };
+
+    assert_eq!(to_json(&outcome).unwrap(), concat!(
+      r#"{"generation_number":7,"#,
+      r#""winner":{"template":"Q W\nq w"},"#,
+      r#""winner_summary":{"effort":1.5,"overheads":2.5,"awkwardness":3.5,"rollingness":4.5,"fitness":5.5},"#,
+      r#""best":{"template":"A S\na s"},"#,
+      r#""best_summary":{"effort":1.5,"overheads":2.5,"awkwardness":3.5,"rollingness":4.5,"fitness":5.5}}"#
+    ));
+  }
+
+  #[test]
+  fn formats_finishes_as_json() {
+    let finish = Finish {
+      reason: Reason::Stagnation,
+      generation_number: 12,
+      best: Layout::new("A S\na s".to_string()),
+      best_summary: Summary { effort: 1.5, overheads: 2.5, awkwardness: 3.5, rollingness: 4.5, fitness: 5.5 }
+    };
+
+    assert_eq!(finish_to_json(&finish).unwrap(), concat!(
+      r#"{"reason":"stagnation","generation_number":12,"#,
+      r#""best":{"template":"A S\na s"},"#,
+      r#""best_summary":{"effort":1.5,"overheads":2.5,"awkwardness":3.5,"rollingness":4.5,"fitness":5.5}}"#
+    ));
+  }
+}
@@ -0,0 +1,87 @@
+/**
+ * Turns layouts from other formats into layout files, and keyboard-layout-editor
+ * files into geometry files
+ */
+use std::fs;
+use std::path::Path;
+use rusty_key::{xkb,klc,kle,Config};
+use rusty_key::importer::Import;
+use crate::cli::Importing;
+
+pub fn run(importing: &Importing, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+  let format = importing.format.clone().unwrap_or_else(|| format_for(&importing.path).to_string());
+  let data = decode(&fs::read(&importing.path)?)?;
+
+  if importing.geometry {
+    if format != "kle" {
+      return Err(format!("geometries come from keyboard-layout-editor files only, not {}", format).into());
+    }
+
+    print!("{}", kle::geometry(&data, &config.distances)?);
+    return Ok(());
+  }
+
+  let imported = import(&data, &format)?;
+
+  for warning in imported.warnings.iter() {
+    eprintln!("warning: {}", warning);
+  }
+
+  println!("{}", imported.layout.template);
+
+  Ok(())
+}
+
+pub fn import(data: &str, format: &str) -> Result<Import, String> {
+  match format {
+    "xkb" => xkb::import(data),
+    "klc" => klc::import(data),
+    "kle" => kle::import(data),
+    _ => Err(format!("unknown import format '{}', expected one of xkb, klc, kle", format))
+  }
+}
+
+// the xkb symbols files usually have no extension at all
+fn format_for(path: &str) -> &str {
+  match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+    Some("klc") => "klc",
+    Some("json") => "kle",
+    _ => "xkb"
+  }
+}
+
+// the keyboard layout creator saves files in UTF-16
+fn decode(bytes: &[u8]) -> Result<String, String> {
+  match bytes {
+    [0xFF, 0xFE, rest @ ..] => {
+      let units: Vec<u16> = rest.chunks(2).map(|pair| u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0)])).collect();
+      String::from_utf16(&units).map_err(|err| err.to_string())
+    },
+    _ => String::from_utf8(bytes.to_vec()).map_err(|err| err.to_string())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use rusty_key::layout::*;
+
+  #[test]
+  fn guesses_the_format() {
+    assert_eq!(format_for("colemak.klc"), "klc");
+    assert_eq!(format_for("colemak.json"), "kle");
+    assert_eq!(format_for("/usr/share/X11/xkb/symbols/us"), "xkb");
+  }
+
+  #[test]
+  fn decodes_utf16() {
+    assert_eq!(decode(&[0xFF, 0xFE, b'K', 0, b'B', 0]), Ok("KB".to_string()));
+    assert_eq!(decode(b"KB"), Ok("KB".to_string()));
+  }
+
+  #[test]
+  fn imports_known_formats() {
+    assert_eq!(import(&klc::export(&COLEMAK, "colemak"), "klc").unwrap().layout.entries(), COLEMAK.entries());
+    assert_eq!(import("", "blah"), Err("unknown import format 'blah', expected one of xkb, klc, kle".to_string()));
+  }
+}
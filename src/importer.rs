@@ -0,0 +1,99 @@
+/**
+ * Puts the symbols read from other layout formats into a `Layout`. The
+ * keys that aren't there keep their QWERTY symbols, and everything that
+ * doesn't fit into the 47 keys ends up in the warnings
+ */
+use hashbrown::{HashMap,HashSet};
+
+use crate::layout::*;
+use crate::parser::Position;
+
+#[derive(Debug,PartialEq)]
+pub struct Import {
+  pub layout: Layout,
+  pub warnings: Vec<String>
+}
+
+/// Fills the gaps with QWERTY and makes sure every symbol shows up once
+pub fn complete(symbols: HashMap<Position, (String, String)>, mut warnings: Vec<String>) -> Result<Import, String> {
+  let mut entries = vec![];
+  let mut missing = vec![];
+
+  for entry in QWERTY.entries() {
+    match symbols.get(&entry.position) {
+      Some((normal, shifted)) if is_symbol(normal) && is_symbol(shifted) => entries.push(Entry {
+        normal: normal.to_string(),
+        shifted: shifted.to_string(),
+        position: entry.position
+      }),
+      Some((normal, shifted)) => {
+        warnings.push(format!("can't put '{}' and '{}' on the QWERTY '{}' key", normal, shifted, entry.normal));
+        missing.push(entry.normal.clone());
+        entries.push(entry);
+      },
+      None => {
+        missing.push(entry.normal.clone());
+        entries.push(entry);
+      }
+    }
+  }
+
+  if !missing.is_empty() {
+    warnings.push(format!("kept the QWERTY symbols on {}", missing.join(" ")));
+  }
+
+  let mut seen = HashSet::new();
+
+  for entry in entries.iter() {
+    for symbol in [&entry.normal, &entry.shifted].iter() {
+      if !seen.insert(symbol.to_string()) {
+        return Err(format!("'{}' shows up on more than one key", symbol));
+      }
+    }
+  }
+
+  Ok(Import { layout: Layout::from_entries(&entries), warnings })
+}
+
+// the layout files have a single visible character per symbol
+fn is_symbol(symbol: &str) -> bool {
+  let mut chars = symbol.chars();
+
+  matches!((chars.next(), chars.next()), (Some(char), None) if !char.is_whitespace() && !char.is_control())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn symbols(list: &[((usize, usize), &str, &str)]) -> HashMap<Position, (String, String)> {
+    list.iter().map(|(position, normal, shifted)| (*position, (normal.to_string(), shifted.to_string()))).collect()
+  }
+
+  #[test]
+  fn takes_complete_layouts_as_they_are() {
+    let all = COLEMAK.entries().into_iter().map(|entry| (entry.position, (entry.normal, entry.shifted))).collect();
+
+    assert_eq!(complete(all, vec![]), Ok(Import { layout: Layout::from_entries(&COLEMAK.entries()), warnings: vec![] }));
+  }
+
+  #[test]
+  fn fills_the_gaps_with_qwerty() {
+    let import = complete(symbols(&[((1, 0), "é", "É"), ((1, 1), " ", "W")]), vec!["blah".to_string()]).unwrap();
+
+    assert_eq!(import.layout.entries()[13].normal, "é");
+    assert_eq!(import.layout.entries()[14].normal, "w");
+    assert_eq!(import.layout.entries()[15].normal, "e");
+    assert_eq!(import.warnings, vec![
+      "blah".to_string(),
+      "can't put ' ' and 'W' on the QWERTY 'w' key".to_string(),
+      format!("kept the QWERTY symbols on {}", QWERTY.entries().iter()
+        .filter(|entry| entry.normal != "q").map(|entry| entry.normal.as_str()).collect::<Vec<_>>().join(" "))
+    ]);
+  }
+
+  #[test]
+  fn fails_on_duplicates() {
+    assert_eq!(complete(symbols(&[((1, 0), "w", "Q")]), vec![]), Err("'w' shows up on more than one key".to_string()));
+  }
+}
@@ -15,15 +15,38 @@ pub struct Keyboard<'a> {
   pub key_map: KeyMap
 }
 
+// the normal symbols where the geometry template has the keys, the special keys stay blank
 impl fmt::Display for Keyboard<'_> {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.layout.to_string(*self.geometry == *US_PC_KEYBOARD))
+    let offsets = self.geometry.offsets();
+    let mut rows: Vec<Vec<(usize, String)>> = vec![];
+
+    for entry in self.layout.entries() {
+      if let Some(key) = self.geometry.key_for_layout(entry.position) {
+        let (row, offset) = (key.position.0, offsets[&key.position]);
+
+        if rows.len() <= row { rows.resize(row + 1, vec![]); }
+        rows[row].push((offset, entry.normal));
+      }
+    }
+
+    let lines: Vec<String> = rows.into_iter().filter(|keys| !keys.is_empty()).map(|mut keys| {
+      keys.sort();
+
+      keys.into_iter().fold(String::new(), |line, (offset, symbol)| {
+        let gap = if line.is_empty() { offset } else { offset.saturating_sub(line.chars().count()).max(1) };
+        format!("{}{}{}", line, " ".repeat(gap), symbol)
+      })
+    }).collect();
+
+    write!(f, "{}", lines.join("\n"))
   }
 }
 
 impl Keyboard<'_> {
+  /// Puts the layout symbols onto the geometry keys
   pub fn from<'a>(layout: &'a Layout, geometry: &'a Geometry) -> Keyboard<'a> {
-    let name = layout.name();
+    let name = layout.metadata.name.clone().unwrap_or_else(|| layout.name());
     let keys = Self::keys_from(layout, geometry);
     
     Keyboard { name, layout, geometry, key_map: keys }
@@ -92,6 +115,25 @@ mod test {
     assert_eq!(letters, "qwertyuiop");
   }
 
+  #[test]
+  fn prints_the_way_the_geometry_goes() {
+    let layout = QWERTY.clone();
+
+    assert_eq!(format!("{}", Keyboard::from(&layout, &US_PC_KEYBOARD)), [
+      "` 1 2 3 4 5 6 7 8 9 0 - =",
+      "  q w e r t y u i o p [ ] \\",
+      "  a s d f g h j k l ; '",
+      "   z x c v b n m , . /"
+    ].join("\n"));
+    assert_eq!(format!("{}", Keyboard::from(&layout, &FULL_ORTHO)), [
+      "1 2 3 4 5   6 7 8 9 0 - =",
+      "q w e r t   y u i o p [ ]",
+      "a s d f g   h j k l ; ' \\",
+      "z x c v b   n m , . /",
+      "   `"
+    ].join("\n"));
+  }
+
   #[test]
   fn builds_correct_key_mapping() {
     let layout = QWERTY.clone();
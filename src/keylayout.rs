@@ -0,0 +1,151 @@
+/**
+ * Writes layouts as macOS `.keylayout` files. Drop the result into
+ * `~/Library/Keyboard Layouts/` and pick it in the input sources
+ */
+use crate::layout::*;
+use crate::parser::Position;
+
+// the keys that don't change between layouts, so the layout is usable on its own
+const FIXED_KEYS: [(u8, &str); 6] = [
+  (36, "\r"),     // return
+  (48, "\t"),     // tab
+  (49, " "),      // space
+  (51, "\u{8}"),  // delete
+  (53, "\u{1b}"), // escape
+  (76, "\u{3}")   // enter
+];
+
+/// The `.keylayout` XML document for the layout, with `name` as its name
+pub fn export(layout: &Layout, name: &str) -> String {
+  let entries = layout.entries();
+  let normal: Vec<(u8, &str)> = entries.iter()
+    .filter_map(|entry| Some((key_code_for(entry.position)?, entry.normal.as_str())))
+    .collect();
+  let shifted: Vec<(u8, &str)> = entries.iter()
+    .filter_map(|entry| Some((key_code_for(entry.position)?, entry.shifted.as_str())))
+    .collect();
+
+  let mut lines = vec![
+    "<?xml version=\"1.1\" encoding=\"UTF-8\"?>".to_string(),
+    "<!DOCTYPE keyboard SYSTEM \"file://localhost/System/Library/DTDs/KeyboardLayout.dtd\">".to_string()
+  ];
+
+  // xml comments can't have double dashes in them
+  lines.extend(layout.metadata.notes().iter().map(|note| format!("<!-- {} -->", note.replace("--", "- -"))));
+
+  lines.extend(vec![
+    format!("<keyboard group=\"126\" id=\"{}\" name=\"{}\" maxout=\"1\">", id_for(name), escape(name)),
+    "  <layouts>".to_string(),
+    "    <layout first=\"0\" last=\"17\" modifiers=\"modifiers\" mapSet=\"ANSI\"/>".to_string(),
+    "  </layouts>".to_string(),
+    "  <modifierMap id=\"modifiers\" defaultIndex=\"0\">".to_string(),
+    "    <keyMapSelect mapIndex=\"0\">".to_string(),
+    "      <modifier keys=\"\"/>".to_string(),
+    "    </keyMapSelect>".to_string(),
+    "    <keyMapSelect mapIndex=\"1\">".to_string(),
+    "      <modifier keys=\"anyShift caps?\"/>".to_string(),
+    "    </keyMapSelect>".to_string(),
+    "  </modifierMap>".to_string(),
+    "  <keyMapSet id=\"ANSI\">".to_string()
+  ]);
+
+  for (index, keys) in [normal, shifted].iter().enumerate() {
+    lines.push(format!("    <keyMap index=\"{}\">", index));
+
+    let mut keys: Vec<&(u8, &str)> = keys.iter().chain(FIXED_KEYS.iter()).collect();
+    keys.sort_by_key(|(code, _)| *code);
+
+    for (code, output) in keys {
+      lines.push(format!("      <key code=\"{}\" output=\"{}\"/>", code, escape(output)));
+    }
+
+    lines.push("    </keyMap>".to_string());
+  }
+
+  lines.push("  </keyMapSet>".to_string());
+  lines.push("</keyboard>".to_string());
+  lines.join("\n") + "\n"
+}
+
+// the ANSI virtual key codes of the number, top, home and bottom rows
+const KEY_CODES: [&[u8]; 4] = [
+  &[50, 18, 19, 20, 21, 23, 22, 26, 28, 25, 29, 27, 24],
+  &[12, 13, 14, 15, 17, 16, 32, 34, 31, 35, 33, 30, 42],
+  &[0, 1, 2, 3, 5, 4, 38, 40, 37, 41, 39],
+  &[6, 7, 8, 9, 11, 45, 46, 43, 47, 44]
+];
+
+fn key_code_for((row, col): Position) -> Option<u8> {
+  KEY_CODES.get(row)?.get(col).copied()
+}
+
+// custom layouts have to have negative ids that don't clash with the system ones
+fn id_for(name: &str) -> i32 {
+  let hash = name.bytes().fold(0u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+
+  -((hash % 30000) as i32) - 2
+}
+
+// markup and control characters go as character references
+fn escape(text: &str) -> String {
+  text.chars().map(|char| match char {
+    '&' | '<' | '>' | '"' | '\'' => format!("&#x{:04X};", char as u32),
+    _ if char.is_control() => format!("&#x{:04X};", char as u32),
+    _ => char.to_string()
+  }).collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn key_maps(document: &str) -> Vec<Vec<String>> {
+    document.split("<keyMap index=").skip(1).map(|key_map| {
+      key_map.lines().filter(|line| line.trim().starts_with("<key code=")).map(|line| line.trim().to_string()).collect()
+    }).collect()
+  }
+
+  #[test]
+  fn maps_positions_to_key_codes() {
+    assert_eq!(key_code_for((0, 0)), Some(50));
+    assert_eq!(key_code_for((1, 0)), Some(12));
+    assert_eq!(key_code_for((2, 3)), Some(3));
+    assert_eq!(key_code_for((3, 9)), Some(44));
+    assert_eq!(key_code_for((3, 10)), None);
+    assert_eq!(key_code_for((4, 0)), None);
+  }
+
+  #[test]
+  fn escapes_markup() {
+    assert_eq!(escape("a<b>&\"'"), "a&#x003C;b&#x003E;&#x0026;&#x0022;&#x0027;");
+    assert_eq!(escape("\t"), "&#x0009;");
+    assert_eq!(escape("é"), "é");
+  }
+
+  #[test]
+  fn exports_layouts() {
+    let document = export(&COLEMAK, "Colemak");
+    let lines: Vec<&str> = document.lines().collect();
+
+    assert_eq!(lines[0], "<?xml version=\"1.1\" encoding=\"UTF-8\"?>");
+    assert!(lines[2].starts_with("<keyboard group=\"126\" id=\"-"));
+    assert!(lines[2].contains("name=\"Colemak\""));
+    assert_eq!(lines.last(), Some(&"</keyboard>"));
+
+    for tag in ["keyboard", "layouts", "modifierMap", "keyMapSelect", "keyMapSet", "keyMap"].iter() {
+      assert_eq!(document.matches(&format!("<{} ", tag)).count() + document.matches(&format!("<{}>", tag)).count(),
+        document.matches(&format!("</{}>", tag)).count(), "unbalanced <{}>", tag);
+    }
+
+    let key_maps = key_maps(&document);
+    assert_eq!(key_maps.len(), 2);
+    assert_eq!(key_maps[0].len(), 47 + FIXED_KEYS.len());
+    assert_eq!(key_maps[1].len(), 47 + FIXED_KEYS.len());
+
+    assert!(key_maps[0].contains(&"<key code=\"14\" output=\"f\"/>".to_string()));
+    assert!(key_maps[1].contains(&"<key code=\"14\" output=\"F\"/>".to_string()));
+    assert!(key_maps[0].contains(&"<key code=\"41\" output=\"o\"/>".to_string()));
+    assert!(key_maps[1].contains(&"<key code=\"39\" output=\"&#x0022;\"/>".to_string()));
+    assert!(key_maps[0].contains(&"<key code=\"49\" output=\" \"/>".to_string()));
+  }
+}
@@ -0,0 +1,404 @@
+/**
+ * Writes layouts as firmware keymaps for programmable keyboards, a QMK
+ * `keymap.c` or a ZMK `.keymap`, walking the geometry template keys.
+ * The host is expected to run the US layout, so the keys whose shifted
+ * symbol isn't the US one become QMK custom keycodes or ZMK mod-morphs
+ */
+use hashbrown::HashMap;
+
+use crate::layout::*;
+use crate::geometry::*;
+
+// a US key, its QMK name and the ZMK names of both of its symbols
+struct UsKey {
+  normal: char,
+  shifted: char,
+  qmk: &'static str,
+  zmk: &'static str,
+  zmk_shifted: &'static str
+}
+
+const US_KEYS: [UsKey; 21] = [
+  UsKey { normal: '`', shifted: '~', qmk: "GRV", zmk: "GRAVE", zmk_shifted: "TILDE" },
+  UsKey { normal: '1', shifted: '!', qmk: "1", zmk: "N1", zmk_shifted: "EXCLAMATION" },
+  UsKey { normal: '2', shifted: '@', qmk: "2", zmk: "N2", zmk_shifted: "AT_SIGN" },
+  UsKey { normal: '3', shifted: '#', qmk: "3", zmk: "N3", zmk_shifted: "HASH" },
+  UsKey { normal: '4', shifted: '$', qmk: "4", zmk: "N4", zmk_shifted: "DOLLAR" },
+  UsKey { normal: '5', shifted: '%', qmk: "5", zmk: "N5", zmk_shifted: "PERCENT" },
+  UsKey { normal: '6', shifted: '^', qmk: "6", zmk: "N6", zmk_shifted: "CARET" },
+  UsKey { normal: '7', shifted: '&', qmk: "7", zmk: "N7", zmk_shifted: "AMPERSAND" },
+  UsKey { normal: '8', shifted: '*', qmk: "8", zmk: "N8", zmk_shifted: "ASTERISK" },
+  UsKey { normal: '9', shifted: '(', qmk: "9", zmk: "N9", zmk_shifted: "LEFT_PARENTHESIS" },
+  UsKey { normal: '0', shifted: ')', qmk: "0", zmk: "N0", zmk_shifted: "RIGHT_PARENTHESIS" },
+  UsKey { normal: '-', shifted: '_', qmk: "MINS", zmk: "MINUS", zmk_shifted: "UNDERSCORE" },
+  UsKey { normal: '=', shifted: '+', qmk: "EQL", zmk: "EQUAL", zmk_shifted: "PLUS" },
+  UsKey { normal: '[', shifted: '{', qmk: "LBRC", zmk: "LEFT_BRACKET", zmk_shifted: "LEFT_BRACE" },
+  UsKey { normal: ']', shifted: '}', qmk: "RBRC", zmk: "RIGHT_BRACKET", zmk_shifted: "RIGHT_BRACE" },
+  UsKey { normal: '\\', shifted: '|', qmk: "BSLS", zmk: "BACKSLASH", zmk_shifted: "PIPE" },
+  UsKey { normal: ';', shifted: ':', qmk: "SCLN", zmk: "SEMICOLON", zmk_shifted: "COLON" },
+  UsKey { normal: '\'', shifted: '"', qmk: "QUOT", zmk: "SINGLE_QUOTE", zmk_shifted: "DOUBLE_QUOTES" },
+  UsKey { normal: ',', shifted: '<', qmk: "COMM", zmk: "COMMA", zmk_shifted: "LESS_THAN" },
+  UsKey { normal: '.', shifted: '>', qmk: "DOT", zmk: "PERIOD", zmk_shifted: "GREATER_THAN" },
+  UsKey { normal: '/', shifted: '?', qmk: "SLSH", zmk: "SLASH", zmk_shifted: "QUESTION" }
+];
+
+/// The keymap source, and what it couldn't bind
+#[derive(Debug,PartialEq)]
+pub struct Keymap {
+  pub source: String,
+  pub warnings: Vec<String>
+}
+
+// what a template key does, either one of the US keys or a shift-dependent pair
+#[derive(Debug,PartialEq)]
+enum Binding {
+  Plain(String, String),
+  Custom(char, char),
+  Special(SpecialSymbol),
+  None
+}
+
+/// The QMK `keymap.c` with a single layer for the layout on the geometry
+pub fn qmk(layout: &Layout, geometry: &Geometry, name: &str) -> Keymap {
+  let rows = bindings_for(layout, geometry);
+  let customs = customs_in(&rows);
+
+  let mut lines = header_for(layout, name);
+  lines.extend(vec![
+    "#include QMK_KEYBOARD_H".to_string(),
+    "".to_string()
+  ]);
+
+  if !customs.is_empty() {
+    lines.push("enum custom_keycodes {".to_string());
+    for (index, (normal, shifted)) in customs.iter().enumerate() {
+      let value = if index == 0 { " = SAFE_RANGE" } else { "" };
+      lines.push(format!("  RK_{}{}, // {} {}", index, value, normal, shifted));
+    }
+    lines.push("  RK_END".to_string());
+    lines.push("};".to_string());
+    lines.push("".to_string());
+    lines.push("// what the custom keycodes send without and with shift".to_string());
+    lines.push(format!("static const uint16_t rk_normal[] = {{ {} }};", customs.iter().map(|(normal, _)| qmk_code_for(*normal)).collect::<Vec<_>>().join(", ")));
+    lines.push(format!("static const uint16_t rk_shifted[] = {{ {} }};", customs.iter().map(|(_, shifted)| qmk_code_for(*shifted)).collect::<Vec<_>>().join(", ")));
+    lines.push("".to_string());
+  }
+
+  let rows: Vec<String> = rows.iter().map(|row| {
+    let codes: Vec<String> = row.iter().map(|binding| match binding {
+      Binding::Plain(code, _) => format!("KC_{}", code),
+      Binding::Custom(normal, shifted) => format!("RK_{}", customs.iter().position(|pair| *pair == (*normal, *shifted)).unwrap()),
+      Binding::Special(special) => qmk_special_for(special).to_string(),
+      Binding::None => "KC_NO".to_string()
+    }).collect();
+
+    format!("    {}", codes.join(", "))
+  }).collect();
+
+  lines.push("const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {".to_string());
+  lines.push("  [0] = LAYOUT(".to_string());
+  lines.push(rows.join(",\n"));
+  lines.push("  )".to_string());
+  lines.push("};".to_string());
+
+  if !customs.is_empty() {
+    lines.extend([
+      "",
+      "bool process_record_user(uint16_t keycode, keyrecord_t *record) {",
+      "  if (keycode < RK_0 || keycode >= RK_END) {",
+      "    return true;",
+      "  }",
+      "",
+      "  if (record->event.pressed) {",
+      "    const uint8_t mods = get_mods();",
+      "    const uint8_t index = keycode - RK_0;",
+      "",
+      "    del_mods(MOD_MASK_SHIFT);",
+      "    tap_code16((mods & MOD_MASK_SHIFT) ? rk_shifted[index] : rk_normal[index]);",
+      "    set_mods(mods);",
+      "  }",
+      "",
+      "  return false;",
+      "}"
+    ].iter().map(|line| line.to_string()));
+  }
+
+  Keymap { source: lines.join("\n") + "\n", warnings: warnings_for(&customs, "KC_NO") }
+}
+
+/// The ZMK `.keymap` with a single layer for the layout on the geometry
+pub fn zmk(layout: &Layout, geometry: &Geometry, name: &str) -> Keymap {
+  let rows = bindings_for(layout, geometry);
+  let customs = customs_in(&rows);
+
+  let mut lines = header_for(layout, name);
+  lines.extend(vec![
+    "#include <behaviors.dtsi>".to_string(),
+    "#include <dt-bindings/zmk/keys.h>".to_string(),
+    "".to_string(),
+    "/ {".to_string()
+  ]);
+
+  if !customs.is_empty() {
+    lines.push("    behaviors {".to_string());
+    for (index, (normal, shifted)) in customs.iter().enumerate() {
+      lines.push(format!("        rk_{}: rk_{} {{ // {} {}", index, index, normal, shifted));
+      lines.push("            compatible = \"zmk,behavior-mod-morph\";".to_string());
+      lines.push("            #binding-cells = <0>;".to_string());
+      lines.push(format!("            bindings = <{}>, <{}>;", zmk_code_for(*normal), zmk_code_for(*shifted)));
+      lines.push("            mods = <(MOD_LSFT|MOD_RSFT)>;".to_string());
+      lines.push("        };".to_string());
+    }
+    lines.push("    };".to_string());
+    lines.push("".to_string());
+  }
+
+  lines.push("    keymap {".to_string());
+  lines.push("        compatible = \"zmk,keymap\";".to_string());
+  lines.push("".to_string());
+  lines.push("        default_layer {".to_string());
+  lines.push("            bindings = <".to_string());
+
+  for row in rows.iter() {
+    let codes: Vec<String> = row.iter().map(|binding| match binding {
+      Binding::Plain(_, code) => format!("&kp {}", code),
+      Binding::Custom(normal, shifted) => format!("&rk_{}", customs.iter().position(|pair| *pair == (*normal, *shifted)).unwrap()),
+      Binding::Special(special) => format!("&kp {}", zmk_special_for(special)),
+      Binding::None => "&none".to_string()
+    }).collect();
+
+    lines.push(format!("                {}", codes.join(" ")));
+  }
+
+  lines.push("            >;".to_string());
+  lines.push("        };".to_string());
+  lines.push("    };".to_string());
+  lines.push("};".to_string());
+
+  Keymap { source: lines.join("\n") + "\n", warnings: warnings_for(&customs, "&none") }
+}
+
+// both keymaps start with the name and the layout notes as line comments
+fn header_for(layout: &Layout, name: &str) -> Vec<String> {
+  let mut lines = vec![format!("// {}, generated by rusty-key", name)];
+  lines.extend(layout.metadata.notes().iter().map(|note| format!("// {}", note)));
+  lines
+}
+
+// the bindings for every template key, row by row
+fn bindings_for(layout: &Layout, geometry: &Geometry) -> Vec<Vec<Binding>> {
+  let mut entries = HashMap::new();
+
+  for entry in layout.entries() {
+    if let Some(key) = geometry.key_for_layout(entry.position) {
+      entries.insert(key.position, entry);
+    }
+  }
+
+  let mut rows: Vec<Vec<Binding>> = vec![];
+
+  for (position, _) in geometry.positions() {
+    let binding = match (entries.get(&position), geometry.special_at(position)) {
+      (Some(entry), _) => binding_for(&entry.normal, &entry.shifted),
+      (None, Some(special)) => Binding::Special(special),
+      (None, None) => Binding::None
+    };
+
+    match rows.get_mut(position.0) {
+      Some(row) => row.push(binding),
+      None => rows.push(vec![binding])
+    }
+  }
+
+  rows
+}
+
+fn binding_for(normal: &str, shifted: &str) -> Binding {
+  let normal = normal.chars().next().unwrap_or(' ');
+  let shifted = shifted.chars().next().unwrap_or(' ');
+
+  match us_key_for(normal) {
+    Some((key, false)) if key.shifted == shifted => Binding::Plain(key.qmk.to_string(), key.zmk.to_string()),
+    None if normal.is_ascii_lowercase() && shifted == normal.to_ascii_uppercase() => {
+      let name = shifted.to_string();
+      Binding::Plain(name.clone(), name)
+    },
+    _ => Binding::Custom(normal, shifted)
+  }
+}
+
+// the distinct shift-dependent pairs in the order they show up
+fn customs_in(rows: &[Vec<Binding>]) -> Vec<(char, char)> {
+  let mut customs = vec![];
+
+  for binding in rows.iter().flatten() {
+    if let Binding::Custom(normal, shifted) = binding {
+      if !customs.contains(&(*normal, *shifted)) {
+        customs.push((*normal, *shifted));
+      }
+    }
+  }
+
+  customs
+}
+
+// the symbols no US key types, the keymaps send nothing for them
+fn warnings_for(customs: &[(char, char)], nothing: &str) -> Vec<String> {
+  let mut symbols: Vec<char> = vec![];
+
+  for symbol in customs.iter().flat_map(|(normal, shifted)| vec![*normal, *shifted]) {
+    if !symbols.contains(&symbol) && us_key_for(symbol).is_none() && !symbol.is_ascii_alphabetic() {
+      symbols.push(symbol);
+    }
+  }
+
+  symbols.iter().map(|symbol| format!("no US key types '{}', it's {} in the keymap", symbol, nothing)).collect()
+}
+
+// the US key typing the symbol, and whether it needs shift for that
+fn us_key_for(symbol: char) -> Option<(&'static UsKey, bool)> {
+  US_KEYS.iter().find_map(|key| {
+    if key.normal == symbol {
+      Some((key, false))
+    } else if key.shifted == symbol {
+      Some((key, true))
+    } else {
+      None
+    }
+  })
+}
+
+fn qmk_code_for(symbol: char) -> String {
+  match us_key_for(symbol) {
+    Some((key, false)) => format!("KC_{}", key.qmk),
+    Some((key, true)) => format!("S(KC_{})", key.qmk),
+    None if symbol.is_ascii_lowercase() => format!("KC_{}", symbol.to_ascii_uppercase()),
+    None if symbol.is_ascii_uppercase() => format!("S(KC_{})", symbol),
+    None => "KC_NO".to_string()
+  }
+}
+
+fn zmk_code_for(symbol: char) -> String {
+  match us_key_for(symbol) {
+    Some((key, false)) => format!("&kp {}", key.zmk),
+    Some((key, true)) => format!("&kp {}", key.zmk_shifted),
+    None if symbol.is_ascii_lowercase() => format!("&kp {}", symbol.to_ascii_uppercase()),
+    None if symbol.is_ascii_uppercase() => format!("&kp LS({})", symbol),
+    None => "&none".to_string()
+  }
+}
+
+fn qmk_special_for(special: &SpecialSymbol) -> &'static str {
+  match special {
+    SpecialSymbol::Tab => "KC_TAB",
+    SpecialSymbol::Space => "KC_SPC",
+    SpecialSymbol::Return => "KC_ENT",
+    SpecialSymbol::LeftShift => "KC_LSFT",
+    SpecialSymbol::RightShift => "KC_RSFT"
+  }
+}
+
+fn zmk_special_for(special: &SpecialSymbol) -> &'static str {
+  match special {
+    SpecialSymbol::Tab => "TAB",
+    SpecialSymbol::Space => "SPACE",
+    SpecialSymbol::Return => "RETURN",
+    SpecialSymbol::LeftShift => "LSHIFT",
+    SpecialSymbol::RightShift => "RSHIFT"
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  // colemak with the colon and the less-than sign swapped around
+  fn layout() -> Layout {
+    Layout::new(COLEMAK.template.replace("Y : {", "Y < {").replace("M < >", "M : >"))
+  }
+
+  #[test]
+  fn binds_the_us_pairs_as_they_are() {
+    assert_eq!(binding_for("a", "A"), Binding::Plain("A".to_string(), "A".to_string()));
+    assert_eq!(binding_for(";", ":"), Binding::Plain("SCLN".to_string(), "SEMICOLON".to_string()));
+    assert_eq!(binding_for(",", ";"), Binding::Custom(',', ';'));
+    assert_eq!(binding_for("a", "!"), Binding::Custom('a', '!'));
+    assert_eq!(binding_for("é", "É"), Binding::Custom('é', 'É'));
+  }
+
+  #[test]
+  fn names_the_symbols() {
+    assert_eq!(qmk_code_for(';'), "KC_SCLN");
+    assert_eq!(qmk_code_for(':'), "S(KC_SCLN)");
+    assert_eq!(qmk_code_for('Q'), "S(KC_Q)");
+    assert_eq!(qmk_code_for('é'), "KC_NO");
+    assert_eq!(zmk_code_for('1'), "&kp N1");
+    assert_eq!(zmk_code_for('"'), "&kp DOUBLE_QUOTES");
+    assert_eq!(zmk_code_for('q'), "&kp Q");
+    assert_eq!(zmk_code_for('é'), "&none");
+  }
+
+  #[test]
+  fn walks_the_geometry() {
+    let rows = bindings_for(&COLEMAK, &FULL_ORTHO);
+
+    assert_eq!(rows.iter().map(|row| row.len()).collect::<Vec<_>>(), vec![12, 12, 12, 10, 6]);
+    assert_eq!(rows[1][2], Binding::Plain("F".to_string(), "F".to_string()));
+    assert_eq!(rows[4], vec![
+      Binding::Plain("GRV".to_string(), "GRAVE".to_string()),
+      Binding::Special(SpecialSymbol::LeftShift),
+      Binding::Special(SpecialSymbol::Space),
+      Binding::Special(SpecialSymbol::Return),
+      Binding::Special(SpecialSymbol::RightShift),
+      Binding::Special(SpecialSymbol::Tab)
+    ]);
+  }
+
+  #[test]
+  fn exports_qmk_keymaps() {
+    let keymap = qmk(&layout(), &FULL_ORTHO, "colemak").source;
+    let lines: Vec<&str> = keymap.lines().collect();
+
+    assert!(lines.contains(&"  RK_0 = SAFE_RANGE, // ; <"));
+    assert!(lines.contains(&"  RK_1, // , :"));
+    assert!(lines.contains(&"static const uint16_t rk_normal[] = { KC_SCLN, KC_COMM };"));
+    assert!(lines.contains(&"static const uint16_t rk_shifted[] = { S(KC_COMM), S(KC_SCLN) };"));
+    assert!(lines.contains(&"    KC_Q, KC_W, KC_F, KC_P, KC_G, KC_J, KC_L, KC_U, KC_Y, RK_0, KC_LBRC, KC_RBRC,"));
+    assert!(lines.contains(&"    KC_Z, KC_X, KC_C, KC_V, KC_B, KC_K, KC_M, RK_1, KC_DOT, KC_SLSH,"));
+    assert!(lines.contains(&"    KC_GRV, KC_LSFT, KC_SPC, KC_ENT, KC_RSFT, KC_TAB"));
+    assert!(lines.contains(&"bool process_record_user(uint16_t keycode, keyrecord_t *record) {"));
+  }
+
+  #[test]
+  fn skips_the_custom_keycodes_when_there_are_none() {
+    let keymap = qmk(&COLEMAK, &FULL_ORTHO, "colemak").source;
+
+    assert!(!keymap.contains("RK_"));
+    assert!(!keymap.contains("process_record_user"));
+  }
+
+  #[test]
+  fn exports_zmk_keymaps() {
+    let keymap = zmk(&layout(), &FULL_ORTHO, "colemak").source;
+    let lines: Vec<&str> = keymap.lines().collect();
+
+    assert!(lines.contains(&"        rk_1: rk_1 { // , :"));
+    assert!(lines.contains(&"            bindings = <&kp COMMA>, <&kp COLON>;"));
+    assert!(lines.contains(&"                &kp Z &kp X &kp C &kp V &kp B &kp K &kp M &rk_1 &kp PERIOD &kp SLASH"));
+    assert!(lines.contains(&"                &kp GRAVE &kp LSHIFT &kp SPACE &kp RETURN &kp RSHIFT &kp TAB"));
+    assert_eq!(keymap.matches('{').count(), keymap.matches('}').count());
+  }
+
+  #[test]
+  fn warns_about_what_it_cannot_send() {
+    let layout = Layout::new(COLEMAK.template.replace("Q W F", "Ä W F").replace("q w f", "ä w f").replace("M < >", "M « >"));
+
+    assert_eq!(qmk(&COLEMAK, &FULL_ORTHO, "colemak").warnings, Vec::<String>::new());
+    assert_eq!(qmk(&layout, &FULL_ORTHO, "umlauts").warnings, vec![
+      "no US key types 'ä', it's KC_NO in the keymap".to_string(),
+      "no US key types 'Ä', it's KC_NO in the keymap".to_string(),
+      "no US key types '«', it's KC_NO in the keymap".to_string()
+    ]);
+    assert_eq!(zmk(&layout, &FULL_ORTHO, "umlauts").warnings[0], "no US key types 'ä', it's &none in the keymap");
+  }
+}
@@ -0,0 +1,119 @@
+/**
+ * The named XKB keysyms of the ASCII punctuation, Latin-1, Latin-2, Cyrillic
+ * and Greek ranges, with their keysym codes and the symbols they stand for,
+ * the way `keysymdef.h` has them. The letters and digits go by themselves
+ */
+pub const KEYSYMS: [(&str, u32, char); 356] = [
+  // the ascii punctuation and latin-1
+  ("space", 0x020, ' '), ("exclam", 0x021, '!'), ("quotedbl", 0x022, '"'), ("numbersign", 0x023, '#'),
+  ("dollar", 0x024, '$'), ("percent", 0x025, '%'), ("ampersand", 0x026, '&'), ("apostrophe", 0x027, '\''),
+  ("parenleft", 0x028, '('), ("parenright", 0x029, ')'), ("asterisk", 0x02a, '*'), ("plus", 0x02b, '+'),
+  ("comma", 0x02c, ','), ("minus", 0x02d, '-'), ("period", 0x02e, '.'), ("slash", 0x02f, '/'),
+  ("colon", 0x03a, ':'), ("semicolon", 0x03b, ';'), ("less", 0x03c, '<'), ("equal", 0x03d, '='),
+  ("greater", 0x03e, '>'), ("question", 0x03f, '?'), ("at", 0x040, '@'), ("bracketleft", 0x05b, '['),
+  ("backslash", 0x05c, '\\'), ("bracketright", 0x05d, ']'), ("asciicircum", 0x05e, '^'),
+  ("underscore", 0x05f, '_'), ("grave", 0x060, '`'), ("braceleft", 0x07b, '{'), ("bar", 0x07c, '|'),
+  ("braceright", 0x07d, '}'), ("asciitilde", 0x07e, '~'), ("nobreakspace", 0x0a0, '\u{a0}'),
+  ("exclamdown", 0x0a1, '¡'), ("cent", 0x0a2, '¢'), ("sterling", 0x0a3, '£'), ("currency", 0x0a4, '¤'),
+  ("yen", 0x0a5, '¥'), ("brokenbar", 0x0a6, '¦'), ("section", 0x0a7, '§'), ("diaeresis", 0x0a8, '¨'),
+  ("copyright", 0x0a9, '©'), ("ordfeminine", 0x0aa, 'ª'), ("guillemotleft", 0x0ab, '«'),
+  ("notsign", 0x0ac, '¬'), ("hyphen", 0x0ad, '\u{ad}'), ("registered", 0x0ae, '®'), ("macron", 0x0af, '¯'),
+  ("degree", 0x0b0, '°'), ("plusminus", 0x0b1, '±'), ("twosuperior", 0x0b2, '²'),
+  ("threesuperior", 0x0b3, '³'), ("acute", 0x0b4, '´'), ("mu", 0x0b5, 'µ'), ("paragraph", 0x0b6, '¶'),
+  ("periodcentered", 0x0b7, '·'), ("cedilla", 0x0b8, '¸'), ("onesuperior", 0x0b9, '¹'),
+  ("masculine", 0x0ba, 'º'), ("guillemotright", 0x0bb, '»'), ("onequarter", 0x0bc, '¼'),
+  ("onehalf", 0x0bd, '½'), ("threequarters", 0x0be, '¾'), ("questiondown", 0x0bf, '¿'),
+  ("Agrave", 0x0c0, 'À'), ("Aacute", 0x0c1, 'Á'), ("Acircumflex", 0x0c2, 'Â'), ("Atilde", 0x0c3, 'Ã'),
+  ("Adiaeresis", 0x0c4, 'Ä'), ("Aring", 0x0c5, 'Å'), ("AE", 0x0c6, 'Æ'), ("Ccedilla", 0x0c7, 'Ç'),
+  ("Egrave", 0x0c8, 'È'), ("Eacute", 0x0c9, 'É'), ("Ecircumflex", 0x0ca, 'Ê'), ("Ediaeresis", 0x0cb, 'Ë'),
+  ("Igrave", 0x0cc, 'Ì'), ("Iacute", 0x0cd, 'Í'), ("Icircumflex", 0x0ce, 'Î'), ("Idiaeresis", 0x0cf, 'Ï'),
+  ("ETH", 0x0d0, 'Ð'), ("Ntilde", 0x0d1, 'Ñ'), ("Ograve", 0x0d2, 'Ò'), ("Oacute", 0x0d3, 'Ó'),
+  ("Ocircumflex", 0x0d4, 'Ô'), ("Otilde", 0x0d5, 'Õ'), ("Odiaeresis", 0x0d6, 'Ö'), ("multiply", 0x0d7, '×'),
+  ("Oslash", 0x0d8, 'Ø'), ("Ooblique", 0x0d8, 'Ø'), ("Ugrave", 0x0d9, 'Ù'), ("Uacute", 0x0da, 'Ú'),
+  ("Ucircumflex", 0x0db, 'Û'), ("Udiaeresis", 0x0dc, 'Ü'), ("Yacute", 0x0dd, 'Ý'), ("THORN", 0x0de, 'Þ'),
+  ("ssharp", 0x0df, 'ß'), ("agrave", 0x0e0, 'à'), ("aacute", 0x0e1, 'á'), ("acircumflex", 0x0e2, 'â'),
+  ("atilde", 0x0e3, 'ã'), ("adiaeresis", 0x0e4, 'ä'), ("aring", 0x0e5, 'å'), ("ae", 0x0e6, 'æ'),
+  ("ccedilla", 0x0e7, 'ç'), ("egrave", 0x0e8, 'è'), ("eacute", 0x0e9, 'é'), ("ecircumflex", 0x0ea, 'ê'),
+  ("ediaeresis", 0x0eb, 'ë'), ("igrave", 0x0ec, 'ì'), ("iacute", 0x0ed, 'í'), ("icircumflex", 0x0ee, 'î'),
+  ("idiaeresis", 0x0ef, 'ï'), ("eth", 0x0f0, 'ð'), ("ntilde", 0x0f1, 'ñ'), ("ograve", 0x0f2, 'ò'),
+  ("oacute", 0x0f3, 'ó'), ("ocircumflex", 0x0f4, 'ô'), ("otilde", 0x0f5, 'õ'), ("odiaeresis", 0x0f6, 'ö'),
+  ("division", 0x0f7, '÷'), ("oslash", 0x0f8, 'ø'), ("ooblique", 0x0f8, 'ø'), ("ugrave", 0x0f9, 'ù'),
+  ("uacute", 0x0fa, 'ú'), ("ucircumflex", 0x0fb, 'û'), ("udiaeresis", 0x0fc, 'ü'), ("yacute", 0x0fd, 'ý'),
+  ("thorn", 0x0fe, 'þ'), ("ydiaeresis", 0x0ff, 'ÿ'),
+  // latin-2
+  ("Aogonek", 0x1a1, 'Ą'), ("breve", 0x1a2, '˘'), ("Lstroke", 0x1a3, 'Ł'), ("Lcaron", 0x1a5, 'Ľ'),
+  ("Sacute", 0x1a6, 'Ś'), ("Scaron", 0x1a9, 'Š'), ("Scedilla", 0x1aa, 'Ş'), ("Tcaron", 0x1ab, 'Ť'),
+  ("Zacute", 0x1ac, 'Ź'), ("Zcaron", 0x1ae, 'Ž'), ("Zabovedot", 0x1af, 'Ż'), ("aogonek", 0x1b1, 'ą'),
+  ("ogonek", 0x1b2, '˛'), ("lstroke", 0x1b3, 'ł'), ("lcaron", 0x1b5, 'ľ'), ("sacute", 0x1b6, 'ś'),
+  ("caron", 0x1b7, 'ˇ'), ("scaron", 0x1b9, 'š'), ("scedilla", 0x1ba, 'ş'), ("tcaron", 0x1bb, 'ť'),
+  ("zacute", 0x1bc, 'ź'), ("doubleacute", 0x1bd, '˝'), ("zcaron", 0x1be, 'ž'), ("zabovedot", 0x1bf, 'ż'),
+  ("Racute", 0x1c0, 'Ŕ'), ("Abreve", 0x1c3, 'Ă'), ("Lacute", 0x1c5, 'Ĺ'), ("Cacute", 0x1c6, 'Ć'),
+  ("Ccaron", 0x1c8, 'Č'), ("Eogonek", 0x1ca, 'Ę'), ("Ecaron", 0x1cc, 'Ě'), ("Dcaron", 0x1cf, 'Ď'),
+  ("Dstroke", 0x1d0, 'Đ'), ("Nacute", 0x1d1, 'Ń'), ("Ncaron", 0x1d2, 'Ň'), ("Odoubleacute", 0x1d5, 'Ő'),
+  ("Rcaron", 0x1d8, 'Ř'), ("Uring", 0x1d9, 'Ů'), ("Udoubleacute", 0x1db, 'Ű'), ("Tcedilla", 0x1de, 'Ţ'),
+  ("racute", 0x1e0, 'ŕ'), ("abreve", 0x1e3, 'ă'), ("lacute", 0x1e5, 'ĺ'), ("cacute", 0x1e6, 'ć'),
+  ("ccaron", 0x1e8, 'č'), ("eogonek", 0x1ea, 'ę'), ("ecaron", 0x1ec, 'ě'), ("dcaron", 0x1ef, 'ď'),
+  ("dstroke", 0x1f0, 'đ'), ("nacute", 0x1f1, 'ń'), ("ncaron", 0x1f2, 'ň'), ("odoubleacute", 0x1f5, 'ő'),
+  ("rcaron", 0x1f8, 'ř'), ("uring", 0x1f9, 'ů'), ("udoubleacute", 0x1fb, 'ű'), ("tcedilla", 0x1fe, 'ţ'),
+  ("abovedot", 0x1ff, '˙'),
+  // cyrillic
+  ("Serbian_dje", 0x6a1, 'ђ'), ("Macedonia_gje", 0x6a2, 'ѓ'), ("Cyrillic_io", 0x6a3, 'ё'),
+  ("Ukrainian_ie", 0x6a4, 'є'), ("Macedonia_dse", 0x6a5, 'ѕ'), ("Ukrainian_i", 0x6a6, 'і'),
+  ("Ukrainian_yi", 0x6a7, 'ї'), ("Cyrillic_je", 0x6a8, 'ј'), ("Cyrillic_lje", 0x6a9, 'љ'),
+  ("Cyrillic_nje", 0x6aa, 'њ'), ("Serbian_tshe", 0x6ab, 'ћ'), ("Macedonia_kje", 0x6ac, 'ќ'),
+  ("Ukrainian_ghe_with_upturn", 0x6ad, 'ґ'), ("Byelorussian_shortu", 0x6ae, 'ў'),
+  ("Cyrillic_dzhe", 0x6af, 'џ'), ("numerosign", 0x6b0, '№'), ("Serbian_DJE", 0x6b1, 'Ђ'),
+  ("Macedonia_GJE", 0x6b2, 'Ѓ'), ("Cyrillic_IO", 0x6b3, 'Ё'), ("Ukrainian_IE", 0x6b4, 'Є'),
+  ("Macedonia_DSE", 0x6b5, 'Ѕ'), ("Ukrainian_I", 0x6b6, 'І'), ("Ukrainian_YI", 0x6b7, 'Ї'),
+  ("Cyrillic_JE", 0x6b8, 'Ј'), ("Cyrillic_LJE", 0x6b9, 'Љ'), ("Cyrillic_NJE", 0x6ba, 'Њ'),
+  ("Serbian_TSHE", 0x6bb, 'Ћ'), ("Macedonia_KJE", 0x6bc, 'Ќ'), ("Ukrainian_GHE_WITH_UPTURN", 0x6bd, 'Ґ'),
+  ("Byelorussian_SHORTU", 0x6be, 'Ў'), ("Cyrillic_DZHE", 0x6bf, 'Џ'), ("Cyrillic_yu", 0x6c0, 'ю'),
+  ("Cyrillic_a", 0x6c1, 'а'), ("Cyrillic_be", 0x6c2, 'б'), ("Cyrillic_tse", 0x6c3, 'ц'),
+  ("Cyrillic_de", 0x6c4, 'д'), ("Cyrillic_ie", 0x6c5, 'е'), ("Cyrillic_ef", 0x6c6, 'ф'),
+  ("Cyrillic_ghe", 0x6c7, 'г'), ("Cyrillic_ha", 0x6c8, 'х'), ("Cyrillic_i", 0x6c9, 'и'),
+  ("Cyrillic_shorti", 0x6ca, 'й'), ("Cyrillic_ka", 0x6cb, 'к'), ("Cyrillic_el", 0x6cc, 'л'),
+  ("Cyrillic_em", 0x6cd, 'м'), ("Cyrillic_en", 0x6ce, 'н'), ("Cyrillic_o", 0x6cf, 'о'),
+  ("Cyrillic_pe", 0x6d0, 'п'), ("Cyrillic_ya", 0x6d1, 'я'), ("Cyrillic_er", 0x6d2, 'р'),
+  ("Cyrillic_es", 0x6d3, 'с'), ("Cyrillic_te", 0x6d4, 'т'), ("Cyrillic_u", 0x6d5, 'у'),
+  ("Cyrillic_zhe", 0x6d6, 'ж'), ("Cyrillic_ve", 0x6d7, 'в'), ("Cyrillic_softsign", 0x6d8, 'ь'),
+  ("Cyrillic_yeru", 0x6d9, 'ы'), ("Cyrillic_ze", 0x6da, 'з'), ("Cyrillic_sha", 0x6db, 'ш'),
+  ("Cyrillic_e", 0x6dc, 'э'), ("Cyrillic_shcha", 0x6dd, 'щ'), ("Cyrillic_che", 0x6de, 'ч'),
+  ("Cyrillic_hardsign", 0x6df, 'ъ'), ("Cyrillic_YU", 0x6e0, 'Ю'), ("Cyrillic_A", 0x6e1, 'А'),
+  ("Cyrillic_BE", 0x6e2, 'Б'), ("Cyrillic_TSE", 0x6e3, 'Ц'), ("Cyrillic_DE", 0x6e4, 'Д'),
+  ("Cyrillic_IE", 0x6e5, 'Е'), ("Cyrillic_EF", 0x6e6, 'Ф'), ("Cyrillic_GHE", 0x6e7, 'Г'),
+  ("Cyrillic_HA", 0x6e8, 'Х'), ("Cyrillic_I", 0x6e9, 'И'), ("Cyrillic_SHORTI", 0x6ea, 'Й'),
+  ("Cyrillic_KA", 0x6eb, 'К'), ("Cyrillic_EL", 0x6ec, 'Л'), ("Cyrillic_EM", 0x6ed, 'М'),
+  ("Cyrillic_EN", 0x6ee, 'Н'), ("Cyrillic_O", 0x6ef, 'О'), ("Cyrillic_PE", 0x6f0, 'П'),
+  ("Cyrillic_YA", 0x6f1, 'Я'), ("Cyrillic_ER", 0x6f2, 'Р'), ("Cyrillic_ES", 0x6f3, 'С'),
+  ("Cyrillic_TE", 0x6f4, 'Т'), ("Cyrillic_U", 0x6f5, 'У'), ("Cyrillic_ZHE", 0x6f6, 'Ж'),
+  ("Cyrillic_VE", 0x6f7, 'В'), ("Cyrillic_SOFTSIGN", 0x6f8, 'Ь'), ("Cyrillic_YERU", 0x6f9, 'Ы'),
+  ("Cyrillic_ZE", 0x6fa, 'З'), ("Cyrillic_SHA", 0x6fb, 'Ш'), ("Cyrillic_E", 0x6fc, 'Э'),
+  ("Cyrillic_SHCHA", 0x6fd, 'Щ'), ("Cyrillic_CHE", 0x6fe, 'Ч'), ("Cyrillic_HARDSIGN", 0x6ff, 'Ъ'),
+  // greek
+  ("Greek_ALPHAaccent", 0x7a1, 'Ά'), ("Greek_EPSILONaccent", 0x7a2, 'Έ'), ("Greek_ETAaccent", 0x7a3, 'Ή'),
+  ("Greek_IOTAaccent", 0x7a4, 'Ί'), ("Greek_IOTAdieresis", 0x7a5, 'Ϊ'), ("Greek_OMICRONaccent", 0x7a7, 'Ό'),
+  ("Greek_UPSILONaccent", 0x7a8, 'Ύ'), ("Greek_UPSILONdieresis", 0x7a9, 'Ϋ'),
+  ("Greek_OMEGAaccent", 0x7ab, 'Ώ'), ("Greek_accentdieresis", 0x7ae, '΅'), ("Greek_horizbar", 0x7af, '―'),
+  ("Greek_alphaaccent", 0x7b1, 'ά'), ("Greek_epsilonaccent", 0x7b2, 'έ'), ("Greek_etaaccent", 0x7b3, 'ή'),
+  ("Greek_iotaaccent", 0x7b4, 'ί'), ("Greek_iotadieresis", 0x7b5, 'ϊ'),
+  ("Greek_iotaaccentdieresis", 0x7b6, 'ΐ'), ("Greek_omicronaccent", 0x7b7, 'ό'),
+  ("Greek_upsilonaccent", 0x7b8, 'ύ'), ("Greek_upsilondieresis", 0x7b9, 'ϋ'),
+  ("Greek_upsilonaccentdieresis", 0x7ba, 'ΰ'), ("Greek_omegaaccent", 0x7bb, 'ώ'),
+  ("Greek_ALPHA", 0x7c1, 'Α'), ("Greek_BETA", 0x7c2, 'Β'), ("Greek_GAMMA", 0x7c3, 'Γ'),
+  ("Greek_DELTA", 0x7c4, 'Δ'), ("Greek_EPSILON", 0x7c5, 'Ε'), ("Greek_ZETA", 0x7c6, 'Ζ'),
+  ("Greek_ETA", 0x7c7, 'Η'), ("Greek_THETA", 0x7c8, 'Θ'), ("Greek_IOTA", 0x7c9, 'Ι'),
+  ("Greek_KAPPA", 0x7ca, 'Κ'), ("Greek_LAMDA", 0x7cb, 'Λ'), ("Greek_LAMBDA", 0x7cb, 'Λ'),
+  ("Greek_MU", 0x7cc, 'Μ'), ("Greek_NU", 0x7cd, 'Ν'), ("Greek_XI", 0x7ce, 'Ξ'),
+  ("Greek_OMICRON", 0x7cf, 'Ο'), ("Greek_PI", 0x7d0, 'Π'), ("Greek_RHO", 0x7d1, 'Ρ'),
+  ("Greek_SIGMA", 0x7d2, 'Σ'), ("Greek_TAU", 0x7d4, 'Τ'), ("Greek_UPSILON", 0x7d5, 'Υ'),
+  ("Greek_PHI", 0x7d6, 'Φ'), ("Greek_CHI", 0x7d7, 'Χ'), ("Greek_PSI", 0x7d8, 'Ψ'),
+  ("Greek_OMEGA", 0x7d9, 'Ω'), ("Greek_alpha", 0x7e1, 'α'), ("Greek_beta", 0x7e2, 'β'),
+  ("Greek_gamma", 0x7e3, 'γ'), ("Greek_delta", 0x7e4, 'δ'), ("Greek_epsilon", 0x7e5, 'ε'),
+  ("Greek_zeta", 0x7e6, 'ζ'), ("Greek_eta", 0x7e7, 'η'), ("Greek_theta", 0x7e8, 'θ'),
+  ("Greek_iota", 0x7e9, 'ι'), ("Greek_kappa", 0x7ea, 'κ'), ("Greek_lamda", 0x7eb, 'λ'),
+  ("Greek_lambda", 0x7eb, 'λ'), ("Greek_mu", 0x7ec, 'μ'), ("Greek_nu", 0x7ed, 'ν'), ("Greek_xi", 0x7ee, 'ξ'),
+  ("Greek_omicron", 0x7ef, 'ο'), ("Greek_pi", 0x7f0, 'π'), ("Greek_rho", 0x7f1, 'ρ'),
+  ("Greek_sigma", 0x7f2, 'σ'), ("Greek_finalsmallsigma", 0x7f3, 'ς'), ("Greek_tau", 0x7f4, 'τ'),
+  ("Greek_upsilon", 0x7f5, 'υ'), ("Greek_phi", 0x7f6, 'φ'), ("Greek_chi", 0x7f7, 'χ'),
+  ("Greek_psi", 0x7f8, 'ψ'), ("Greek_omega", 0x7f9, 'ω')
+];
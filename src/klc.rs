@@ -0,0 +1,344 @@
+/**
+ * Writes layouts as Microsoft Keyboard Layout Creator `.klc` sources,
+ * which MSKLC or `kbdutool` compile into an installable layout, and reads
+ * them back
+ */
+use hashbrown::HashMap;
+
+use crate::layout::*;
+use crate::importer::*;
+use crate::parser::Position;
+
+/// Reads the normal and shift columns of the `LAYOUT` section
+pub fn import(data: &str) -> Result<Import, String> {
+  let lines: Vec<Vec<&str>> = data.lines()
+    .map(|line| line.split("//").next().unwrap_or("").split_whitespace().collect())
+    .filter(|tokens: &Vec<&str>| !tokens.is_empty())
+    .collect();
+
+  let states: Vec<&str> = section(&lines, "SHIFTSTATE").iter().map(|tokens| tokens[0]).collect();
+  let column_of = |state: &str| states.iter().position(|s| *s == state).ok_or(format!("no shift state {} in SHIFTSTATE", state));
+  let (normal_column, shifted_column) = (column_of("0")?, column_of("1")?);
+
+  // the symbols outside the BMP go as `%%` in the columns, and as UTF-16 code units in LIGATURE
+  let ligatures: HashMap<(&str, usize), String> = section(&lines, "LIGATURE").iter()
+    .filter_map(|tokens| {
+      let units: Option<Vec<u16>> = tokens.get(2..)?.iter().map(|unit| u16::from_str_radix(unit, 16).ok()).collect();
+      let symbol: String = std::char::decode_utf16(units?).collect::<Result<String, _>>().ok()?;
+
+      Some(((tokens[0], tokens[1].parse().ok()?), symbol))
+    })
+    .collect();
+
+  let mut symbols = HashMap::new();
+  let mut warnings = vec![];
+
+  for tokens in section(&lines, "LAYOUT") {
+    let scan_code = tokens[0];
+    let columns = tokens.get(3..).unwrap_or_default();
+    let values: Vec<Option<(String, bool)>> = columns.iter().enumerate().map(|(column, value)| match *value {
+      "%%" => ligatures.get(&(tokens[1], column)).map(|symbol| (symbol.clone(), false)),
+      value => value_for(value).map(|(char, dead)| (char.to_string(), dead))
+    })
+    .collect();
+    let position = KEYS.iter().enumerate()
+      .find_map(|(row, keys)| Some((row, keys.iter().position(|(code, _)| *code == scan_code)?)));
+
+    let extras: Vec<&str> = columns.iter().enumerate()
+      .filter(|(column, value)| ![normal_column, shifted_column].contains(column) && states.get(*column) != Some(&"2") && **value != "-1")
+      .map(|(_, value)| *value)
+      .collect();
+
+    match (position, values.get(normal_column).cloned().flatten(), values.get(shifted_column).cloned().flatten()) {
+      (None, _, _) if scan_code == "39" => {},
+      (None, _, _) => warnings.push(format!("skipped scan code {}, it's not one of the 47 keys", scan_code)),
+      (Some(position), Some((normal, normal_dead)), Some((shifted, shifted_dead))) => {
+        if normal_dead || shifted_dead {
+          warnings.push(format!("the dead keys on scan code {} work as plain symbols", scan_code));
+        }
+
+        if !extras.is_empty() {
+          warnings.push(format!("skipped {} on scan code {}", extras.join(", "), scan_code));
+        }
+
+        symbols.insert(position, (normal, shifted));
+      },
+      (Some(_), _, _) => warnings.push(format!("scan code {} needs both the normal and the shifted symbols", scan_code))
+    }
+  }
+
+  complete(symbols, warnings)
+}
+
+// the section keywords, the ligature lines start with virtual keys like OEM_3 that look the same
+const KEYWORDS: [&str; 17] = [
+  "KBD", "COPYRIGHT", "COMPANY", "LOCALENAME", "LOCALEID", "VERSION", "ATTRIBUTES", "SHIFTSTATE", "LAYOUT",
+  "LIGATURE", "DEADKEY", "KEYNAME", "KEYNAME_EXT", "KEYNAME_DEAD", "DESCRIPTIONS", "LANGUAGENAMES", "ENDKBD"
+];
+
+// the lines after a section keyword, up to the next keyword
+fn section<'a>(lines: &'a [Vec<&'a str>], name: &str) -> &'a [Vec<&'a str>] {
+  let is_keyword = |tokens: &Vec<&str>| KEYWORDS.contains(&tokens[0]);
+
+  match lines.iter().position(|tokens| tokens[0] == name) {
+    Some(start) => {
+      let rest = &lines[start + 1..];
+      &rest[..rest.iter().position(is_keyword).unwrap_or(rest.len())]
+    },
+    None => &[]
+  }
+}
+
+// a column value is either a character, or its hex code, with an `@` for dead keys
+fn value_for(value: &str) -> Option<(char, bool)> {
+  let dead = value.len() > 1 && value.ends_with('@');
+  let value = if dead { &value[..value.len() - 1] } else { value };
+  let mut chars = value.chars();
+
+  match (chars.next(), chars.next()) {
+    (Some(char), None) => Some((char, dead)),
+    _ if value == "-1" => None,
+    _ => u32::from_str_radix(value, 16).ok().and_then(std::char::from_u32).map(|char| (char, dead))
+  }
+}
+
+/// The `.klc` source for the layout, with `name` as its description
+pub fn export(layout: &Layout, name: &str) -> String {
+  let author = layout.metadata.author.as_deref().unwrap_or("rusty-key").replace('"', "'");
+
+  let mut lines = vec![
+    format!("KBD\t{}\t\"{}\"", kbd_name_for(name), name.replace('"', "'")),
+    "".to_string(),
+    format!("COPYRIGHT\t\"(c) {}\"", author),
+    "".to_string(),
+    format!("COMPANY\t\"{}\"", author),
+    "".to_string(),
+    "LOCALENAME\t\"en-US\"".to_string(),
+    "".to_string(),
+    "LOCALEID\t\"00000409\"".to_string(),
+    "".to_string(),
+    "VERSION\t1.0".to_string(),
+    "".to_string(),
+    "SHIFTSTATE".to_string(),
+    "".to_string(),
+    "0\t//Column 4".to_string(),
+    "1\t//Column 5 : Shft".to_string(),
+    "".to_string(),
+    "LAYOUT\t\t;an extra '@' at the end is a dead key".to_string(),
+    "".to_string(),
+    "//SC\tVK_\t\tCap\t0\t1".to_string(),
+    "//--\t----\t\t----\t----\t----".to_string(),
+    "".to_string()
+  ];
+
+  let mut keys: Vec<(&str, String)> = vec![];
+  let mut ligatures: Vec<(&str, String)> = vec![];
+
+  for entry in layout.entries() {
+    if let Some((scan_code, virtual_key)) = key_for(entry.position) {
+      keys.push((scan_code, key_line(scan_code, virtual_key, &entry.normal, &entry.shifted)));
+      ligatures.extend([&entry.normal, &entry.shifted].iter().enumerate()
+        .filter_map(|(column, symbol)| Some((scan_code, ligature_line(virtual_key, column, symbol)?))));
+    }
+  }
+
+  keys.push(("39", "39\tSPACE\t\t0\t0020\t0020\t// space".to_string()));
+  keys.sort();
+  ligatures.sort();
+
+  lines.extend(keys.into_iter().map(|(_, line)| line));
+
+  if !ligatures.is_empty() {
+    lines.extend(vec![
+      "".to_string(),
+      "LIGATURE".to_string(),
+      "".to_string(),
+      "//VK_\tMod#\tChar0\tChar1".to_string(),
+      "//----\t----\t----\t----".to_string(),
+      "".to_string()
+    ]);
+    lines.extend(ligatures.into_iter().map(|(_, line)| line));
+  }
+
+  lines.extend(vec![
+    "".to_string(),
+    "DESCRIPTIONS".to_string(),
+    "".to_string(),
+    format!("0409\t{}", name),
+    "".to_string(),
+    "LANGUAGENAMES".to_string(),
+    "".to_string(),
+    "0409\tEnglish (United States)".to_string(),
+    "".to_string(),
+    "ENDKBD".to_string()
+  ]);
+
+  lines.join("\r\n") + "\r\n"
+}
+
+// scan codes and US virtual keys of the number, top, home and bottom rows
+const KEYS: [&[(&str, &str)]; 4] = [
+  &[
+    ("29", "OEM_3"), ("02", "1"), ("03", "2"), ("04", "3"), ("05", "4"), ("06", "5"), ("07", "6"),
+    ("08", "7"), ("09", "8"), ("0a", "9"), ("0b", "0"), ("0c", "OEM_MINUS"), ("0d", "OEM_PLUS")
+  ],
+  &[
+    ("10", "Q"), ("11", "W"), ("12", "E"), ("13", "R"), ("14", "T"), ("15", "Y"), ("16", "U"),
+    ("17", "I"), ("18", "O"), ("19", "P"), ("1a", "OEM_4"), ("1b", "OEM_6"), ("2b", "OEM_5")
+  ],
+  &[
+    ("1e", "A"), ("1f", "S"), ("20", "D"), ("21", "F"), ("22", "G"), ("23", "H"),
+    ("24", "J"), ("25", "K"), ("26", "L"), ("27", "OEM_1"), ("28", "OEM_7")
+  ],
+  &[
+    ("2c", "Z"), ("2d", "X"), ("2e", "C"), ("2f", "V"), ("30", "B"),
+    ("31", "N"), ("32", "M"), ("33", "OEM_COMMA"), ("34", "OEM_PERIOD"), ("35", "OEM_2")
+  ]
+];
+
+fn key_for((row, col): Position) -> Option<(&'static str, &'static str)> {
+  KEYS.get(row)?.get(col).copied()
+}
+
+// caps lock shifts only the keys where the shifted symbol is the capital letter
+fn key_line(scan_code: &str, virtual_key: &str, normal: &str, shifted: &str) -> String {
+  let caps = normal != shifted && normal.to_uppercase() == shifted;
+
+  format!(
+    "{}\t{}\t\t{}\t{}\t{}\t// {}, {}",
+    scan_code, virtual_key, caps as u8, code_point(normal), code_point(shifted), normal, shifted
+  )
+}
+
+// the columns only take the BMP, the rest goes into the ligatures
+fn code_point(symbol: &str) -> String {
+  match symbol.chars().next().unwrap_or(' ') as u32 {
+    code if code > 0xFFFF => "%%".to_string(),
+    code => format!("{:04x}", code)
+  }
+}
+
+// the UTF-16 surrogate pair of a symbol outside the BMP, for the shift state column
+fn ligature_line(virtual_key: &str, column: usize, symbol: &str) -> Option<String> {
+  let char = symbol.chars().next().filter(|char| *char as u32 > 0xFFFF)?;
+  let units: Vec<String> = char.encode_utf16(&mut [0; 2]).iter().map(|unit| format!("{:04x}", unit)).collect();
+
+  Some(format!("{}\t{}\t{}\t// {}", virtual_key, column, units.join("\t"), char))
+}
+
+// the layout dll name, eight letters or digits at most
+fn kbd_name_for(name: &str) -> String {
+  let kbd_name: String = name.chars().filter(|c| c.is_ascii_alphanumeric()).take(8).collect();
+
+  if kbd_name.is_empty() { "rustykey".to_string() } else { kbd_name }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn maps_positions_to_keys() {
+    assert_eq!(key_for((0, 0)), Some(("29", "OEM_3")));
+    assert_eq!(key_for((1, 12)), Some(("2b", "OEM_5")));
+    assert_eq!(key_for((2, 9)), Some(("27", "OEM_1")));
+    assert_eq!(key_for((3, 9)), Some(("35", "OEM_2")));
+    assert_eq!(key_for((3, 10)), None);
+  }
+
+  #[test]
+  fn writes_key_lines() {
+    assert_eq!(key_line("10", "Q", "q", "Q"), "10\tQ\t\t1\t0071\t0051\t// q, Q");
+    assert_eq!(key_line("27", "OEM_1", ";", ":"), "27\tOEM_1\t\t0\t003b\t003a\t// ;, :");
+    assert_eq!(key_line("10", "Q", "é", "É"), "10\tQ\t\t1\t00e9\t00c9\t// é, É");
+    assert_eq!(key_line("10", "Q", "𝑥", "X"), "10\tQ\t\t0\t%%\t0058\t// 𝑥, X");
+  }
+
+  #[test]
+  fn writes_symbols_outside_the_bmp_as_ligatures() {
+    let layout = Layout::new(COLEMAK.template.replace("Q W F", "Q 😀 F").replace("q w f", "q 𝑥 f").replace("~", "𝄞"));
+    let source = export(&layout, "emoji");
+    let lines: Vec<&str> = source.split("\r\n").collect();
+
+    assert!(lines.contains(&"11\tW\t\t0\t%%\t%%\t// 𝑥, 😀"));
+    assert!(lines.contains(&"W\t0\td835\tdc65\t// 𝑥"));
+    assert!(lines.contains(&"W\t1\td83d\tde00\t// 😀"));
+    assert!(lines.contains(&"OEM_3\t1\td834\tdd1e\t// 𝄞"));
+    assert_eq!(ligature_line("W", 0, "w"), None);
+
+    let imported = import(&source).unwrap();
+    assert_eq!(imported.layout.entries(), layout.entries());
+    assert_eq!(imported.warnings, Vec::<String>::new());
+  }
+
+  #[test]
+  fn names_the_kbd() {
+    assert_eq!(kbd_name_for("colemak"), "colemak");
+    assert_eq!(kbd_name_for("halmak_21-extended"), "halmak21");
+    assert_eq!(kbd_name_for("---"), "rustykey");
+  }
+
+  #[test]
+  fn exports_layouts() {
+    let source = export(&COLEMAK, "colemak");
+    let lines: Vec<&str> = source.split("\r\n").collect();
+
+    assert_eq!(lines[0], "KBD\tcolemak\t\"colemak\"");
+    assert_eq!(lines.iter().filter(|line| line.len() > 2 && line.as_bytes()[2] == b'\t').count(), 47 + 1);
+    assert!(lines.contains(&"12\tE\t\t1\t0066\t0046\t// f, F"));
+    assert!(lines.contains(&"27\tOEM_1\t\t1\t006f\t004f\t// o, O"));
+    assert!(lines.contains(&"39\tSPACE\t\t0\t0020\t0020\t// space"));
+    assert!(lines.contains(&"0409\tcolemak"));
+    assert_eq!(lines[lines.len() - 2], "ENDKBD");
+  }
+
+  #[test]
+  fn reads_column_values() {
+    assert_eq!(value_for("0071"), Some(('q', false)));
+    assert_eq!(value_for("q"), Some(('q', false)));
+    assert_eq!(value_for("0060@"), Some(('`', true)));
+    assert_eq!(value_for("-1"), None);
+    assert_eq!(value_for("%%"), None);
+  }
+
+  #[test]
+  fn imports_what_it_exports() {
+    let imported = import(&export(&COLEMAK, "colemak")).unwrap();
+
+    assert_eq!(imported.layout.entries(), COLEMAK.entries());
+    assert_eq!(imported.warnings, Vec::<String>::new());
+  }
+
+  #[test]
+  fn reports_what_does_not_fit() {
+    let source = "
+SHIFTSTATE
+
+0 //Column 4
+1 //Column 5 : Shft
+2 //Column 6 : Ctrl
+6 //Column 7 : Ctrl Alt
+
+LAYOUT
+
+10 Q 1 0071 0051 -1 00e4 // q, Q, -, ä
+11 W 1 0077@ 0057 -1 -1
+12 E 1 0065 -1 -1 -1
+56 OEM_102 0 005c 007c -1 -1
+
+KEYNAME
+
+01 Esc
+";
+    let imported = import(source).unwrap();
+
+    assert_eq!(imported.layout.entries()[14].normal, "w");
+    assert_eq!(&imported.warnings[0..4], &[
+      "skipped 00e4 on scan code 10".to_string(),
+      "the dead keys on scan code 11 work as plain symbols".to_string(),
+      "scan code 12 needs both the normal and the shifted symbols".to_string(),
+      "skipped scan code 56, it's not one of the 47 keys".to_string()
+    ]);
+    assert_eq!(import("LAYOUT"), Err("no shift state 0 in SHIFTSTATE".to_string()));
+  }
+}
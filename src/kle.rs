@@ -0,0 +1,472 @@
+/**
+ * Reads keyboard-layout-editor (http://www.keyboard-layout-editor.com)
+ * JSON files. The keys with single character legends make up the layout
+ * rows, the shifted legend on top and the normal one below it, the way
+ * the keycaps show them. The QWERTY legends and the key positions make up
+ * a geometry as well
+ */
+use hashbrown::HashMap;
+use serde_json::Value;
+use once_cell::sync::Lazy;
+
+use crate::layout::*;
+use crate::importer::*;
+use crate::config::DistanceConfig;
+use crate::geometry::{Geometry,Hand,Finger};
+
+// a key as the editor places it, in key units
+#[derive(Debug,Clone,PartialEq)]
+pub struct KleKey {
+  pub legends: Vec<String>,
+  pub x: f64,
+  pub y: f64,
+  pub width: f64
+}
+
+/// Reads the layout off the key legends, row by row
+pub fn import(data: &str) -> Result<Import, String> {
+  let mut symbols = HashMap::new();
+  let mut warnings = vec![];
+
+  let rows: Vec<Vec<KleKey>> = keys_for(data)?.into_iter()
+    .map(|row| row.into_iter().filter(is_symbol_key).collect::<Vec<_>>())
+    .filter(|row| !row.is_empty())
+    .collect();
+
+  for (row, keys) in rows.iter().enumerate() {
+    let size = ROW_SIZES.get(row).copied().unwrap_or(0);
+
+    for (col, key) in keys.iter().take(size).enumerate() {
+      match key.legends.as_slice() {
+        [shifted, normal] => { symbols.insert((row, col), (normal.to_string(), shifted.to_string())); },
+        [letter] if letter.to_lowercase() != letter.to_uppercase() => {
+          symbols.insert((row, col), (letter.to_lowercase(), letter.to_uppercase()));
+        },
+        _ => warnings.push(format!("the '{}' key needs both the normal and the shifted legends", key.legends.join(" ")))
+      }
+    }
+
+    if keys.len() > size {
+      let skipped: Vec<String> = keys[size..].iter().map(|key| key.legends.join("")).collect();
+      warnings.push(format!("skipped {} on row {}, they don't fit the 47 keys", skipped.join(" "), row + 1));
+    }
+  }
+
+  complete(symbols, warnings)
+}
+
+/// Every key in the file, row by row, with the editor's positioning worked out
+pub fn keys_for(data: &str) -> Result<Vec<Vec<KleKey>>, String> {
+  let json: Value = serde_json::from_str(data).map_err(|err| format!("not a keyboard-layout-editor JSON, {}", err))?;
+  let rows = json.as_array().ok_or("not a keyboard-layout-editor JSON, expected a list of rows")?;
+
+  let mut keys = vec![];
+  let mut y = 0.0;
+
+  // the first item can be the keyboard metadata instead of a row
+  for row in rows.iter().filter_map(Value::as_array) {
+    let mut x = 0.0;
+    let mut width = 1.0;
+    let mut row_keys = vec![];
+
+    for item in row {
+      match item {
+        Value::Object(props) => {
+          let number = |name: &str| props.get(name).and_then(Value::as_f64);
+
+          x += number("x").unwrap_or(0.0);
+          y += number("y").unwrap_or(0.0);
+          width = number("w").unwrap_or(width);
+        },
+        Value::String(labels) => {
+          let legends = labels.split('\n').map(unescape).filter(|legend| !legend.is_empty()).collect();
+
+          row_keys.push(KleKey { legends, x, y, width });
+          x += width;
+          width = 1.0;
+        },
+        _ => return Err(format!("unexpected {} in a keyboard-layout-editor row", item))
+      }
+    }
+
+    keys.push(row_keys);
+    y += 1.0;
+  }
+
+  Ok(keys)
+}
+
+// a geometry key, with its center in key units
+#[derive(Debug,Clone)]
+struct Placed {
+  symbol: String,
+  center: (f64, f64),
+  preset: Option<(Hand, Finger)>
+}
+
+// the QWERTY home row, the keys the fingers rest on go first
+const HOME_KEYS: [(&str, Hand, Finger); 10] = [
+  ("a", Hand::Left, Finger::Pinky), ("s", Hand::Left, Finger::Ring), ("d", Hand::Left, Finger::Middle),
+  ("f", Hand::Left, Finger::Pointy), ("j", Hand::Right, Finger::Pointy), ("k", Hand::Right, Finger::Middle),
+  ("l", Hand::Right, Finger::Ring), (";", Hand::Right, Finger::Pinky),
+  ("g", Hand::Left, Finger::Pointy), ("h", Hand::Right, Finger::Pointy)
+];
+
+const SPECIALS: [&str; 5] = ["⇥", "︺", "↵", "⇧", "⇪"];
+
+// the QWERTY legends, looked up for every key
+static LEGENDS: Lazy<Vec<String>> = Lazy::new(|| QWERTY.entries().into_iter().map(|entry| entry.normal).collect());
+
+/// Works a geometry file out of the QWERTY legends and the key positions.
+/// The home row fingers carry over to the keys above and below it, the keys
+/// under the bottom row go to the thumbs, and the efforts grow with the
+/// distance to the finger's home key. An `l1`..`r5` legend pins the hand
+/// and the finger (pinky to thumb) of a key
+pub fn geometry(data: &str, distances: &DistanceConfig) -> Result<String, String> {
+  let rows = placed_rows(keys_for(data)?);
+  let has = |symbol: &str| rows.iter().flatten().any(|key| key.symbol == symbol);
+
+  let missing: Vec<String> = LEGENDS.iter().cloned()
+    .chain(vec!["︺".to_string(), "⇧".to_string()])
+    .filter(|symbol| !has(symbol))
+    .collect();
+
+  if !missing.is_empty() {
+    return Err(format!("no keys with the {} legends, the geometry needs all the QWERTY ones", missing.join(" ")));
+  }
+
+  let fingering = fingering_for(&rows);
+  let center_of = |symbol: &str| rows.iter().flatten().find(|key| key.symbol == symbol).unwrap().center;
+  let resting = |hand: Hand, finger: Finger| center_of(HOME_KEYS.iter().find(|(_, h, f)| *h == hand && *f == finger).unwrap().0);
+
+  // how far every finger goes sideways and up or down to reach its keys
+  let reaches: Vec<Vec<Option<(f64, f64)>>> = rows.iter().zip(fingering.iter())
+    .map(|(keys, fingers)| keys.iter().zip(fingers.iter()).map(|(key, (hand, finger))| match finger {
+      Finger::Thumb => None,
+      _ => {
+        let home = resting(*hand, *finger);
+        Some(((key.center.0 - home.0).abs(), (key.center.1 - home.1).abs()))
+      }
+    }).collect())
+    .collect();
+
+  let effort_for = |finger: Finger, reach: Option<(f64, f64)>| {
+    let base = match finger {
+      Finger::Pinky => distances.pinky,
+      Finger::Ring => distances.ring,
+      Finger::Middle => distances.middle,
+      Finger::Pointy => distances.pointy,
+      Finger::Thumb => distances.thumb
+    };
+    let (dx, dy) = reach.unwrap_or((0.0, 0.0));
+
+    base + (distances.unit as f64 * dx.hypot(dy)).round() as usize
+  };
+
+  let is_symbol = |row: usize, col: usize| !SPECIALS.contains(&rows[row][col].symbol.as_str()) && reaches[row][col].is_some();
+  let first_row = rows.iter().position(|keys| keys.iter().any(|key| key.symbol == "a")).unwrap().saturating_sub(1);
+  let last_row = rows.iter().position(|keys| keys.iter().any(|key| key.symbol == "z")).unwrap();
+
+  let mut rolling_pairs = vec![];
+
+  for row in first_row..=last_row {
+    let mut pairs = vec![];
+
+    for (from, (from_hand, from_finger)) in fingering[row].iter().enumerate() {
+      for (to, (to_hand, to_finger)) in fingering[row].iter().enumerate() {
+        if from_hand == to_hand && rank(*from_finger) < rank(*to_finger) && is_symbol(row, from) && is_symbol(row, to) {
+          pairs.push(format!("{}{}", rows[row][from].symbol, rows[row][to].symbol));
+        }
+      }
+    }
+
+    rolling_pairs.push(pairs.join(" "));
+  }
+
+  let left = rows.iter().flatten().map(|key| key.center.0 - 0.5).fold(f64::MAX, f64::min);
+  let grid = |value: &dyn Fn(usize, usize) -> String| grid_for(&rows, left, value);
+
+  let sections = [
+    ("template", grid(&|row, col| rows[row][col].symbol.clone())),
+    ("hands", grid(&|row, col| match fingering[row][col].0 { Hand::Left => "l", Hand::Right => "r" }.to_string())),
+    ("fingers", grid(&|row, col| (rank(fingering[row][col].1) + 1).to_string())),
+    ("efforts", grid(&|row, col| format!("{:02}", effort_for(fingering[row][col].1, reaches[row][col])))),
+    ("rolling_pairs", rolling_pairs.join("\n")),
+    // the index fingers going sideways and the fingers skipping a row start awkwardly
+    ("bad_starters", grid(&|row, col| match reaches[row][col] {
+      Some((dx, dy)) if is_symbol(row, col) && (dx >= 0.75 || dy >= 1.5) => rows[row][col].symbol.clone(),
+      _ => "".to_string()
+    }))
+  ];
+
+  let text: String = sections.iter()
+    .map(|(name, value)| format!("{} = \"\"\"\n{}\n\"\"\"\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")))
+    .collect();
+
+  toml::from_str::<Geometry>(&text)
+    .map_err(|err| err.to_string())
+    .and_then(|geometry| geometry.validate())
+    .map_err(|err| format!("can't make a geometry out of the keys, {}", err))?;
+
+  Ok(text)
+}
+
+// the geometry keys row by row, the first key wins if a legend repeats
+fn placed_rows(rows: Vec<Vec<KleKey>>) -> Vec<Vec<Placed>> {
+  let mut seen = vec![];
+  let mut shifts = 0;
+  let mut placed = vec![];
+
+  for row in rows {
+    let mut keys = vec![];
+
+    for key in row {
+      let preset = key.legends.iter().find_map(|legend| preset_for(legend));
+      let legends: Vec<String> = key.legends.iter().filter(|legend| preset_for(legend).is_none()).cloned().collect();
+
+      let symbol = match symbol_for(&legends, key.width) {
+        Some(symbol) if symbol == "⇧" => { shifts += 1; if shifts == 1 { symbol } else { "⇪".to_string() } },
+        Some(symbol) => symbol,
+        None => continue
+      };
+
+      if seen.contains(&symbol) { continue; }
+      seen.push(symbol.clone());
+
+      keys.push(Placed { symbol, center: (key.x + key.width / 2.0, key.y + 0.5), preset });
+    }
+
+    if !keys.is_empty() { placed.push(keys); }
+  }
+
+  placed
+}
+
+// the template symbol for the QWERTY legends, the modifiers go by their names
+fn symbol_for(legends: &[String], width: f64) -> Option<String> {
+  let symbol = match legends {
+    [] if width >= 3.0 => "︺".to_string(),
+    [name] if name.chars().count() > 1 => match name.to_lowercase().as_str() {
+      "tab" => "⇥",
+      "space" => "︺",
+      "enter" | "return" => "↵",
+      "shift" => "⇧",
+      _ => return None
+    }.to_string(),
+    [letter] => letter.to_lowercase(),
+    [_, normal] => normal.to_string(),
+    _ => return None
+  };
+
+  let known = SPECIALS.contains(&symbol.as_str()) || LEGENDS.contains(&symbol);
+
+  if known { Some(symbol) } else { None }
+}
+
+// the `l1`..`r5` legends, a hand and a finger from the pinky to the thumb
+fn preset_for(legend: &str) -> Option<(Hand, Finger)> {
+  let mut chars = legend.chars();
+
+  let hand = match chars.next()? { 'l' => Hand::Left, 'r' => Hand::Right, _ => return None };
+  let finger = match chars.next()? {
+    '1' => Finger::Pinky, '2' => Finger::Ring, '3' => Finger::Middle, '4' => Finger::Pointy, '5' => Finger::Thumb,
+    _ => return None
+  };
+
+  if chars.next().is_none() { Some((hand, finger)) } else { None }
+}
+
+// the home row keys go to their fingers, the rows above and below take the
+// finger of the nearest key in the row next to them, leaning towards the
+// middle of the keyboard, and the thumbs get everything under the bottom row
+fn fingering_for(rows: &[Vec<Placed>]) -> Vec<Vec<(Hand, Finger)>> {
+  let row_with = |symbol: &str| rows.iter().position(|keys| keys.iter().any(|key| key.symbol == symbol)).unwrap();
+  let (home, bottom) = (row_with("a"), row_with("z"));
+  let home_key = |key: &Placed| HOME_KEYS.iter().find(|(symbol, _, _)| *symbol == key.symbol).map(|(_, hand, finger)| (*hand, *finger));
+  let center_of = |symbol: &str| rows.iter().flatten().find(|key| key.symbol == symbol).map_or(0.0, |key| key.center.0);
+  let middle = (center_of("g") + center_of("h")) / 2.0;
+
+  let mut fingering = vec![vec![]; rows.len()];
+
+  fingering[home] = rows[home].iter().map(|key| home_key(key).unwrap_or_else(|| {
+    nearest(key, rows[home].iter().filter_map(|other| Some((other, home_key(other)?))), true)
+  })).collect();
+
+  for row in (0..home).rev() {
+    fingering[row] = rows[row].iter()
+      .map(|key| nearest(key, rows[row + 1].iter().zip(fingering[row + 1].iter().copied()), true))
+      .collect();
+  }
+
+  for row in home + 1..rows.len() {
+    fingering[row] = rows[row].iter().map(|key| match row > bottom {
+      true => (if key.center.0 < middle { Hand::Left } else { Hand::Right }, Finger::Thumb),
+      false => nearest(key, rows[row - 1].iter().zip(fingering[row - 1].iter().copied()), false)
+    }).collect();
+  }
+
+  rows.iter().zip(fingering)
+    .map(|(keys, fingers)| keys.iter().zip(fingers).map(|(key, finger)| key.preset.unwrap_or(finger)).collect())
+    .collect()
+}
+
+// the finger of the closest key sideways, the ties go to the right or to the left one
+fn nearest<'a>(key: &Placed, others: impl Iterator<Item = (&'a Placed, (Hand, Finger))>, rightwards: bool) -> (Hand, Finger) {
+  let distance = |other: &Placed| ((other.center.0 - key.center.0).abs() * 100.0).round() as i64;
+
+  others
+    .min_by(|(a, _), (b, _)| distance(a).cmp(&distance(b)).then_with(|| match rightwards {
+      true => b.center.0.total_cmp(&a.center.0),
+      false => a.center.0.total_cmp(&b.center.0)
+    }))
+    .map(|(_, finger)| finger)
+    .unwrap()
+}
+
+fn rank(finger: Finger) -> usize {
+  match finger {
+    Finger::Pinky => 0,
+    Finger::Ring => 1,
+    Finger::Middle => 2,
+    Finger::Pointy => 3,
+    Finger::Thumb => 4
+  }
+}
+
+// lays the values out the way the keys sit, three characters to a key width
+fn grid_for(rows: &[Vec<Placed>], left: f64, value: &dyn Fn(usize, usize) -> String) -> String {
+  let lines: Vec<String> = rows.iter().enumerate().map(|(row, keys)| {
+    let mut line = String::new();
+
+    for (col, key) in keys.iter().enumerate() {
+      let text = value(row, col);
+      if text.is_empty() { continue; }
+
+      let offset = ((key.center.0 - 0.5 - left) * 3.0).round() as usize;
+      let length = line.chars().count();
+
+      line.push_str(&" ".repeat(if length == 0 { offset } else { offset.saturating_sub(length).max(1) }));
+      line.push_str(&text);
+    }
+
+    line
+  }).collect();
+
+  lines.join("\n")
+}
+
+// the layout keys have one or two single character legends
+fn is_symbol_key(key: &KleKey) -> bool {
+  !key.legends.is_empty() && key.legends.len() <= 2 && key.legends.iter().all(|legend| legend.chars().count() == 1)
+}
+
+// the editor legends are html
+fn unescape(legend: &str) -> String {
+  legend.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&#39;", "'").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::geometry::SpecialSymbol;
+
+  // the ANSI 104 preset, without the function row and the numpad
+  const ANSI: &str = r##"[
+    {"name": "ANSI"},
+    ["~\n`","!\n1","@\n2","#\n3","$\n4","%\n5","^\n6","&\n7","*\n8","(\n9",")\n0","_\n-","+\n=",{"w":2},"Backspace"],
+    [{"w":1.5},"Tab","Q","W","E","R","T","Y","U","I","O","P","{\n[","}\n]",{"w":1.5},"|\n\\"],
+    [{"w":1.75},"Caps Lock","A","S","D","F","G","H","J","K","L",":\n;","\"\n'",{"w":2.25},"Enter"],
+    [{"w":2.25},"Shift","Z","X","C","V","B","N","M","&lt;\n,","&gt;\n.","?\n/",{"w":2.75},"Shift"],
+    [{"w":1.25},"Ctrl",{"w":1.25},"Win",{"w":1.25},"Alt",{"a":7,"w":6.25},"",{"w":1.25},"Alt"]
+  ]"##;
+
+  #[test]
+  fn works_out_the_positions() {
+    let rows = keys_for(ANSI).unwrap();
+
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows[1][1], KleKey { legends: vec!["Q".to_string()], x: 1.5, y: 1.0, width: 1.0 });
+    assert_eq!(rows[4][3], KleKey { legends: vec![], x: 3.75, y: 4.0, width: 6.25 });
+  }
+
+  #[test]
+  fn imports_layouts() {
+    let imported = import(ANSI).unwrap();
+
+    assert_eq!(imported.layout.entries(), QWERTY.entries());
+    assert_eq!(imported.warnings, Vec::<String>::new());
+  }
+
+  #[test]
+  fn reports_what_does_not_fit() {
+    let data = r##"[
+      ["~\n`","2","Esc"],
+      ["Q","W","E","R","T","Y","U","I","O","P","{\n[","}\n]","|\n\\","7","8"]
+    ]"##;
+    let imported = import(data).unwrap();
+
+    assert_eq!(imported.layout.entries()[0].normal, "`");
+    assert_eq!(imported.layout.entries()[1].normal, "1");
+    assert_eq!(&imported.warnings[0..2], &[
+      "the '2' key needs both the normal and the shifted legends".to_string(),
+      "skipped 7 8 on row 2, they don't fit the 47 keys".to_string()
+    ]);
+  }
+
+  #[test]
+  fn works_out_geometries() {
+    let geometry: Geometry = toml::from_str(&geometry(ANSI, &DistanceConfig::default()).unwrap()).unwrap();
+    let key = |symbol: &str| {
+      let position = QWERTY.entries().iter().find(|entry| entry.normal == symbol).unwrap().position;
+      let key = geometry.key_for_layout(position).unwrap();
+      (key.hand, key.finger, key.effort)
+    };
+
+    assert_eq!(key("`"), (Hand::Left, Finger::Pinky, 18));
+    assert_eq!(key("2"), (Hand::Left, Finger::Ring, 14));
+    assert_eq!(key("q"), (Hand::Left, Finger::Pinky, 8));
+    assert_eq!(key("a"), (Hand::Left, Finger::Pinky, 2));
+    assert_eq!(key("f"), (Hand::Left, Finger::Pointy, 0));
+    assert_eq!(key("g"), (Hand::Left, Finger::Pointy, 6));
+    assert_eq!(key("b"), (Hand::Left, Finger::Pointy, 11));
+    assert_eq!(key("n"), (Hand::Right, Finger::Pointy, 7));
+    assert_eq!(key("'"), (Hand::Right, Finger::Pinky, 8));
+
+    let specials = geometry.special_keys();
+    let special = |symbol| specials.get(&symbol).map(|key| (key.hand, key.finger));
+
+    assert_eq!(special(SpecialSymbol::Space), Some((Hand::Right, Finger::Thumb)));
+    assert_eq!(special(SpecialSymbol::LeftShift), Some((Hand::Left, Finger::Pinky)));
+    assert_eq!(special(SpecialSymbol::Return), Some((Hand::Right, Finger::Pinky)));
+    assert!(geometry.mappings().rolling_pairs.contains(&((2, 0), (2, 3))));
+    assert!(geometry.mappings().bad_starters.contains(&(2, 4)));
+  }
+
+  #[test]
+  fn takes_the_preset_fingers() {
+    let data = ANSI.replace(r#""Z","X""#, r#""Z\n\n\nl2","X\nr1""#);
+    let text = geometry(&data, &DistanceConfig::default()).unwrap();
+    let geometry: Geometry = toml::from_str(&text).unwrap();
+    let z_key = geometry.key_for_layout((3, 0)).unwrap();
+    let x_key = geometry.key_for_layout((3, 1)).unwrap();
+
+    assert_eq!((z_key.hand, z_key.finger), (Hand::Left, Finger::Ring));
+    assert_eq!((x_key.hand, x_key.finger), (Hand::Right, Finger::Pinky));
+  }
+
+  #[test]
+  fn needs_the_qwerty_legends() {
+    let data = ANSI.replace(r#""Q","W""#, r#""Q","Blah""#);
+
+    assert_eq!(
+      geometry(&data, &DistanceConfig::default()),
+      Err("no keys with the w legends, the geometry needs all the QWERTY ones".to_string())
+    );
+  }
+
+  #[test]
+  fn fails_on_broken_files() {
+    assert!(import("blah").unwrap_err().starts_with("not a keyboard-layout-editor JSON"));
+    assert_eq!(import("{}"), Err("not a keyboard-layout-editor JSON, expected a list of rows".to_string()));
+  }
+}
@@ -1,36 +1,64 @@
+use std::io;
 use std::fs;
 use std::fmt;
 use once_cell::sync::Lazy;
+use serde::{Serialize,Deserialize};
 
 use crate::parser::*;
 
-pub const QWERTY: Lazy<Layout> = Lazy::new(|| { 
-  Layout::load(&"assets/layouts/qwerty".to_string())
+pub static QWERTY: Lazy<Layout> = Lazy::new(|| { 
+  Layout::load("assets/layouts/qwerty")
 });
 #[allow(dead_code)]
-pub const DVORAK: Lazy<Layout> = Lazy::new(|| { 
-  Layout::load(&"assets/layouts/dvorak".to_string())
+pub static DVORAK: Lazy<Layout> = Lazy::new(|| { 
+  Layout::load("assets/layouts/dvorak")
 });
 #[allow(dead_code)]
-pub const COLEMAK: Lazy<Layout> = Lazy::new(|| { 
-  Layout::load(&"assets/layouts/colemak".to_string())
+pub static COLEMAK: Lazy<Layout> = Lazy::new(|| { 
+  Layout::load("assets/layouts/colemak")
 });
 #[allow(dead_code)]
-pub const WORKMAN: Lazy<Layout> = Lazy::new(|| { 
-  Layout::load(&"assets/layouts/workman".to_string())
+pub static WORKMAN: Lazy<Layout> = Lazy::new(|| { 
+  Layout::load("assets/layouts/workman")
 });
 #[allow(dead_code)]
-pub const THE_1: Lazy<Layout> = Lazy::new(|| { 
-  Layout::load(&"assets/layouts/the_1".to_string())
+pub static THE_1: Lazy<Layout> = Lazy::new(|| { 
+  Layout::load("assets/layouts/the_1")
 });
 #[allow(dead_code)]
-pub const HALMAK_21: Lazy<Layout> = Lazy::new(|| { 
-  Layout::load(&"assets/layouts/halmak_21".to_string())
+pub static HALMAK_21: Lazy<Layout> = Lazy::new(|| { 
+  Layout::load("assets/layouts/halmak_21")
 });
 
-#[derive(Debug,PartialEq,Clone)]
+// how many keys there are on each row of the standard layouts
+pub const ROW_SIZES: [usize; 4] = [13, 13, 11, 10];
+
+// the standard layout rows are staggered the way the keyboard is
+const ROW_INDENTS: [&str; 4] = ["", "  ", "  ", "    "];
+
+// what goes first when a layout has to be squeezed into fewer keys
+const SYMBOLS_BY_PRIORITY: &str = ",.;'/-=[]\\1234567890`";
+
+#[derive(Debug,PartialEq,Clone,Serialize,Deserialize)]
 pub struct Layout {
-  pub template: String
+  pub template: String,
+  #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+  pub metadata: Metadata
+}
+
+// the optional header of a layout file, `// name: value` lines before the rows
+#[derive(Debug,PartialEq,Clone,Default,Serialize,Deserialize)]
+pub struct Metadata {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub author: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub geometry: Option<String>, // the one the layout is meant for
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub corpus: Option<String> // the text it was optimized against
 }
 
 #[derive(Debug,PartialEq)]
@@ -41,24 +69,114 @@ pub struct Entry {
 }
 
 impl Layout {
-  pub fn load(filename: &String) -> Layout {
-    let content = fs::read_to_string(filename).unwrap();
+  /// Reads a layout file, panics if it's not there
+  pub fn load(filename: &str) -> Layout {
+    Layout::read(filename).unwrap()
+  }
+
+  /// Reads a layout file, failing on the rows that don't pair up
+  pub fn read(filename: &str) -> Result<Layout, io::Error> {
+    let content = fs::read_to_string(filename)?;
+    let errors = structure_errors(&content);
+
+    if !errors.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, errors.join("; ")));
+    }
+
+    Ok(Layout::new(content))
+  }
 
-    Layout { template: content.to_string() }
+  /// A layout out of the layout file contents, with the header if there is one
+  pub fn new(template: String) -> Layout {
+    let metadata = Metadata::parse(&template);
+
+    Layout { template, metadata }
   }
   
+  /// Writes the entries out in the layout file format
+  pub fn from_entries(entries: &[Entry]) -> Layout {
+    let rows = shape_of(entries);
+    let standard = rows == ROW_SIZES;
+    let mut lines = vec![];
+
+    for row in 0..rows.len() {
+      let indent = ROW_INDENTS.get(row).filter(|_| standard).unwrap_or(&"");
+      let mut keys: Vec<&Entry> = entries.iter().filter(|entry| entry.position.0 == row).collect();
+      keys.sort_by_key(|entry| entry.position);
+
+      let shifted: Vec<&str> = keys.iter().map(|entry| entry.shifted.as_str()).collect();
+      let normal: Vec<&str> = keys.iter().map(|entry| entry.normal.as_str()).collect();
+
+      lines.push(format!("{}{}", indent, shifted.join(" ")));
+      lines.push(format!("{}{}", indent, normal.join(" ")));
+    }
+
+    Layout::new(lines.join("\n"))
+  }
+
+  /// The same symbols spread over a differently shaped keyboard, the letters
+  /// go first, then the punctuation and the digits, the rest is left out
+  pub fn reshaped(&self, shape: &[usize]) -> Result<Layout, String> {
+    let entries = self.entries();
+
+    if shape_of(&entries) == shape {
+      return Ok(self.clone());
+    }
+
+    let needed: usize = shape.iter().sum();
+
+    if needed > entries.len() {
+      return Err(format!("{} has {} keys, the geometry needs {}", self.name(), entries.len(), needed));
+    }
+
+    let priority = |entry: &Entry| match entry.normal.chars().next() {
+      Some(char) if char.is_alphabetic() => 0,
+      Some(char) => 1 + SYMBOLS_BY_PRIORITY.find(char).unwrap_or(SYMBOLS_BY_PRIORITY.len()),
+      None => usize::MAX
+    };
+
+    let mut keys: Vec<&Entry> = entries.iter().collect();
+    keys.sort_by_key(|entry| (priority(entry), entry.position));
+
+    let positions = shape.iter().enumerate().flat_map(|(row, size)| (0..*size).map(move |col| (row, col)));
+    let reshaped: Vec<Entry> = keys.into_iter().zip(positions)
+      .map(|(entry, position)| Entry { normal: entry.normal.clone(), shifted: entry.shifted.clone(), position })
+      .collect();
+
+    Ok(Layout::from_entries(&reshaped))
+  }
+
+  /// How many keys there are on every row
+  pub fn shape(&self) -> Vec<usize> {
+    shape_of(&self.entries())
+  }
+
+  /// The name from the header, or else the start of the top letter row
+  pub fn title(&self) -> String {
+    self.metadata.name.clone().unwrap_or_else(|| self.long_name())
+  }
+
+  /// The first six symbols of the top letter row
   pub fn name(self: &Self) -> String {
-    let first6 = &self.entries()[13..19];
-    let name = first6.iter().fold(String::new(), |name, key| format!("{}{}", name, key.normal));
-  
-    name.to_uppercase()
+    self.letter_row_start(6)
   }
 
   pub fn long_name(&self) -> String {
-    let first6 = &self.entries()[13..23];
-    let name = first6.iter().fold(String::new(), |name, key| format!("{}{}", name, key.normal));
-  
-    name.to_uppercase()
+    self.letter_row_start(10)
+  }
+
+  // the number row goes first on the standard layouts, the names start
+  // with the first row that has any letters on it
+  fn letter_row_start(&self, size: usize) -> String {
+    let entries = self.entries();
+    let has_letters = |row: usize| entries.iter().any(|entry| entry.position.0 == row && entry.normal.chars().any(char::is_alphabetic));
+    let row = (0..shape_of(&entries).len()).find(|row| has_letters(*row)).unwrap_or(0);
+
+    entries.iter()
+      .filter(|entry| entry.position.0 == row)
+      .take(size)
+      .fold(String::new(), |name, key| format!("{}{}", name, key.normal))
+      .to_uppercase()
   }
 
   pub fn entries(self: &Self) -> Vec<Entry> {
@@ -73,30 +191,76 @@ impl Layout {
       })
       .collect() 
   }
+}
 
-  pub fn to_string(self: &Self, us_pc: bool) -> String {
-    let mut string = "".to_string();
-  
-    for (i, key) in self.entries().iter().enumerate() {
-      string = format!("{} {}", string, key.normal);
-  
-      match i {
-        12 | 25 => string = format!("{}\n  ", string),
-        36 if us_pc => string = format!("{}\n   ", string),
+impl Metadata {
+  pub fn is_empty(&self) -> bool {
+    *self == Metadata::default()
+  }
+
+  /// The description and the author, for the exported files to mention
+  pub fn notes(&self) -> Vec<String> {
+    let author = self.author.as_ref().map(|author| format!("by {}", author));
+
+    self.description.iter().cloned().chain(author).collect()
+  }
+
+  // the header goes up to the first row, the other comments are left alone
+  fn parse(template: &str) -> Metadata {
+    let mut metadata = Metadata::default();
+
+    for line in template.lines().take_while(|line| is_blank_or_comment(line)) {
+      let field = line.trim().strip_prefix("//").and_then(|rest| {
+        let (key, value) = rest.split_at(rest.find(':')?);
+        Some((key.trim(), value[1..].trim()))
+      });
 
-        5 | 17 | 30 | 36 | 41 if !us_pc => string = format!("{}  ", string),
-        _ => {}
+      if let Some((key, value)) = field.filter(|(_, value)| !value.is_empty()) {
+        let value = Some(value.to_string());
+
+        match key {
+          "name" => metadata.name = value,
+          "author" => metadata.author = value,
+          "description" => metadata.description = value,
+          "geometry" => metadata.geometry = value,
+          "corpus" => metadata.corpus = value,
+          _ => {}
+        }
       }
     }
-  
-    string
-  }  
+
+    metadata
+  }
 }
 
+// prints the normal symbols the way the layout file lays the rows out
 impl fmt::Display for Layout {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.to_string(true))
+    let entries = self.entries();
+    let lines: Vec<&str> = self.template.lines().filter(|line| !is_blank_or_comment(line)).collect();
+
+    let rows: Vec<String> = (0..shape_of(&entries).len()).map(|row| {
+      let line = lines.get(row * 2 + 1).copied().unwrap_or("");
+      let indent = &line[..line.len() - line.trim_start().len()];
+      let normals: Vec<&str> = entries.iter().filter(|entry| entry.position.0 == row).map(|entry| entry.normal.as_str()).collect();
+
+      format!("{}{}", indent, normals.join(" "))
+    }).collect();
+
+    write!(f, "{}", rows.join("\n"))
+  }
+}
+
+// how many entries there are on every row
+fn shape_of(entries: &[Entry]) -> Vec<usize> {
+  let mut shape = vec![];
+
+  for entry in entries {
+    if shape.len() <= entry.position.0 { shape.resize(entry.position.0 + 1, 0); }
+    shape[entry.position.0] += 1;
   }
+
+  shape
 }
 
 #[cfg(test)]
@@ -135,6 +299,73 @@ mod test {
   fn it_prints() {
     let result = format!("{}", QWERTY.clone());
 
-    assert_eq!(result, " ` 1 2 3 4 5 6 7 8 9 0 - =\n   q w e r t y u i o p [ ] \\\n   a s d f g h j k l ; \'\n    z x c v b n m , . /")
+    assert_eq!(result, "` 1 2 3 4 5 6 7 8 9 0 - =\n  q w e r t y u i o p [ ] \\\n  a s d f g h j k l ; \'\n    z x c v b n m , . /")
+  }
+
+  #[test]
+  fn follows_the_rows() {
+    let layout = Layout::new("Q W F P G\nq w f p g\nA R S T D\na r s t d\n".to_string());
+
+    assert_eq!(QWERTY.shape(), ROW_SIZES.to_vec());
+    assert_eq!(layout.shape(), vec![5, 5]);
+    assert_eq!(layout.name(), "QWFPG");
+    assert_eq!(format!("{}", layout), "q w f p g\na r s t d");
+    assert_eq!(Layout::from_entries(&layout.entries()).template, layout.template.trim_end());
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn fails_on_broken_files() {
+    let filename = std::env::temp_dir().join("rusty-key-broken-layout-test");
+    let filename = filename.to_str().unwrap();
+
+    fs::write(filename, "Q W E\nq w\nA S").unwrap();
+
+    assert_eq!(Layout::read(filename).map_err(|err| err.to_string()), Err([
+      "line 1 has 3 shifted symbols, but line 2 has 2 normal ones",
+      "line 3 has no normal symbols line under it, the layout needs an even number of lines"
+    ].join("; ")));
+
+    fs::remove_file(filename).unwrap();
+  }
+
+  #[test]
+  fn reads_the_header() {
+    let layout = Layout::new([
+      "// name: Colemak",
+      "// author: Shai Coleman",
+      "// geometry: US-PC",
+      "// source: https://colemak.com",
+      "",
+      COLEMAK.template.as_str()
+    ].join("\n"));
+
+    assert_eq!(layout.metadata, Metadata {
+      name: Some("Colemak".to_string()),
+      author: Some("Shai Coleman".to_string()),
+      description: None,
+      geometry: Some("US-PC".to_string()),
+      corpus: None
+    });
+    assert_eq!(layout.entries(), COLEMAK.entries());
+    assert_eq!(layout.title(), "Colemak");
+    assert_eq!(layout.metadata.notes(), vec!["by Shai Coleman".to_string()]);
+
+    assert!(COLEMAK.metadata.is_empty());
+    assert_eq!(COLEMAK.title(), "QWFPGJLUY;");
+  }
+
+  #[test]
+  fn reshapes_layouts() {
+    let reshaped = QWERTY.reshaped(&[10, 10, 10]).unwrap();
+
+    assert_eq!(reshaped.shape(), vec![10, 10, 10]);
+    assert_eq!(format!("{}", reshaped), "q w e r t y u i o p\na s d f g h j k l z\nx c v b n m , . ; '");
+    assert_eq!(QWERTY.reshaped(&ROW_SIZES).unwrap(), QWERTY.clone());
+    assert_eq!(QWERTY.reshaped(&[50]), Err("QWERTY has 47 keys, the geometry needs 50".to_string()));
+  }
+
+  #[test]
+  fn writes_entries_out() {
+    assert_eq!(Layout::from_entries(&COLEMAK.entries()).template, COLEMAK.template.trim_end());
+  }
+}
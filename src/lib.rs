@@ -0,0 +1,68 @@
+//! Keyboard layouts scoring and evolution.
+//!
+//! Everything runs off an explicit [`Config`], which carries the settings,
+//! the geometry and the text to score layouts against.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use rusty_key::{Config, Evolution, Keyboard, Layout, Summary, Update};
+//!
+//! let config = Config::load(Some("config.toml")).unwrap();
+//!
+//! // scoring a single layout
+//! let layout = Layout::read("assets/layouts/colemak").unwrap();
+//! let keyboard = Keyboard::from(&layout, &config.geometry);
+//! let summary = Summary::calculate(&keyboard, &config);
+//! println!("{}\n{}", summary, summary.score(&config.weights));
+//!
+//! // evolving a better one
+//! let evolution = Evolution::starting_with(&[layout], 42, Arc::new(config));
+//! evolution.start(|update| {
+//!   if let Update::Finished(finish) = update {
+//!     println!("{}\n{}", finish.reason, finish.best.template);
+//!   }
+//! });
+//! ```
+mod parser;
+mod ngrams;
+mod dna;
+mod frequency;
+mod profiler;
+mod keysyms;
+
+pub mod source;
+pub mod code;
+pub mod config;
+pub mod layout;
+pub mod geometry;
+pub mod keyboard;
+pub mod calculator;
+pub mod preservative;
+pub mod population;
+pub mod generation;
+pub mod selection;
+pub mod evolution;
+pub mod summary;
+pub mod mutator;
+pub mod checkpoint;
+pub mod stopping;
+pub mod importer;
+pub mod xkb;
+pub mod keylayout;
+pub mod klc;
+pub mod keymap;
+pub mod svg;
+pub mod kle;
+pub mod lint;
+pub mod symbols;
+
+pub use config::{Config,ExternalConfig};
+pub use layout::Layout;
+pub use geometry::Geometry;
+pub use keyboard::Keyboard;
+pub use calculator::Calculator;
+pub use summary::Summary;
+pub use evolution::{Evolution,Update};
+pub use generation::Outcome;
+pub use checkpoint::Checkpoint;
+pub use stopping::{Finish,Reason};
@@ -0,0 +1,181 @@
+/**
+ * Checks layout files for the mistakes that would otherwise quietly skew
+ * the scores. Rows that don't pair up are errors, the layout can't be read
+ * with them. Repeated or missing symbols, keys the text needs but the layout
+ * doesn't have, and rows that don't fit the geometry are warnings
+ */
+use std::fmt;
+use hashbrown::HashMap;
+
+use crate::config::Config;
+use crate::layout::*;
+use crate::keyboard::Keyboard;
+use crate::parser::{structure_errors,symbol_lines};
+
+// how many of the symbols with no keys get listed
+const LISTED_SYMBOLS: usize = 10;
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Severity {
+  Error,
+  Warning
+}
+
+#[derive(Debug,PartialEq)]
+pub struct Problem {
+  pub severity: Severity,
+  pub message: String
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let severity = match self.severity { Severity::Error => "error", Severity::Warning => "warning" };
+
+    write!(f, "{}: {}", severity, self.message)
+  }
+}
+
+/// The rows that don't pair up, the layout can't be read with those
+pub fn structure(template: &str) -> Vec<Problem> {
+  structure_errors(template).into_iter().map(error).collect()
+}
+
+/// Everything wrong with the layout, the structure errors and the warnings
+/// about the repeated symbols and what it misses against QWERTY, the config
+/// text and the geometry
+pub fn check(layout: &Layout, config: &Config) -> Vec<Problem> {
+  let mut problems = structure(&layout.template);
+  let mut seen: HashMap<&str, usize> = HashMap::new();
+
+  for (number, line) in symbol_lines(&layout.template) {
+    for symbol in line.split_whitespace() {
+      match seen.get(symbol) {
+        Some(first) => problems.push(warning(format!("line {} repeats '{}' from line {}", number, symbol, first))),
+        None => { seen.insert(symbol, number); }
+      }
+    }
+  }
+
+  let symbols: Vec<String> = layout.entries().into_iter().flat_map(|entry| vec![entry.normal, entry.shifted]).collect();
+  // the layouts for other alphabets only need the digits QWERTY has
+  let latin = symbols.iter().flat_map(|symbol| symbol.chars()).any(|c| c.is_ascii_alphabetic());
+  let missing: Vec<String> = QWERTY.entries().into_iter()
+    .map(|entry| entry.normal)
+    .filter(|symbol| symbol.chars().all(|c| c.is_ascii_digit() || (latin && c.is_ascii_alphabetic())) && !symbols.contains(symbol))
+    .collect();
+
+  if !missing.is_empty() {
+    problems.push(warning(format!("no keys for {}, QWERTY has them", missing.join(" "))));
+  }
+
+  let keyboard = Keyboard::from(layout, &config.geometry);
+  let mut counts: HashMap<char, usize> = HashMap::new();
+
+  for (symbol, count) in config.corpora.iter().flat_map(|corpus| corpus.frequencies().iter()) {
+    *counts.entry(*symbol).or_insert(0) += count;
+  }
+
+  let mut keyless: Vec<(char, usize)> = counts.into_iter()
+    .filter(|(symbol, _)| keyboard.key_for(symbol).is_none() && !symbol.is_control())
+    .collect();
+  keyless.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+  if !keyless.is_empty() {
+    let listed: Vec<String> = keyless.iter().take(LISTED_SYMBOLS).map(|(symbol, count)| format!("'{}' {}", symbol, count)).collect();
+    let more = if keyless.len() > LISTED_SYMBOLS { format!(" and {} more", keyless.len() - LISTED_SYMBOLS) } else { "".to_string() };
+
+    problems.push(warning(format!("the text uses {} symbols with no keys: {}{}", keyless.len(), listed.join(", "), more)));
+  }
+
+  let (shape, keys) = (layout.shape(), config.geometry.shape());
+
+  for row in 0..shape.len().max(keys.len()) {
+    let (symbols, keys) = (shape.get(row).copied().unwrap_or(0), keys.get(row).copied().unwrap_or(0));
+
+    if symbols != keys {
+      problems.push(warning(format!("row {} has {} symbols, but the geometry has {} keys there", row + 1, symbols, keys)));
+    }
+  }
+
+  problems
+}
+
+fn error(message: String) -> Problem {
+  Problem { severity: Severity::Error, message }
+}
+
+fn warning(message: String) -> Problem {
+  Problem { severity: Severity::Warning, message }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::source::Corpus;
+
+  fn messages(problems: &[Problem]) -> Vec<String> {
+    problems.iter().map(|problem| problem.to_string()).collect()
+  }
+
+  #[test]
+  fn passes_the_standard_layouts() {
+    let config = Config::defaults().unwrap();
+
+    for layout in [QWERTY.clone(), COLEMAK.clone(), DVORAK.clone(), WORKMAN.clone()].iter() {
+      assert_eq!(structure(&layout.template), vec![]);
+      assert!(check(layout, &config).iter().all(|problem| problem.severity == Severity::Warning));
+    }
+  }
+
+  #[test]
+  fn finds_structure_errors() {
+    let template = "// name: broken\n\nQ W E\nq w e r\n\nA S\na s\n// the end\nZ X";
+
+    assert_eq!(messages(&structure(template)), vec![
+      "error: line 3 has 3 shifted symbols, but line 4 has 4 normal ones",
+      "error: line 9 has no normal symbols line under it, the layout needs an even number of lines"
+    ]);
+  }
+
+  #[test]
+  fn warns_about_duplicates() {
+    let layout = Layout::new(QWERTY.template.replace("Q W", "Q Q").replace("a s", "a w"));
+
+    assert_eq!(messages(&check(&layout, &Config::defaults().unwrap()))[0..2], [
+      "warning: line 3 repeats 'Q' from line 3".to_string(),
+      "warning: line 6 repeats 'w' from line 4".to_string()
+    ]);
+  }
+
+  #[test]
+  fn warns_about_what_is_missing() {
+    let mut config = Config::defaults().unwrap();
+    config.corpora = vec![
+      Corpus::new("prose", 1, "the quick brown fox — jumps —\n".to_string()),
+      Corpus::new("code", 1, "fn main() {}".to_string())
+    ];
+
+    let template = QWERTY.template.replace("Q W", "É W").replace("q w", "é w");
+    let layout = Layout::new(template.lines().take(6).collect::<Vec<&str>>().join("\n"));
+
+    assert_eq!(messages(&check(&layout, &config)), vec![
+      "warning: no keys for q z x c v b n m, QWERTY has them",
+      "warning: the text uses 7 symbols with no keys: 'n' 3, 'm' 2, '—' 2, 'b' 1, 'c' 1, 'q' 1, 'x' 1",
+      "warning: row 4 has 0 symbols, but the geometry has 10 keys there"
+    ]);
+  }
+
+  #[test]
+  fn compares_other_alphabets_by_the_digits() {
+    let (latin, cyrillic): (Vec<char>, Vec<char>) = ('a'..='z').zip("фисвуапршолдьтщзйкыегмцчня".chars()).unzip();
+    let template: String = QWERTY.template.chars().map(|c| match latin.iter().position(|l| *l == c.to_ascii_lowercase()) {
+      Some(index) if c.is_ascii_uppercase() => cyrillic[index].to_uppercase().next().unwrap(),
+      Some(index) => cyrillic[index],
+      None => c
+    })
+    .collect();
+    let layout = Layout::new(template.replace("` 1 2", "` 1 ё"));
+
+    assert_eq!(messages(&check(&layout, &Config::defaults().unwrap()))[0], "warning: no keys for 2, QWERTY has them");
+  }
+}
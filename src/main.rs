@@ -1,27 +1,120 @@
-mod source;
-mod config;
-mod parser;
-mod layout;
-mod geometry;
-mod keyboard;
-mod calculator;
-mod preservative;
-mod population;
-mod generation;
-mod selection;
-mod evolution;
-mod frequency;
-mod profiler;
-mod summary;
-mod mutator;
-mod dna;
+/**
+ * The command line runner and the TUI on top of the `rusty_key` library
+ */
 mod ui;
 mod model;
 mod events;
+mod cli;
+mod evaluate;
+mod compare;
+mod export;
+mod render;
+mod import;
+mod check;
+mod headless;
+
+use std::sync::Arc;
+use rand::{Rng,SeedableRng};
+use rand::rngs::StdRng;
+
+use rusty_key::{Config,Evolution,Checkpoint,Layout,Update};
+use rusty_key::config::StoppingConfig;
+use rusty_key::generation;
+use cli::Command;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-  let evolution = evolution::Evolution::new();
-  evolution.start();
+  let args: Vec<String> = std::env::args().skip(1).collect();
+
+  let (config_file, command) = match cli::take_config(&args).and_then(|(file, args)| Ok((file, cli::parse(&args)?))) {
+    Ok(parsed) => parsed,
+    Err(error) => {
+      eprintln!("{}", error);
+      std::process::exit(2);
+    }
+  };
+
+  let config = match Config::load(config_file.as_deref()) {
+    Ok(config) => config,
+    Err(error) => {
+      eprintln!("Bad config, {}", error);
+      std::process::exit(2);
+    }
+  };
+
+  match command {
+    Command::Evaluate(filename) => evaluate::run(&filename, &config),
+    Command::Compare(comparison) => compare::run(&comparison, &config),
+    Command::Export(exporting) => export::run(&exporting),
+    Command::Render(rendering) => render::run(&rendering, &config),
+    Command::Import(importing) => import::run(&importing, &config),
+    Command::Check(paths) => check::run(&paths, &config),
+    Command::Evolve(evolving) => evolve(&evolving, config)
+  }
+}
+
+fn evolve(evolving: &cli::Evolving, mut config: Config) -> Result<(), Box<dyn std::error::Error>> {
+  config.stopping = stopping_for(evolving, &config.stopping);
+
+  // a resumed run keeps saving into the checkpoint it came from
+  if let Some(path) = evolving.resume.as_ref().filter(|path| !path.is_empty()) {
+    config.checkpoint.path = path.clone();
+  }
+
+  let config = Arc::new(config);
+
+  // the outcomes a resumed run replays from its checkpoint
+  let (evolution, replayed) = match &evolving.resume {
+    None => {
+      let seed = evolving.seed.or(config.seed).unwrap_or_else(rand::random);
+      let mut rng = StdRng::seed_from_u64(seed);
+      let sources = if evolving.start.is_empty() { &config.population.start } else { &evolving.start };
+
+      for filename in sources.iter().filter(|source| *source != "random") {
+        if let Ok(layout) = Layout::read(filename) { evaluate::warn_about(filename, &layout, &config); }
+      }
+
+      let layouts = generation::starting_layouts(sources, &config, &mut rng)?;
+
+      if evolving.headless { eprintln!("seed: {}", seed); }
+
+      (Evolution::starting_with(&layouts, rng.gen(), config.clone()), 0)
+    },
+    Some(_) => {
+      let checkpoint = Checkpoint::load(&config.checkpoint.path)?;
+      let replayed = checkpoint.history.len();
+
+      (Evolution::resume(checkpoint, config.clone()), replayed)
+    }
+  };
+
+  evolution.start(|update| match update {
+    Update::Result(outcome) => events::inst().send_result(&outcome),
+    Update::Finished(finish) => events::inst().send_finish(&finish),
+    Update::Warning(message) => events::inst().send_warning(&message)
+  });
+
+  let finished = if evolving.headless {
+    headless::run(&evolving.output, replayed)
+  } else {
+    ui::render(&config)
+  };
+
+  // the run gets saved even when the output fails on the way
+  evolution.stop();
+
+  let saved = evolution.checkpoint().save(&config.checkpoint.path);
+
+  finished?;
+  saved?;
+
+  Ok(())
+}
 
-  ui::render()
+// the command line options take over the configured criteria
+fn stopping_for(evolving: &cli::Evolving, configured: &StoppingConfig) -> StoppingConfig {
+  StoppingConfig {
+    max_generations: evolving.max_generations.unwrap_or(configured.max_generations),
+    time_limit: evolving.time_limit.unwrap_or(configured.time_limit),
+    stagnation: evolving.stagnation.unwrap_or(configured.stagnation)
+  }
 }
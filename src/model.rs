@@ -1,32 +1,46 @@
 /* this is the view-model for the ui */
 
-use crate::config::CONFIG;
-use crate::summary::Summary;
-use crate::generation::Outcome;
+use rusty_key::config::WeightsConfig;
+use rusty_key::summary::Summary;
+use rusty_key::generation::Outcome;
+use rusty_key::stopping::Finish;
 
 pub struct ViewModel {
-  pub outcomes: Vec<Outcome>
-}
-
-fn to_score(summary: &Summary) -> usize {
-  (summary.score() * 1000.0) as usize
+  pub outcomes: Vec<Outcome>,
+  pub finish: Option<Finish>,
+  pub warning: Option<String>,
+  window_size: usize,
+  weights: WeightsConfig
 }
 
 impl ViewModel {
-  pub fn new() -> ViewModel {
-    ViewModel { outcomes: vec![] }
+  pub fn new(window_size: usize, weights: WeightsConfig) -> ViewModel {
+    ViewModel { outcomes: vec![], finish: None, warning: None, window_size, weights }
+  }
+
+  fn to_score(&self, summary: &Summary) -> usize {
+    (summary.score(&self.weights) * 1000.0) as usize
   }
 
   pub fn record(&mut self, outcome: Outcome) {
     self.outcomes.push(outcome);
   }
+
+  pub fn finish(&mut self, finish: Finish) {
+    self.finish = Some(finish);
+  }
+
+  // the latest one only, the title has room for one
+  pub fn warn(&mut self, message: String) {
+    self.warning = Some(message);
+  }
   
   pub fn top_list(&self) -> Vec<Vec<String>> {
     self.sorted_outcomes().iter().enumerate().map(|(i, outcome)|
       vec![
         format!("{}", i+1), 
-        outcome.best.long_name(), 
-        format!("{}", to_score(&outcome.best_summary))
+        outcome.best.title(), 
+        format!("{}", self.to_score(&outcome.best_summary))
       ]
     ).collect()
   }
@@ -42,30 +56,30 @@ impl ViewModel {
   pub fn top_scores(&self) -> Vec<(f64, f64)> {
     let mut list = self.sorted_outcomes().clone();
     list.reverse(); // historical order
-    list.iter().rev().take(CONFIG.progress_window_size).rev()
+    list.iter().rev().take(self.window_size).rev()
     .enumerate().map(|(i, outcome)|
-      (i as f64, to_score(&outcome.best_summary) as f64)
+      (i as f64, self.to_score(&outcome.best_summary) as f64)
     ).collect()
   }
 
   // best scores as they come
   pub fn best_scores(&self) -> Vec<(f64, f64)> {
-    self.outcomes.iter().rev().take(CONFIG.progress_window_size).rev()
+    self.outcomes.iter().rev().take(self.window_size).rev()
     .enumerate().map(|(i, outcome)|
-      (i as f64, to_score(&outcome.best_summary) as f64)
+      (i as f64, self.to_score(&outcome.best_summary) as f64)
     ).collect()
   }
 
   pub fn winner_scores(&self) -> Vec<(f64, f64)> {
-    self.outcomes.iter().rev().take(CONFIG.progress_window_size).rev()
+    self.outcomes.iter().rev().take(self.window_size).rev()
     .enumerate().map(|(i, outcome)|
-      (i as f64, to_score(&outcome.winner_summary) as f64)
+      (i as f64, self.to_score(&outcome.winner_summary) as f64)
     ).collect()
   }
 
   fn sorted_outcomes(&self) -> Vec<Outcome> {
     let mut list = self.outcomes.clone();
-    list.sort_by_key(|o| to_score(&o.best_summary));
+    list.sort_by_key(|o| self.to_score(&o.best_summary));
     list.reverse();
     list
   }
@@ -74,17 +88,23 @@ impl ViewModel {
 #[cfg(test)]
 mod test {
   use super::*;
-  use crate::layout::*;
+  use rusty_key::layout::*;
+  use rusty_key::config::ExternalConfig;
+
+  fn model() -> ViewModel {
+    ViewModel::new(200, ExternalConfig::default().weights)
+  }
 
   #[test]
   fn test_instance() {
-    let model = ViewModel::new();
+    let model = model();
     assert_eq!(model.outcomes, vec![]);
+    assert_eq!(model.finish, None);
   }
 
   #[test]
   fn test_push() {
-    let mut model = ViewModel::new();
+    let mut model = model();
     let outcome = Outcome {
       generation_number: 1,
       best: COLEMAK.clone(),
@@ -111,7 +131,7 @@ mod test {
 
   #[test]
   fn top_list() {
-    let mut model = ViewModel::new();
+    let mut model = model();
 
     assert_eq!(model.top_list().len(), 0);
 
@@ -167,7 +187,7 @@ mod test {
 
   #[test]
   fn best_outcome() {
-    let mut model = ViewModel::new();
+    let mut model = model();
 
     assert_eq!(model.best_outcome(), None);
 
@@ -218,7 +238,7 @@ mod test {
 
   #[test]
   fn top_scores() {
-    let mut model = ViewModel::new();
+    let mut model = model();
 
     assert_eq!(model.top_scores().len(), 0);
 
@@ -271,7 +291,7 @@ mod test {
 
   #[test]
   fn best_scores() {
-    let mut model = ViewModel::new();
+    let mut model = model();
 
     assert_eq!(model.best_scores().len(), 0);
 
@@ -324,7 +344,7 @@ mod test {
 
   #[test]
   fn winner_scores() {
-    let mut model = ViewModel::new();
+    let mut model = model();
 
     assert_eq!(model.winner_scores().len(), 0);
 
@@ -1,26 +1,29 @@
-use rand::Rng;
+use std::cell::RefCell;
+
+use rand::{Rng,SeedableRng};
+use rand::rngs::StdRng;
 
 use hashbrown::HashSet;
 
 use crate::dna::*;
-use crate::config::*;
 use crate::layout::*;
 use crate::parser::*;
 use crate::preservative::*;
+use crate::symbols::Classes;
 
 pub struct Mutator<'a> {
   presie: &'a Preservative,
+  classes: &'a Classes,
+  rng: RefCell<StdRng>,
   #[allow(dead_code)]
   cache: HashSet<String>
 }
 
 impl Mutator<'_> {
-  pub fn new() -> Mutator<'static> {
-    Mutator::with_presie(&CONFIG.preserve)
-  }
+  pub fn new<'a>(presie: &'a Preservative, classes: &'a Classes, seed: u64) -> Mutator<'a> {
+    let rng = RefCell::new(StdRng::seed_from_u64(seed));
 
-  fn with_presie(presie: &Preservative) -> Mutator {
-    Mutator { presie, cache: HashSet::new() }
+    Mutator { presie, classes, rng, cache: HashSet::new() }
   }
 
   pub fn mutate_keys(self: &Self, layout: &Layout) -> Layout {
@@ -37,6 +40,21 @@ impl Mutator<'_> {
     new_dna.to_layout()
   }
 
+  // a uniformly random permutation of all the keys that aren't preserved
+  pub fn shuffle(self: &Self, layout: &Layout) -> Layout {
+    let mut dna = DNA::from(layout);
+    let positions = (0..dna.len())
+      .filter(|i| self.presie.is_safe_position((*i, 0)) && self.presie.is_safe_position((*i, 1)))
+      .collect::<Vec<usize>>();
+
+    for i in (1..positions.len()).rev() {
+      let j = self.random_number(i + 1);
+      if i != j { dna = dna.swap_keys(positions[i], positions[j]); }
+    }
+
+    dna.to_layout()
+  }
+
   fn swap_random_keys(self: &Self, original: &DNA) -> DNA {
     let (first_pos, second_pos) = self.two_random_key_positions(&original);
 
@@ -78,7 +96,7 @@ impl Mutator<'_> {
 
   fn two_random_symbol_positions(self: &Self, sequence: &DNA) -> (Position, Position) {
     let non_alpha_positions = sequence.iter().enumerate()
-      .filter(|(_, pair)| pair.0.chars().all(|c| !c.is_ascii_alphabetic()))
+      .filter(|(_, pair)| pair.0.chars().all(|c| !self.classes.is_letter(c)))
       .map(|entry| entry.0)
       .collect::<Vec<usize>>();
 
@@ -110,8 +128,7 @@ impl Mutator<'_> {
   }
 
   fn random_number(self: &Self, size: usize) -> usize {
-    let mut rng = rand::thread_rng();
-    rng.gen_range(0..size)
+    self.rng.borrow_mut().gen_range(0..size)
   }
 }
 
@@ -123,7 +140,8 @@ mod test {
   fn it_mutates_keys() {
     let layout = QWERTY.clone();
     let presie = Preservative::from("".to_string());
-    let mutator = Mutator::with_presie(&presie);
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
     let new_layout = mutator.mutate_keys(&layout);
 
     assert_ne!(new_layout.template, layout.template);
@@ -133,12 +151,55 @@ mod test {
   fn it_mutates_symbols() {
     let layout = QWERTY.clone();
     let presie = Preservative::from("".to_string());
-    let mutator = Mutator::with_presie(&presie);
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
     let new_layout = mutator.mutate_symbols(&layout);
 
     assert_ne!(new_layout.template, layout.template);
   }
 
+  #[test]
+  fn it_shuffles_layouts() {
+    let presie = Preservative::from("".to_string());
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
+    let shuffled = DNA::from(&mutator.shuffle(&QWERTY));
+    let original = qwerty_dna();
+
+    assert_ne!(shuffled, original);
+
+    let mut shuffled_pairs = shuffled.sequence.clone();
+    let mut original_pairs = original.sequence.clone();
+    shuffled_pairs.sort();
+    original_pairs.sort();
+
+    assert_eq!(shuffled_pairs, original_pairs);
+  }
+
+  #[test]
+  fn it_never_shuffles_preserved_positions() {
+    let presie = Preservative::from("
+      ∙ ! ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙
+      ∙ 1 ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙
+        Q ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙
+        q ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙
+        ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ 
+        ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ 
+          ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ 
+          ∙ ∙ ∙ ∙ ∙ ∙ ∙ , ∙ ∙ 
+    ".to_string());
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
+
+    for _ in 0..100 {
+      let dna = DNA::from(&mutator.shuffle(&QWERTY));
+
+      assert_eq!(("!".to_string(), "1".to_string()), dna.pair_at(1));
+      assert_eq!(("Q".to_string(), "q".to_string()), dna.pair_at(13));
+      assert_eq!(("<".to_string(), ",".to_string()), dna.pair_at(44));
+    }
+  }
+
   #[test]
   fn it_never_mutates_preserved_positions() {
     let presie = Preservative::from("
@@ -151,7 +212,8 @@ mod test {
           ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ 
           ∙ ∙ ∙ ∙ ∙ ∙ ∙ , ∙ ∙ 
     ".to_string());
-    let mutator = Mutator::with_presie(&presie);
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
     let mut dna = qwerty_dna();
 
     for _ in 0..2000 {
@@ -179,7 +241,8 @@ mod test {
   #[test]
   fn test_random_keys_swap() {
     let presie = Preservative::from("".to_string());
-    let mutator = Mutator::with_presie(&presie);
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
     let original = qwerty_dna();
 
     let new_dna1 = mutator.swap_random_keys(&original);
@@ -197,7 +260,8 @@ mod test {
   #[test]
   fn swapping_random_symbols() {
     let presie = Preservative::from("".to_string());
-    let mutator = Mutator::with_presie(&presie);
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
     let original = qwerty_dna();
     let new_dna1 = mutator.swap_random_symbols(&original);
     let new_dna2 = mutator.swap_random_symbols(&original);
@@ -214,7 +278,8 @@ mod test {
   #[test]
   fn getting_two_random_symbol_positions() {
     let presie = Preservative::from("".to_string());
-    let mutator = Mutator::with_presie(&presie);
+    let classes = Classes::default();
+    let mutator = Mutator::new(&presie, &classes, 42);
     let sequence = qwerty_dna();
 
     for _ in 0..10 {
@@ -224,10 +289,10 @@ mod test {
 
       assert_ne!(pos1, pos2);
 
-      assert_eq!(true, entry1.0.chars().all(|c| !c.is_ascii_alphabetic()), "{:?}", entry1.0);
-      assert_eq!(true, entry1.1.chars().all(|c| !c.is_ascii_alphabetic()), "{:?}", entry1.1);
-      assert_eq!(true, entry2.0.chars().all(|c| !c.is_ascii_alphabetic()), "{:?}", entry2.0);
-      assert_eq!(true, entry2.1.chars().all(|c| !c.is_ascii_alphabetic()), "{:?}", entry2.1);
+      assert_eq!(true, entry1.0.chars().all(|c| !c.is_alphabetic()), "{:?}", entry1.0);
+      assert_eq!(true, entry1.1.chars().all(|c| !c.is_alphabetic()), "{:?}", entry1.1);
+      assert_eq!(true, entry2.0.chars().all(|c| !c.is_alphabetic()), "{:?}", entry2.0);
+      assert_eq!(true, entry2.1.chars().all(|c| !c.is_alphabetic()), "{:?}", entry2.1);
     }
   }
 
@@ -244,7 +309,7 @@ mod test {
   //       ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ 
   //   ".to_string());
   //   println!("{:?}", presie.restriced_positions);
-  //   let mutator = Mutator::with_presie(&presie);
+  //   let mutator = Mutator::new(&presie);
   //   let mut dna = qwerty_dna();
     
   //   assert!(false);
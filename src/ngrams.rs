@@ -0,0 +1,204 @@
+/**
+ * The corpus boiled down to symbol and pair counts, so scoring a layout adds
+ * up over the distinct pairs instead of going over the whole text. The rolls
+ * need the text order, that's the chain of the distinct runs between spaces,
+ * or the longest n-grams when all there is are count tables
+ */
+use hashbrown::HashMap;
+
+pub type Pair = (u32, u32);
+
+/// What the calculator works the rolls out from, both point into `bigrams`
+#[derive(Debug,PartialEq)]
+pub enum Rolls {
+  /// The distinct runs of pairs between the spaces, and the order they come in the text
+  Chain { runs: Vec<Vec<u32>>, order: Vec<u32> },
+  /// The pairs of the longest n-grams, and how many times each comes up
+  Contexts(Vec<(Vec<u32>, usize)>)
+}
+
+#[derive(Debug,PartialEq)]
+pub struct NGrams {
+  /// The text length in symbols, the metrics are taken against it
+  pub length: usize,
+  /// The distinct symbols, the counts point at them by index
+  pub symbols: Vec<char>,
+  pub unigrams: Vec<usize>,
+  pub bigrams: Vec<(Pair, usize)>,
+  pub rolls: Rolls
+}
+
+impl NGrams {
+  /// Counts the symbols the keyboard has keys for, the rest of them get
+  /// skipped the way the calculator skips them
+  pub fn from(text: &str, typeable: &[char]) -> NGrams {
+    // the text starts off the space key, the way the calculator does
+    let mut symbols = vec![' '];
+    let mut indexes: HashMap<char, u32> = HashMap::new();
+    indexes.insert(' ', 0);
+
+    let mut unigrams = vec![0];
+    let mut bigrams: Vec<(Pair, usize)> = vec![];
+    let mut bigram_indexes: HashMap<Pair, u32> = HashMap::new();
+    let mut runs = vec![];
+    let mut run_indexes: HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut order = vec![];
+    let mut run = vec![];
+    let mut previous = 0;
+
+    for symbol in text.chars().filter(|symbol| typeable.contains(symbol)) {
+      let index = *indexes.entry(symbol).or_insert_with(|| {
+        symbols.push(symbol);
+        unigrams.push(0);
+        (symbols.len() - 1) as u32
+      });
+
+      let pair = (previous, index);
+      let bigram = *bigram_indexes.entry(pair).or_insert_with(|| {
+        bigrams.push((pair, 0));
+        (bigrams.len() - 1) as u32
+      });
+
+      unigrams[index as usize] += 1;
+      bigrams[bigram as usize].1 += 1;
+
+      // the pairs off or onto the space, and the same key twice, never touch the rolls
+      if previous != 0 && index != 0 && previous != index {
+        run.push(bigram);
+      }
+
+      if index == 0 {
+        add_run(std::mem::take(&mut run), &mut runs, &mut run_indexes, &mut order);
+      }
+
+      previous = index;
+    }
+
+    add_run(run, &mut runs, &mut run_indexes, &mut order);
+
+    NGrams { length: text.chars().count(), symbols, unigrams, bigrams, rolls: Rolls::Chain { runs, order } }
+  }
+
+  /// Takes the symbols and pairs of the count tables, and the longest n-grams
+  /// for the rolls. The ones with symbols the keyboard has no keys for get dropped
+  pub fn from_counts(counts: &[(String, usize)], typeable: &[char]) -> NGrams {
+    let mut ngrams = NGrams { length: 0, symbols: vec![' '], unigrams: vec![0], bigrams: vec![], rolls: Rolls::Contexts(vec![]) };
+    let mut indexes: HashMap<char, u32> = HashMap::new();
+    let mut bigram_indexes: HashMap<Pair, u32> = HashMap::new();
+    let mut contexts: HashMap<Vec<u32>, usize> = HashMap::new();
+    indexes.insert(' ', 0);
+
+    let symbols_length = if counts.iter().any(|(ngram, _)| ngram.chars().count() == 1) { 1 } else { 2 };
+    let context_length = counts.iter().map(|(ngram, _)| ngram.chars().count()).max().unwrap_or(0).max(3);
+
+    for (ngram, count) in counts {
+      let symbols: Vec<char> = ngram.chars().collect();
+
+      // the text length comes off the symbols
+      if symbols.len() == symbols_length {
+        ngrams.length += count;
+      }
+
+      if ![1, 2, context_length].contains(&symbols.len()) || !symbols.iter().all(|symbol| typeable.contains(symbol)) { continue; }
+
+      let ids: Vec<u32> = symbols.iter().map(|symbol| *indexes.entry(*symbol).or_insert_with(|| {
+        ngrams.symbols.push(*symbol);
+        ngrams.unigrams.push(0);
+        (ngrams.symbols.len() - 1) as u32
+      }))
+      .collect();
+
+      if symbols.len() == symbols_length {
+        ngrams.unigrams[ids[ids.len() - 1] as usize] += count;
+      }
+
+      let pairs: Vec<u32> = ids.windows(2).map(|pair| *bigram_indexes.entry((pair[0], pair[1])).or_insert_with(|| {
+        ngrams.bigrams.push(((pair[0], pair[1]), 0));
+        (ngrams.bigrams.len() - 1) as u32
+      }))
+      .collect();
+
+      match pairs.len() {
+        0 => {},
+        1 => ngrams.bigrams[pairs[0] as usize].1 += count,
+        _ => *contexts.entry(pairs).or_insert(0) += count
+      }
+    }
+
+    let mut contexts: Vec<(Vec<u32>, usize)> = contexts.into_iter().collect();
+    contexts.sort();
+    ngrams.rolls = Rolls::Contexts(contexts);
+
+    ngrams
+  }
+}
+
+// the words with no pairs to roll on don't make runs
+fn add_run(run: Vec<u32>, runs: &mut Vec<Vec<u32>>, indexes: &mut HashMap<Vec<u32>, u32>, order: &mut Vec<u32>) {
+  if run.is_empty() { return; }
+
+  let index = *indexes.entry(run).or_insert_with_key(|run| {
+    runs.push(run.clone());
+    (runs.len() - 1) as u32
+  });
+
+  order.push(index);
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn pairs(ngrams: &NGrams, indexes: &[u32]) -> Vec<String> {
+    indexes.iter().map(|index| {
+      let ((first, second), _) = ngrams.bigrams[*index as usize];
+      format!("{}{}", ngrams.symbols[first as usize], ngrams.symbols[second as usize])
+    })
+    .collect()
+  }
+
+  #[test]
+  fn counts_symbols_and_pairs() {
+    let ngrams = NGrams::from("abab b", &['a', 'b', ' ']);
+    let all: Vec<u32> = (0..ngrams.bigrams.len() as u32).collect();
+
+    assert_eq!(ngrams.length, 6);
+    assert_eq!(ngrams.symbols, vec![' ', 'a', 'b']);
+    assert_eq!(ngrams.unigrams, vec![1, 2, 3]);
+    assert_eq!(pairs(&ngrams, &all), vec![" a", "ab", "ba", "b ", " b"]);
+    assert_eq!(ngrams.bigrams.iter().map(|(_, count)| *count).collect::<Vec<usize>>(), vec![1, 2, 1, 1, 1]);
+    assert_eq!(ngrams.rolls, Rolls::Chain { runs: vec![vec![1, 2, 1]], order: vec![0] });
+  }
+
+  #[test]
+  fn keeps_the_distinct_runs() {
+    let ngrams = NGrams::from("ab ba ab", &['a', 'b', ' ']);
+
+    assert_eq!(pairs(&ngrams, &[1, 4]), vec!["ab", "ba"]);
+    assert_eq!(ngrams.rolls, Rolls::Chain { runs: vec![vec![1], vec![4]], order: vec![0, 1, 0] });
+  }
+
+  #[test]
+  fn skips_what_cannot_be_typed() {
+    let ngrams = NGrams::from("aé b", &['a', 'b', ' ']);
+
+    assert_eq!(ngrams.length, 4);
+    assert_eq!(ngrams.symbols, vec![' ', 'a', 'b']);
+    assert_eq!(ngrams.unigrams.iter().sum::<usize>(), 3);
+  }
+
+  #[test]
+  fn reads_the_count_tables() {
+    let counts: Vec<(String, usize)> = vec![("a", 3), ("é", 1), ("b", 2), ("ab", 2), ("ba", 1), ("bé", 1), ("aba", 1), ("abab", 1)]
+      .into_iter().map(|(ngram, count)| (ngram.to_string(), count)).collect();
+    let ngrams = NGrams::from_counts(&counts, &['a', 'b', ' ']);
+    let all: Vec<u32> = (0..ngrams.bigrams.len() as u32).collect();
+
+    assert_eq!(ngrams.length, 3 + 1 + 2);
+    assert_eq!(ngrams.symbols, vec![' ', 'a', 'b']);
+    assert_eq!(ngrams.unigrams, vec![0, 3, 2]);
+    assert_eq!(pairs(&ngrams, &all), vec!["ab", "ba"]);
+    assert_eq!(ngrams.bigrams.iter().map(|(_, count)| *count).collect::<Vec<usize>>(), vec![2, 1]);
+    assert_eq!(ngrams.rolls, Rolls::Contexts(vec![(vec![0, 1, 0], 1)]));
+  }
+}
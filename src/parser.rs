@@ -34,23 +34,62 @@ pub fn mapping_for(template: &String) -> Mapping {
 
 pub fn two_layer_mapping_for(template: &String) -> TwoLayerMapping {
   let mut mapping = TwoLayerMapping::new();
-  let lines: Vec<&str> = template.trim().lines().collect();
-  
-  for (row, lines) in lines.chunks(2).enumerate() {
-    match lines {
-      [shifted_line, normals_line] => {
-        let normals = line_to_symbols(normals_line);
-        let shifted = line_to_symbols(shifted_line);
-
-        for (pos, (up, low)) in shifted.iter().zip(normals).enumerate() {
-          mapping.insert((row, pos), (up.to_string(), low.to_string()));
+  let lines: Vec<&str> = template.trim().lines()
+    .filter(|line| !is_blank_or_comment(line))
+    .collect();
+
+  // a lone shifted line has nothing to pair with, `structure_errors` reports it
+  for (row, lines) in lines.chunks_exact(2).enumerate() {
+    let normals = line_to_symbols(lines[1]);
+    let shifted = line_to_symbols(lines[0]);
+
+    for (pos, (up, low)) in shifted.iter().zip(normals).enumerate() {
+      mapping.insert((row, pos), (up.to_string(), low.to_string()));
+    }
+  }
+
+  mapping
+}
+
+// layout files can have `// comments` and empty lines between the rows
+pub fn is_blank_or_comment(line: &str) -> bool {
+  let trimmed = line.trim();
+
+  trimmed.is_empty() || trimmed.starts_with("//")
+}
+
+/// The rows that don't pair up, the layout can't be read with those
+pub fn structure_errors(template: &str) -> Vec<String> {
+  let mut errors = vec![];
+
+  for pair in symbol_lines(template).chunks(2) {
+    match pair {
+      [(shifted_number, shifted), (normal_number, normal)] => {
+        let (shifted_count, normal_count) = (shifted.split_whitespace().count(), normal.split_whitespace().count());
+
+        if shifted_count != normal_count {
+          errors.push(format!(
+            "line {} has {} shifted symbols, but line {} has {} normal ones",
+            shifted_number, shifted_count, normal_number, normal_count
+          ));
         }
       },
-      _ => panic!("unbalanced template lines")
+      [(number, _)] => errors.push(format!(
+        "line {} has no normal symbols line under it, the layout needs an even number of lines", number
+      )),
+      _ => {}
     }
   }
 
-  mapping
+  errors
+}
+
+// the lines with the symbols on them, numbered from 1
+pub fn symbol_lines(template: &str) -> Vec<(usize, &str)> {
+  template.lines().enumerate()
+    .filter(|(_, line)| !is_blank_or_comment(line))
+    .map(|(index, line)| (index + 1, line))
+    .collect()
 }
 
 fn line_to_symbols(line: &str) -> Vec<String> {
@@ -129,4 +168,27 @@ mod test {
       (1, 4) => ("(".to_string(), ",".to_string()) 
     });
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn it_skips_comments_and_empty_lines_in_two_layer_mappings() {
+    let template = "
+      // a comment
+
+      W L
+      w l
+    ".to_string();
+
+    assert_eq!(two_layer_mapping_for(&template), map! {
+      (0, 0) => ("W".to_string(), "w".to_string()), 
+      (0, 1) => ("L".to_string(), "l".to_string())
+    });
+  }
+
+  #[test]
+  fn it_skips_a_lone_last_line() {
+    assert_eq!(two_layer_mapping_for(&"W L\nw l\nS".to_string()), map! {
+      (0, 0) => ("W".to_string(), "w".to_string()),
+      (0, 1) => ("L".to_string(), "l".to_string())
+    });
+  }
+}
@@ -1,3 +1,6 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+
 use crate::config::*;
 use crate::layout::*;
 use crate::mutator::*;
@@ -9,24 +12,34 @@ pub struct Population {
 }
 
 impl Population {
-  pub fn new(normal: &Layout, elite: &Layout) -> Population {
-    let members = Population::create_members(normal, elite);
+  pub fn new(normal: &Layout, elite: &Layout, config: &Config, rng: &mut StdRng) -> Population {
+    let members = Population::create_members(normal, elite, config, rng);
 
     Population { members }
   }
 
-  fn create_members(normal: &Layout, elite: &Layout) -> Members {
-    let mutator = Mutator::new();
+  // a population bred from several parents, each one getting a fair share of the batch
+  pub fn mixed(parents: &[Layout], config: &Config, rng: &mut StdRng) -> Population {
+    let batch = Population::make_mixed_batch(parents, &config.population);
+    let members = Population::grow(parents.to_vec(), batch, config, rng);
 
-    let mut members = Members::new();
+    Population { members }
+  }
 
-    members.push((*normal).clone()); // retaining the original
+  fn create_members(normal: &Layout, elite: &Layout, config: &Config, rng: &mut StdRng) -> Members {
+    let members = vec![(*normal).clone()]; // retaining the original
+    let batch = Population::make_prestine_batch(normal, elite, &config.population);
+
+    Population::grow(members, batch, config, rng)
+  }
+
+  fn grow(mut members: Members, mut batch: Members, config: &Config, rng: &mut StdRng) -> Members {
+    let mutator = Mutator::new(&config.preserve, &config.classes, rng.gen());
+    let size = config.population.size;
 
-    let mut batch = Population::make_prestine_batch(normal, elite);
-    
     loop {
       batch = batch.iter().enumerate().map(|(i, layout)| {
-        if CONFIG.population.symbols && i % 2 != 0 {
+        if config.population.symbols && i % 2 != 0 {
           mutator.mutate_symbols(layout)
         } else {
           mutator.mutate_keys(layout)
@@ -35,16 +48,16 @@ impl Population {
 
       for layout in batch.iter() { members.push((*layout).clone()); }
 
-      if members.len() > CONFIG.population.size { break; }
+      if members.len() > size { break; }
     }
 
-    members.truncate(CONFIG.population.size);
+    members.truncate(size);
     members
   }
 
-  fn make_prestine_batch(normal: &Layout, elite: &Layout) -> Members {
-    let batch_size = ((CONFIG.population.size as f64) / (CONFIG.population.steps as f64)).ceil() as usize;
-    let elites_per_batch = ((batch_size as f64) * (CONFIG.population.elites as f64) / 100.0).ceil() as usize;
+  fn make_prestine_batch(normal: &Layout, elite: &Layout, config: &PopulationConfig) -> Members {
+    let batch_size = Population::batch_size(config);
+    let elites_per_batch = ((batch_size as f64) * (config.elites as f64) / 100.0).ceil() as usize;
     let normals_per_batch = batch_size - elites_per_batch;
 
     let mut batch = Members::new();
@@ -57,6 +70,16 @@ impl Population {
     batch
   }
 
+  fn make_mixed_batch(parents: &[Layout], config: &PopulationConfig) -> Members {
+    let batch_size = Population::batch_size(config);
+
+    parents.iter().cycle().take(batch_size).cloned().collect()
+  }
+
+  fn batch_size(config: &PopulationConfig) -> usize {
+    ((config.size as f64) / (config.steps as f64)).ceil() as usize
+  }
+
   pub fn deviation_for(self: &Self, member: &Layout) -> f64 {
     let original = &self.members[0];
     let mut diffs = 0;
@@ -72,13 +95,23 @@ impl Population {
 #[cfg(test)]
 mod test {
   use super::*;
+  use rand::SeedableRng;
+
+  fn rng() -> StdRng {
+    StdRng::seed_from_u64(42)
+  }
+
+  fn config() -> Config {
+    Config::defaults().unwrap()
+  }
 
   #[test]
   fn test_new() {
     let original = QWERTY.clone();
-    let population = Population::new(&original, &original);
+    let config = config();
+    let population = Population::new(&original, &original, &config, &mut rng());
 
-    assert_eq!(population.members.len(), CONFIG.population.size);
+    assert_eq!(population.members.len(), config.population.size);
     assert_eq!(population.members[0].template, original.template);
 
     assert_ne!(population.members[0].template, population.members[1].template);
@@ -86,11 +119,41 @@ mod test {
     assert_ne!(population.members[2].template, population.members[3].template);
   }
 
+  #[test]
+  fn test_mixed() {
+    let parents = vec![QWERTY.clone(), COLEMAK.clone(), WORKMAN.clone()];
+    let config = config();
+    let population = Population::mixed(&parents, &config, &mut rng());
+
+    assert_eq!(population.members.len(), config.population.size);
+    assert_eq!(&population.members[0..3], &parents[..]);
+
+    // every parent gets its own share of mutated offspring
+    for parent in parents.iter() {
+      let offspring = population.members.iter()
+        .filter(|member| differences(member, parent) <= 2 * config.population.steps)
+        .count();
+
+      assert!(offspring > config.population.steps, "{} has {} offspring", parent.name(), offspring);
+    }
+  }
+
+  #[test]
+  fn test_same_seed_same_members() {
+    let population1 = Population::new(&QWERTY, &COLEMAK, &config(), &mut rng());
+    let population2 = Population::new(&QWERTY, &COLEMAK, &config(), &mut rng());
+
+    assert_eq!(population1.members, population2.members);
+  }
+
+  fn differences(layout: &Layout, other: &Layout) -> usize {
+    layout.entries().iter().zip(other.entries()).filter(|(a, b)| **a != *b).count()
+  }
+
   #[test]
   fn test_deviation() {
     let original = QWERTY.clone();
-    let population = Population::new(&original, &original);
-
+    let population = Population::new(&original, &original, &config(), &mut rng());
 
     assert_eq!(population.deviation_for(&population.members[0]), 0.0);
     assert_eq!(population.deviation_for(&population.members[1]), 4.0/(original.template.len() as f64));
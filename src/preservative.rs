@@ -5,6 +5,10 @@ use crate::parser::*;
 
 type Positions = HashSet<Position>;
 
+// the placeholder for the symbols that can go anywhere, every other symbol
+// stays put, the lookalike • and · dots too
+const FREE: char = '∙';
+
 pub struct Preservative {
   pub restriced_positions: Positions
 }
@@ -15,21 +19,29 @@ impl Preservative {
     Preservative { restriced_positions }
   }
 
+  /// A template with keys but no `∙` placeholders would lock every one of
+  /// them, that's a template written with some other dot
+  pub fn check(template: &str) -> Result<(), String> {
+    let has_keys = template.lines().any(|line| !line.trim().is_empty());
+
+    if has_keys && !template.contains(FREE) {
+      return Err(format!("there are no {} placeholders, so every key would stay put", FREE));
+    }
+
+    Ok(())
+  }
+
   pub fn is_safe_position(self: &Self, position: Position) -> bool {
     !self.restriced_positions.contains(&position)
   }
 
   fn parse_positions(template: String) -> Positions {
-    let tmp_layout = Layout { template };
+    let tmp_layout = Layout::new(template);
     let mut restriced_positions = Positions::new();
   
     for (position, entry) in tmp_layout.entries().iter().enumerate() {
-      let shifted_is_okay = entry.shifted.chars().all(|c|
-        c.is_ascii_punctuation() || c.is_ascii_alphanumeric()
-      );
-      let normal_is_okay = entry.normal.chars().all(|c|
-        c.is_ascii_punctuation() || c.is_ascii_alphanumeric()
-      );
+      let shifted_is_okay = entry.shifted.chars().all(|c| c != FREE);
+      let normal_is_okay = entry.normal.chars().all(|c| c != FREE);
   
       if shifted_is_okay { restriced_positions.insert((position, 0)); }
       if normal_is_okay { restriced_positions.insert((position, 1)); }
@@ -76,4 +88,26 @@ mod test {
       (45, 1)
     ]);
   }
+
+  #[test]
+  fn checks_for_the_placeholders() {
+    assert_eq!(Preservative::check(""), Ok(()));
+    assert_eq!(Preservative::check("\n  ∙ ! ∙\n  q ∙ ∙\n"), Ok(()));
+    assert_eq!(
+      Preservative::check("\n  • ! •\n  q · ·\n"),
+      Err("there are no ∙ placeholders, so every key would stay put".to_string())
+    );
+  }
+
+  #[test]
+  fn preserves_any_alphabet() {
+    let presie = Preservative::from("
+      ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙
+      ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙
+        Й ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙
+        й λ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ ∙ «
+    ".to_string());
+
+    assert_eq!(presie.restriced_positions, set! [(13, 0), (13, 1), (14, 1), (25, 1)]);
+  }
 }
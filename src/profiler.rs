@@ -1,58 +1,51 @@
-use once_cell::sync::OnceCell;
-
 use crate::frequency::*;
 use crate::keyboard::*;
+use crate::symbols::*;
 
-pub fn calculate_fitness(keyboard: &Keyboard) -> f64 {
-  let symbols = symbols_by_frequency();
+pub fn calculate_fitness(keyboard: &Keyboard, frequencies: &SymbolFrequencies, classes: &Classes) -> f64 {
+  let symbols = &symbols_by_frequency(frequencies);
   let efforts = symbols_by_effort(&keyboard);
 
-  let max_score = total_possible_score(symbols);
+  let max_score = total_possible_score(symbols, classes);
   let mut score = 0;
 
   for (i, symbol) in symbols.iter().enumerate() {
     let band = symbols_in_band(&efforts, i);
     let fits = band.iter().any(|s| s == symbol);
 
-    if fits { score += score_for(symbol); }
+    if fits { score += score_for(symbol, classes); }
   }
 
   3.0 * (score as f64) / (max_score as f64)
 }
 
-fn total_possible_score(symbols: &Vec<String>) -> usize {
-  static TOTAL_SCORE: OnceCell<usize> = OnceCell::new();
-  *TOTAL_SCORE.get_or_init(|| {
-    symbols.iter()
-      .map(|symbol| score_for(symbol))
-      .sum()
-  })
+fn total_possible_score(symbols: &Vec<String>, classes: &Classes) -> usize {
+  symbols.iter()
+    .map(|symbol| score_for(symbol, classes))
+    .sum()
 }
 
-fn score_for(symbol: &String) -> usize {
-  match symbol.chars().nth(0).unwrap() {
-    'a'..='z' => 4,
-    '0'..='9' => 2,
-    _ => 1
+fn score_for(symbol: &String, classes: &Classes) -> usize {
+  match classes.class_of(symbol.chars().nth(0).unwrap()) {
+    Class::Letter => 4,
+    Class::Digit => 2,
+    Class::Other => 1
   }
 }
 
 // returns a list of symbols ordered by usage frequency
-fn symbols_by_frequency() -> &'static Vec<String> {
-  static SYMBOLS: OnceCell<Vec<String>> = OnceCell::new();
-  SYMBOLS.get_or_init(|| {
-    let mut sorted = CURRENT_FREQUENCIES.to_vec();
-    sorted.sort_by(|a,b| {
-      if a.1 == b.1 { a.0.cmp(&b.0) }
-      else { b.1.cmp(&a.1) }
-    });
-
-    sorted.iter()
-      .filter(|(symbol,_)| *symbol != ' ')
-      .filter(|(symbol,_)| !symbol.is_ascii_uppercase())
-      .map(|(symbol,_)| symbol.to_string())
-      .collect()
-  })
+fn symbols_by_frequency(frequencies: &SymbolFrequencies) -> Vec<String> {
+  let mut sorted = frequencies.to_vec();
+  sorted.sort_by(|a,b| {
+    if a.1 == b.1 { a.0.cmp(&b.0) }
+    else { b.1.cmp(&a.1) }
+  });
+
+  sorted.iter()
+    .filter(|(symbol,_)| *symbol != ' ')
+    .filter(|(symbol,_)| !symbol.is_uppercase())
+    .map(|(symbol,_)| symbol.to_string())
+    .collect()
 }
 
 // returns a list of symbol<>effort pairs that represent the keyboard
@@ -60,7 +53,7 @@ type SymbolEfforts = Vec<(String, usize)>;
 fn symbols_by_effort(keyboard: &Keyboard) -> SymbolEfforts {
   let mut sorted: SymbolEfforts = keyboard.key_map.iter()
     .filter(|(symbol, _)| **symbol != ' ')
-    .filter(|(symbol, _)| !symbol.is_ascii_uppercase())
+    .filter(|(symbol, _)| !symbol.is_uppercase())
     .map(|(symbol, key)| (symbol.to_string(), key.effort))
     .collect();
 
@@ -88,27 +81,36 @@ mod test {
   use super::*;
   use crate::layout::*;
   use crate::geometry::*;
+  use crate::config::Config;
+
+  fn frequencies() -> SymbolFrequencies {
+    Config::defaults().unwrap().corpora[0].frequencies().clone()
+  }
 
   #[test]
   fn test_total_possible_score() {
-    let symbols = symbols_by_frequency();
+    let symbols = symbols_by_frequency(&frequencies());
     
-    assert_eq!(total_possible_score(&symbols), 106);
+    assert_eq!(total_possible_score(&symbols, &Classes::default()), 114);
   }
 
   #[test]
   fn test_score_for() {
-    assert_eq!(score_for(&"a".to_string()), 4);
-    assert_eq!(score_for(&"z".to_string()), 4);
-    assert_eq!(score_for(&"0".to_string()), 2);
-    assert_eq!(score_for(&"9".to_string()), 2);
-    assert_eq!(score_for(&"<".to_string()), 1);
-    assert_eq!(score_for(&">".to_string()), 1);
+    let classes = Classes::default();
+
+    assert_eq!(score_for(&"a".to_string(), &classes), 4);
+    assert_eq!(score_for(&"z".to_string(), &classes), 4);
+    assert_eq!(score_for(&"ж".to_string(), &classes), 4);
+    assert_eq!(score_for(&"λ".to_string(), &classes), 4);
+    assert_eq!(score_for(&"0".to_string(), &classes), 2);
+    assert_eq!(score_for(&"9".to_string(), &classes), 2);
+    assert_eq!(score_for(&"<".to_string(), &classes), 1);
+    assert_eq!(score_for(&">".to_string(), &classes), 1);
   }
 
   #[test]
   fn test_symbols_by_frequency() {
-    let most_frequent_symbols = symbols_by_frequency();
+    let most_frequent_symbols = symbols_by_frequency(&frequencies());
     let top10 = &most_frequent_symbols[0..10];
     assert_eq!(top10, vec!["e", "a", "o", "i", "s", "n", "t", "r", "l", "p"]);
   }
@@ -155,15 +157,15 @@ mod test {
   fn get_fitness_for(layout: &Layout) -> f64 {
     let keyboard = Keyboard::from(layout, &US_PC_KEYBOARD);
 
-    calculate_fitness(&keyboard)
+    calculate_fitness(&keyboard, &frequencies(), &Classes::default())
   }
   
   #[test]
   fn test_selection() {   
-    assert_eq!(get_fitness_for(&QWERTY), 0.42452830188679247);
-    assert_eq!(get_fitness_for(&DVORAK), 0.5660377358490566);
-    assert_eq!(get_fitness_for(&COLEMAK), 0.8207547169811321);
-    assert_eq!(get_fitness_for(&WORKMAN), 0.7075471698113207);
-    assert_eq!(get_fitness_for(&HALMAK_21), 0.7924528301886793);
+    assert_eq!(get_fitness_for(&QWERTY), 0.39473684210526316);
+    assert_eq!(get_fitness_for(&DVORAK), 0.5263157894736842);
+    assert_eq!(get_fitness_for(&COLEMAK), 0.7631578947368421);
+    assert_eq!(get_fitness_for(&WORKMAN), 0.6578947368421053);
+    assert_eq!(get_fitness_for(&HALMAK_21), 0.7368421052631579);
   }
 }
\ No newline at end of file
@@ -0,0 +1,38 @@
+/**
+ * Draws a layout file as an SVG heatmap on the configured geometry
+ */
+use rusty_key::config::Config;
+use rusty_key::layout::*;
+use rusty_key::keyboard::*;
+use rusty_key::svg::{self,Tint};
+use crate::cli::Rendering;
+
+pub fn run(rendering: &Rendering, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+  let tint = tint_for(&rendering.tint)?;
+  let layout = Layout::read(&rendering.path)?;
+  let keyboard = Keyboard::from(&layout, &config.geometry);
+
+  print!("{}", svg::render(&keyboard, config, tint));
+
+  Ok(())
+}
+
+fn tint_for(name: &str) -> Result<Tint, String> {
+  match name {
+    "usage" => Ok(Tint::Usage),
+    "effort" => Ok(Tint::Effort),
+    _ => Err(format!("unknown tint '{}', expected usage or effort", name))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn picks_the_tint() {
+    assert_eq!(tint_for("usage"), Ok(Tint::Usage));
+    assert_eq!(tint_for("effort"), Ok(Tint::Effort));
+    assert_eq!(tint_for("blah"), Err("unknown tint 'blah', expected usage or effort".to_string()));
+  }
+}
@@ -7,9 +7,10 @@
  * 4. Use degrading probability to pick the next fittest score
  */
 use rand::Rng;
+use rand::rngs::StdRng;
 use core::cmp::Ordering::Less;
 
-use crate::config::CONFIG;
+use crate::config::SelectionConfig;
 
 #[derive(PartialEq,Debug)]
 pub struct Score {
@@ -21,24 +22,24 @@ pub type Scores = Vec<Score>;
 type RankSpace = Vec<(usize, f64)>;
 
 pub struct Selection {
-  pub scores: Scores
+  pub scores: Scores,
+  pub config: SelectionConfig
 }
 
 impl Selection {
-  pub fn lucky_draw(self: &Self) -> &Score {
+  pub fn lucky_draw(self: &Self, rng: &mut StdRng) -> &Score {
     let rank_space = self.create_rank_space();
-    let (index, _) = self.select_from_rank_space(&rank_space);
+    let (index, _) = self.select_from_rank_space(&rank_space, rng);
     &self.scores[index]
   }
 
-  fn select_from_rank_space(self: &Self, list: &RankSpace) -> (usize, f64) {
-    let mut rng = rand::thread_rng();
-    let lucky = rng.gen_range(0..100) < CONFIG.selection.rank_space_cut_off;
+  fn select_from_rank_space(self: &Self, list: &RankSpace, rng: &mut StdRng) -> (usize, f64) {
+    let lucky = rng.gen_range(0..100) < self.config.rank_space_cut_off;
 
     if lucky || list.len() == 1 {
       *list.first().unwrap()
     } else {
-      self.select_from_rank_space(&(&list[1..]).to_vec())
+      self.select_from_rank_space(&(&list[1..]).to_vec(), rng)
     }
   }
 
@@ -54,7 +55,7 @@ impl Selection {
     self.renormalise().iter().map(|score| {
       // recalculating from the top right corner
       let x = (1.0 - score.performance).powf(2.0);
-      let y = (1.0 - score.deviation * CONFIG.selection.diversity_bias).powf(2.0); // deprioritizing the deviation
+      let y = (1.0 - score.deviation * self.config.diversity_bias).powf(2.0); // deprioritizing the deviation
       
       (x + y).sqrt() // distance from the top right corner
     })
@@ -76,6 +77,12 @@ impl Selection {
 #[cfg(test)]
 mod test {
   use super::*;
+  use rand::SeedableRng;
+  use crate::config::ExternalConfig;
+
+  fn selection() -> Selection {
+    Selection { scores: get_scores(), config: ExternalConfig::default().selection }
+  }
 
   fn get_scores() -> Scores {
     vec![
@@ -88,11 +95,12 @@ mod test {
 
   #[test]
   fn test_selection_of_the_fittest() {
-    let selection = Selection { scores: get_scores() };
+    let selection = selection();
+    let mut rng = StdRng::seed_from_u64(42);
     let mut the_fittest_was_selected = 0;
 
     for _ in 0..1000 {
-      let selected = selection.lucky_draw();
+      let selected = selection.lucky_draw(&mut rng);
       let the_fittest = &selection.scores[3];
 
       println!("{:?}", selected);
@@ -107,7 +115,7 @@ mod test {
 
   #[test]
   fn test_rank_space_selection() {
-    let selection = Selection { scores: get_scores() };
+    let selection = selection();
     let rank_space = selection.create_rank_space();
 
     let first = rank_space[0];
@@ -119,9 +127,10 @@ mod test {
     let mut second_selected = 0;
     let mut third_selected = 0;
     let mut fourth_selected = 0;
+    let mut rng = StdRng::seed_from_u64(42);
 
     for _ in 0..1000 {
-      let selected = selection.select_from_rank_space(&rank_space);
+      let selected = selection.select_from_rank_space(&rank_space, &mut rng);
       if selected == first { first_selected += 1; }
       else if selected == second { second_selected += 1; }
       else if selected == third { third_selected += 1; }
@@ -137,9 +146,20 @@ mod test {
     assert!((50..150).contains(&fourth_selected));
   }
 
+  #[test]
+  fn test_same_seed_same_draws() {
+    let selection = selection();
+    let mut rng1 = StdRng::seed_from_u64(7);
+    let mut rng2 = StdRng::seed_from_u64(7);
+
+    for _ in 0..100 {
+      assert_eq!(selection.lucky_draw(&mut rng1), selection.lucky_draw(&mut rng2));
+    }
+  }
+
   #[test]
   fn test_creation_of_rank_space() {
-    let sel = Selection { scores: get_scores() };
+    let sel = selection();
 
     assert_eq!(sel.create_rank_space(), vec![
       (3, 0.0), 
@@ -151,7 +171,7 @@ mod test {
 
   #[test]
   fn test_ratings_calculation() {
-    let sel = Selection { scores: get_scores() };
+    let sel = selection();
 
     assert_eq!(sel.calculate_ranks(), vec![
       0.774620378602204, 
@@ -163,7 +183,7 @@ mod test {
   
   #[test]
   fn test_renormalise() {
-    let sel = Selection { scores: get_scores() };
+    let sel = selection();
 
     assert_eq!(sel.renormalise(), vec![
       Score { performance: 0.7333333333333334, deviation: 0.2727272727272727 }, 
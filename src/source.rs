@@ -1,20 +1,268 @@
+/**
+ * The texts layouts get scored against. A config can have several named
+ * corpora, prose and code say, each with a weight in the total score.
+ * A corpus can also come as the n-gram counts of a text, the way other
+ * analyzers and public datasets ship them, in `.tsv` files with an
+ * `ngram<TAB>count` a line, newlines, tabs and backslashes escaped C style
+ */
 use std::io;
 use std::fs;
+use std::path::{Path,PathBuf};
+use std::sync::{Arc,RwLock};
+use hashbrown::HashMap;
+use once_cell::sync::OnceCell;
 
-pub fn load(folder: String) -> Result<String, io::Error> {
-  let pathname = format!("assets/sources/{}", folder);
-  let filenames = fs::read_dir(pathname).unwrap();
+use crate::frequency::*;
+use crate::ngrams::NGrams;
+
+// how many odd symbol sets get their n-grams kept, past the first one
+const NGRAMS_CACHE_SIZE: usize = 16;
+
+/// The n-grams of a text with how many times each one shows up
+pub type Counts = Vec<(String, usize)>;
+
+#[derive(Debug,PartialEq)]
+pub enum Text {
+  Plain(String),
+  Counted(Counts)
+}
+
+#[derive(Debug)]
+pub struct Corpus {
+  pub name: String,
+  pub weight: usize,
+  pub text: Text,
+
+  frequencies_cache: OnceCell<SymbolFrequencies>,
+  // the layouts of a run mostly have the same symbols, the rest go to the others
+  ngrams_cache: OnceCell<(Vec<char>, Arc<NGrams>)>,
+  other_ngrams_cache: RwLock<HashMap<Vec<char>, Arc<NGrams>>>
+}
+
+impl Corpus {
+  pub fn new(name: &str, weight: usize, data: String) -> Corpus {
+    Corpus::from(name, weight, Text::Plain(data))
+  }
+
+  pub fn from(name: &str, weight: usize, text: Text) -> Corpus {
+    Corpus {
+      name: name.to_string(), weight, text,
+      frequencies_cache: OnceCell::new(), ngrams_cache: OnceCell::new(), other_ngrams_cache: RwLock::new(HashMap::new())
+    }
+  }
+
+  /// How often each symbol shows up in the text
+  pub fn frequencies(&self) -> &SymbolFrequencies {
+    self.frequencies_cache.get_or_init(|| match &self.text {
+      Text::Plain(data) => calculate_frequencies(data).into_iter()
+        .map(|(symbol, count)| (symbol.chars().next().unwrap(), count))
+        .collect(),
+      Text::Counted(counts) => counted_frequencies(counts)
+    })
+  }
+
+  /// The n-grams the layouts get scored over, counted once for every set
+  /// of symbols the layouts have keys for. The first set stays for good, a
+  /// few more get kept for the layouts with other symbols
+  pub fn ngrams(&self, typeable: &[char]) -> Arc<NGrams> {
+    let (symbols, ngrams) = self.ngrams_cache.get_or_init(|| (typeable.to_vec(), self.count_ngrams(typeable)));
+
+    if symbols == typeable {
+      return ngrams.clone();
+    }
+
+    if let Some(ngrams) = self.other_ngrams_cache.read().unwrap().get(typeable) {
+      return ngrams.clone();
+    }
+
+    let ngrams = self.count_ngrams(typeable);
+    let mut cache = self.other_ngrams_cache.write().unwrap();
+
+    if cache.len() >= NGRAMS_CACHE_SIZE {
+      cache.clear();
+    }
+
+    cache.insert(typeable.to_vec(), ngrams.clone());
+    ngrams
+  }
+
+  fn count_ngrams(&self, typeable: &[char]) -> Arc<NGrams> {
+    Arc::new(match &self.text {
+      Text::Plain(data) => NGrams::from(data, typeable),
+      Text::Counted(counts) => NGrams::from_counts(counts, typeable)
+    })
+  }
+}
+
+/// Reads a text file, or all the files in a directory one after another.
+/// The `.tsv` files get read as n-gram counts, all of them have to be for
+/// a directory
+pub fn read(path: &str) -> Result<Text, io::Error> {
+  let filenames = if Path::new(path).is_dir() {
+    let mut filenames = vec![];
+
+    for entry in fs::read_dir(path)? {
+      filenames.push(entry?.path());
+    }
+
+    filenames.sort();
+    filenames
+  } else {
+    vec![PathBuf::from(path)]
+  };
+
+  let tables = filenames.iter().filter(|filename| is_table(filename)).count();
+
+  if tables > 0 && tables < filenames.len() {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, "mixes n-gram counts with plain text files"));
+  }
 
   let mut contents = Vec::new();
 
-  for path in filenames {
-    let content = fs::read_to_string(path.unwrap().path().as_os_str())?;
-    contents.push(content);
+  for filename in &filenames {
+    contents.push(fs::read_to_string(filename)?);
+  }
+
+  if tables == 0 {
+    return Ok(Text::Plain(contents.join("\n\n")));
   }
 
-  Ok(contents.join("\n\n"))
+  let mut counts = Counts::new();
+
+  for (filename, content) in filenames.iter().zip(&contents) {
+    let table = parse_counts(content)
+      .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", filename.to_string_lossy(), err)))?;
+
+    counts.extend(table);
+  }
+
+  Ok(Text::Counted(counts))
 }
 
-pub fn load_english_text() -> String {
-  load(String::from("text")).unwrap()
-}
\ No newline at end of file
+/// Reads the `ngram<TAB>count` lines, the blank ones get skipped
+pub fn parse_counts(content: &str) -> Result<Counts, String> {
+  let mut counts = Counts::new();
+
+  for (index, line) in content.lines().enumerate() {
+    if line.trim().is_empty() { continue; }
+
+    let mut parts = line.rsplitn(2, '\t');
+    let count = parts.next().unwrap_or_default().trim();
+    let ngram = match parts.next() {
+      Some(ngram) if !ngram.is_empty() => ngram,
+      _ => return Err(format!("line {} is not an ngram<TAB>count pair", index + 1))
+    };
+    let count = count.parse::<usize>()
+      .map_err(|_| format!("line {} has '{}' for the count, expected a whole number", index + 1, count))?;
+
+    counts.push((unescape(ngram), count));
+  }
+
+  Ok(counts)
+}
+
+// the newlines and tabs come as \n and \t in the tables, and the backslash as \\
+fn unescape(ngram: &str) -> String {
+  let mut symbols = String::new();
+  let mut chars = ngram.chars();
+
+  while let Some(symbol) = chars.next() {
+    match (symbol, chars.clone().next()) {
+      ('\\', Some('n')) => { symbols.push('\n'); chars.next(); },
+      ('\\', Some('t')) => { symbols.push('\t'); chars.next(); },
+      ('\\', Some('\\')) => { symbols.push('\\'); chars.next(); },
+      _ => symbols.push(symbol)
+    }
+  }
+
+  symbols
+}
+
+fn is_table(filename: &Path) -> bool {
+  filename.extension().is_some_and(|extension| extension == "tsv")
+}
+
+// the single symbol rows, the tables without them get their symbols off the pairs
+fn counted_frequencies(counts: &Counts) -> SymbolFrequencies {
+  let mut frequencies: HashMap<char, usize> = HashMap::new();
+  let length = if counts.iter().any(|(ngram, _)| ngram.chars().count() == 1) { 1 } else { 2 };
+
+  for (ngram, count) in counts.iter().filter(|(ngram, _)| ngram.chars().count() == length) {
+    *frequencies.entry(ngram.chars().last().unwrap()).or_insert(0) += count;
+  }
+
+  frequencies.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn counts_the_symbols() {
+    let corpus = Corpus::new("test", 1, "abba".to_string());
+    let mut frequencies = corpus.frequencies().clone();
+    frequencies.sort();
+
+    assert_eq!(frequencies, vec![('a', 2), ('b', 2)]);
+  }
+
+  #[test]
+  fn keeps_the_ngrams_of_every_symbol_set() {
+    let corpus = Corpus::new("test", 1, "ab ba".to_string());
+    let (usual, other) = (corpus.ngrams(&[' ', 'a', 'b']), corpus.ngrams(&[' ', 'a']));
+
+    assert!(Arc::ptr_eq(&usual, &corpus.ngrams(&[' ', 'a', 'b'])));
+    assert!(Arc::ptr_eq(&other, &corpus.ngrams(&[' ', 'a'])));
+    assert_eq!(other.symbols, vec![' ', 'a']);
+
+    for size in 0..NGRAMS_CACHE_SIZE {
+      corpus.ngrams(&vec!['a'; size + 1]);
+    }
+
+    assert!(!Arc::ptr_eq(&other, &corpus.ngrams(&[' ', 'a'])));
+    assert!(Arc::ptr_eq(&usual, &corpus.ngrams(&[' ', 'a', 'b'])));
+  }
+
+  #[test]
+  fn reads_files_and_directories() {
+    let directory = std::env::temp_dir().join("rusty-key-source-test");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("b.txt"), "second").unwrap();
+    fs::write(directory.join("a.txt"), "first").unwrap();
+
+    assert_eq!(read(directory.to_str().unwrap()).unwrap(), Text::Plain("first\n\nsecond".to_string()));
+    assert_eq!(read(directory.join("b.txt").to_str().unwrap()).unwrap(), Text::Plain("second".to_string()));
+    assert!(read("blah").is_err());
+
+    fs::write(directory.join("c.tsv"), "ab\t2\n").unwrap();
+    assert_eq!(read(directory.join("c.tsv").to_str().unwrap()).unwrap(), Text::Counted(vec![("ab".to_string(), 2)]));
+    assert_eq!(read(directory.to_str().unwrap()).unwrap_err().to_string(), "mixes n-gram counts with plain text files");
+
+    fs::remove_dir_all(directory).unwrap();
+  }
+
+  #[test]
+  fn parses_the_counts() {
+    assert_eq!(parse_counts("a\t3\n \t2\n\na b\t1\n\t\t4").unwrap(), vec![
+      ("a".to_string(), 3), (" ".to_string(), 2), ("a b".to_string(), 1), ("\t".to_string(), 4)
+    ]);
+    assert_eq!(parse_counts("\\n\\t\t1\n\\\\n\t2\n\\x\t3").unwrap(), vec![
+      ("\n\t".to_string(), 1), ("\\n".to_string(), 2), ("\\x".to_string(), 3)
+    ]);
+    assert_eq!(parse_counts("a\t3\nb 2").unwrap_err(), "line 2 is not an ngram<TAB>count pair");
+    assert_eq!(parse_counts("a\tmany").unwrap_err(), "line 1 has 'many' for the count, expected a whole number");
+  }
+
+  #[test]
+  fn counts_the_symbols_of_a_table() {
+    let with_symbols = Corpus::from("test", 1, Text::Counted(vec![("a".to_string(), 3), ("b".to_string(), 1), ("ab".to_string(), 1)]));
+    let with_pairs = Corpus::from("test", 1, Text::Counted(vec![("ab".to_string(), 2), ("bb".to_string(), 1)]));
+    let (mut first, mut second) = (with_symbols.frequencies().clone(), with_pairs.frequencies().clone());
+    first.sort();
+    second.sort();
+
+    assert_eq!(first, vec![('a', 3), ('b', 1)]);
+    assert_eq!(second, vec![('b', 3)]);
+  }
+}
@@ -0,0 +1,188 @@
+/**
+ * Decides when an evolution run has gone far enough. A run stops on the
+ * first criteria met: the generations count, the time budget, or the best
+ * score not improving for a number of generations in a row
+ */
+use std::fmt;
+use std::time::{Duration,Instant};
+use serde::{Serialize,Deserialize};
+
+use crate::config::*;
+use crate::generation::Outcome;
+use crate::layout::Layout;
+use crate::summary::Summary;
+
+#[derive(Debug,Clone,Copy,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+  MaxGenerations,
+  TimeLimit,
+  Stagnation
+}
+
+// the final word of a run, the best layout it has ever seen
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct Finish {
+  pub reason: Reason,
+  pub generation_number: usize,
+  pub best: Layout,
+  pub best_summary: Summary
+}
+
+pub struct Tracker {
+  criteria: StoppingConfig,
+  weights: WeightsConfig,
+  started_at: Instant,
+  best: Option<Outcome>,
+  stale: usize,
+  // the generations this run made, a resumed one counts from where it picked up
+  generations: usize
+}
+
+impl fmt::Display for Reason {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Reason::MaxGenerations => write!(f, "reached the generations limit"),
+      Reason::TimeLimit => write!(f, "ran out of time"),
+      Reason::Stagnation => write!(f, "the best score stopped improving")
+    }
+  }
+}
+
+impl Tracker {
+  // the history of a resumed run counts towards the stagnation too
+  pub fn new(config: &Config, history: &[Outcome]) -> Tracker {
+    let mut tracker = Tracker {
+      criteria: config.stopping,
+      weights: config.weights.clone(),
+      started_at: Instant::now(),
+      best: None,
+      stale: 0,
+      generations: 0
+    };
+
+    for outcome in history {
+      tracker.track(outcome);
+    }
+
+    tracker
+  }
+
+  pub fn check(&mut self, outcome: &Outcome) -> Option<Finish> {
+    self.track(outcome);
+    self.generations += 1;
+
+    let reason = self.reason_to_stop()?;
+    let best = self.best.as_ref().unwrap();
+
+    Some(Finish {
+      reason,
+      generation_number: outcome.generation_number,
+      best: best.best.clone(),
+      best_summary: best.best_summary.clone()
+    })
+  }
+
+  fn track(&mut self, outcome: &Outcome) {
+    let improved = match &self.best {
+      Some(best) => outcome.best_summary.score(&self.weights) > best.best_summary.score(&self.weights),
+      None => true
+    };
+
+    if improved {
+      self.best = Some(outcome.clone());
+      self.stale = 0;
+    } else {
+      self.stale += 1;
+    }
+  }
+
+  fn reason_to_stop(&self) -> Option<Reason> {
+    let StoppingConfig { max_generations, time_limit, stagnation } = self.criteria;
+
+    if max_generations > 0 && self.generations >= max_generations {
+      Some(Reason::MaxGenerations)
+    } else if time_limit > 0 && self.started_at.elapsed() >= Duration::from_secs(time_limit) {
+      Some(Reason::TimeLimit)
+    } else if stagnation > 0 && self.stale >= stagnation {
+      Some(Reason::Stagnation)
+    } else {
+      None
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::layout::*;
+
+  fn outcome(generation_number: usize, effort: f64) -> Outcome {
+    let summary = Summary { effort, overheads: 1.0, awkwardness: 1.0, rollingness: 1.0, fitness: 1.0 };
+
+    Outcome {
+      generation_number,
+      winner: QWERTY.clone(),
+      winner_summary: summary.clone(),
+      best: if effort < 2.0 { COLEMAK.clone() } else { QWERTY.clone() },
+      best_summary: summary
+    }
+  }
+
+  fn criteria(max_generations: usize, time_limit: u64, stagnation: usize) -> Config {
+    let mut config = Config::defaults().unwrap();
+    config.stopping = StoppingConfig { max_generations, time_limit, stagnation };
+    config
+  }
+
+  #[test]
+  fn never_stops_by_default() {
+    let mut tracker = Tracker::new(&criteria(0, 0, 0), &[]);
+
+    for number in 1..100 {
+      assert_eq!(tracker.check(&outcome(number, 2.0)), None);
+    }
+  }
+
+  #[test]
+  fn stops_at_max_generations() {
+    let mut tracker = Tracker::new(&criteria(3, 0, 0), &[]);
+
+    assert_eq!(tracker.check(&outcome(1, 2.0)), None);
+    assert_eq!(tracker.check(&outcome(2, 1.0)), None);
+
+    let finish = tracker.check(&outcome(3, 2.0)).unwrap();
+    assert_eq!(finish.reason, Reason::MaxGenerations);
+    assert_eq!(finish.generation_number, 3);
+    assert_eq!(finish.best, COLEMAK.clone());
+  }
+
+  #[test]
+  fn counts_the_generations_from_where_it_resumed() {
+    let history: Vec<Outcome> = (1..=10).map(|number| outcome(number, 2.0)).collect();
+    let mut tracker = Tracker::new(&criteria(2, 0, 0), &history);
+
+    assert_eq!(tracker.check(&outcome(11, 2.0)), None);
+    assert_eq!(tracker.check(&outcome(12, 2.0)).unwrap().generation_number, 12);
+  }
+
+  #[test]
+  fn stops_when_out_of_time() {
+    let mut tracker = Tracker::new(&criteria(0, 1, 0), &[]);
+    tracker.started_at -= Duration::from_secs(2);
+
+    assert_eq!(tracker.check(&outcome(1, 2.0)).unwrap().reason, Reason::TimeLimit);
+  }
+
+  #[test]
+  fn stops_on_stagnation() {
+    let mut tracker = Tracker::new(&criteria(0, 0, 2), &[outcome(1, 2.0)]);
+
+    assert_eq!(tracker.check(&outcome(2, 1.0)), None);
+    assert_eq!(tracker.check(&outcome(3, 1.5)), None);
+
+    let finish = tracker.check(&outcome(4, 1.0)).unwrap();
+    assert_eq!(finish.reason, Reason::Stagnation);
+    assert_eq!(finish.best_summary.effort, 1.0);
+  }
+}
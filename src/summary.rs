@@ -1,11 +1,13 @@
 use std::fmt;
+use serde::{Serialize,Deserialize};
 
-use crate::config::CONFIG;
+use crate::config::*;
 use crate::keyboard::*;
 use crate::calculator;
 use crate::profiler;
+use crate::source::Corpus;
 
-#[derive(Debug,PartialEq,Clone)]
+#[derive(Debug,PartialEq,Clone,Serialize,Deserialize)]
 pub struct Summary {
   pub effort: f64,
   pub overheads: f64,
@@ -24,9 +26,23 @@ impl fmt::Display for Summary  {
 }
 
 impl Summary {
-  pub fn calculate(keyboard: &Keyboard) -> Summary {
-    let result = calculator::process(keyboard);
-    let fitness = profiler::calculate_fitness(keyboard);
+  /// Scores the keyboard against the config corpora, the metrics are
+  /// averaged by the corpora weights
+  pub fn calculate(keyboard: &Keyboard, config: &Config) -> Summary {
+    Summary::combine(&Summary::per_corpus(keyboard, config))
+  }
+
+  /// Scores the keyboard against every corpus on its own
+  pub fn per_corpus<'a>(keyboard: &Keyboard, config: &'a Config) -> Vec<(&'a Corpus, Summary)> {
+    config.corpora.iter()
+      .map(|corpus| (corpus, Summary::for_corpus(keyboard, config, corpus)))
+      .collect()
+  }
+
+  /// Scores the keyboard against a single text
+  pub fn for_corpus(keyboard: &Keyboard, config: &Config, corpus: &Corpus) -> Summary {
+    let result = calculator::process(keyboard, config, corpus);
+    let fitness = profiler::calculate_fitness(keyboard, corpus.frequencies(), &config.classes);
 
     Summary {
       effort: result.effort,
@@ -37,9 +53,24 @@ impl Summary {
     }
   }
 
-  pub fn score(self: &Self) -> f64 {
-    let weights = &CONFIG.weights;
-    
+  /// The weighted average of the corpora summaries
+  pub fn combine(summaries: &[(&Corpus, Summary)]) -> Summary {
+    let total = summaries.iter().map(|(corpus, _)| corpus.weight).sum::<usize>().max(1) as f64;
+    let average = |metric: fn(&Summary) -> f64| {
+      summaries.iter().map(|(corpus, summary)| corpus.weight as f64 * metric(summary)).sum::<f64>() / total
+    };
+
+    Summary {
+      effort: average(|summary| summary.effort),
+      overheads: average(|summary| summary.overheads),
+      awkwardness: average(|summary| summary.awkwardness),
+      rollingness: average(|summary| summary.rollingness),
+      fitness: average(|summary| summary.fitness)
+    }
+  }
+
+  /// Weights the metrics into a single number, the higher the better
+  pub fn score(self: &Self, weights: &WeightsConfig) -> f64 {
     (weights.rollingness as f64) * self.rollingness / 18.0 +
     (weights.fitness as f64) * self.fitness / 18.0 +
     (weights.effort as f64) * 9.0 / self.effort +
@@ -52,8 +83,39 @@ impl Summary {
 mod test {
   use super::*;
   
+  #[test]
+  fn combines_the_corpora_by_weight() {
+    let prose = Corpus::new("prose", 3, "".to_string());
+    let code = Corpus::new("code", 1, "".to_string());
+    let summary = |value: f64| Summary { effort: value, overheads: value, awkwardness: value, rollingness: value, fitness: value };
+
+    assert_eq!(Summary::combine(&[(&prose, summary(2.0)), (&code, summary(6.0))]), summary(3.0));
+    assert_eq!(Summary::combine(&[(&prose, summary(2.5))]), summary(2.5));
+  }
+
+  #[test]
+  fn scores_every_corpus() {
+    use crate::layout::QWERTY;
+    use crate::geometry::US_PC_KEYBOARD;
+
+    let layout = QWERTY.clone();
+    let keyboard = Keyboard::from(&layout, &US_PC_KEYBOARD);
+    let config = Config::with_corpora(ExternalConfig::default(), vec![
+      Corpus::new("prose", 7, "the quick brown fox jumps over the lazy dog".to_string()),
+      Corpus::new("code", 3, "fn main() { println!(\"{}\", x[0]); }".to_string())
+    ]).unwrap();
+
+    let summaries = Summary::per_corpus(&keyboard, &config);
+    let names: Vec<&str> = summaries.iter().map(|(corpus, _)| corpus.name.as_str()).collect();
+
+    assert_eq!(names, vec!["prose", "code"]);
+    assert!(summaries[1].1.effort > summaries[0].1.effort);
+    assert_eq!(Summary::calculate(&keyboard, &config), Summary::combine(&summaries));
+  }
+
   #[test]
   fn test_score() {
+    let weights = ExternalConfig::default().weights;
     let qwerty = Summary {
       effort: 25.364358927857115,
       overheads: 21.749859480289295,
@@ -82,9 +144,9 @@ mod test {
       rollingness: 8.13194318501912,
       fitness: 8.775510204081632
     };
-    assert_eq!(qwerty.score(), 1.3943012416927965);
-    assert_eq!(dvorak.score(), 2.2856546599937184);
-    assert_eq!(workman.score(), 2.5427443285981193);
-    assert_eq!(halmak.score(), 2.805648048745065);
+    assert_eq!(qwerty.score(&weights), 1.3943012416927965);
+    assert_eq!(dvorak.score(&weights), 2.2856546599937184);
+    assert_eq!(workman.score(&weights), 2.5427443285981193);
+    assert_eq!(halmak.score(&weights), 2.805648048745065);
   }
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+/**
+ * Draws a keyboard as an SVG picture. The keys go where the geometry
+ * template puts them, with the normal and shifted legends, and get tinted
+ * by how much the text uses them or by how much effort they cost
+ */
+use hashbrown::HashMap;
+
+use crate::config::Config;
+use crate::calculator::{self,Calculator};
+use crate::frequency::SymbolFrequencies;
+use crate::source::Corpus;
+use crate::keyboard::Keyboard;
+use crate::parser::Position;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Tint {
+  Usage,
+  Effort
+}
+
+const UNIT: f64 = 60.0;
+const KEY_SIZE: f64 = 56.0;
+const PADDING: f64 = 10.0;
+const TITLE_HEIGHT: f64 = 40.0;
+
+/// The SVG picture of the keyboard tinted against the config text
+pub fn render(keyboard: &Keyboard, config: &Config, tint: Tint) -> String {
+  let values = weighted(config, |corpus| match tint {
+    Tint::Usage => usage_for(keyboard, corpus.frequencies()),
+    Tint::Effort => Calculator::from(keyboard, &config.penalties).efforts(&calculator::ngrams_for(keyboard, corpus))
+  });
+  let max = values.values().copied().fold(f64::EPSILON, f64::max);
+  let legends = legends_for(keyboard);
+  let offsets = keyboard.geometry.offsets();
+  let key_width = key_width_for(&offsets);
+
+  let mut keys = vec![];
+  let mut width: f64 = 0.0;
+  let mut height: f64 = 0.0;
+
+  for (position, _) in keyboard.geometry.positions() {
+    let x = PADDING + offsets[&position] as f64 * UNIT / key_width;
+    let y = TITLE_HEIGHT + position.0 as f64 * UNIT;
+    let value = values.get(&position).copied().unwrap_or(0.0);
+    let (normal, shifted) = legends.get(&position).cloned().unwrap_or_default();
+
+    keys.push(format!(
+      "  <g transform=\"translate({},{})\">\n    <title>{:.2}%</title>\n    <rect width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\" stroke=\"#555\"/>\n    <text x=\"8\" y=\"20\" font-size=\"12\" fill=\"#555\">{}</text>\n    <text x=\"8\" y=\"46\" font-size=\"18\">{}</text>\n  </g>",
+      x, y, value * 100.0, KEY_SIZE, KEY_SIZE, color_for(value / max), escape(&shifted), escape(&normal)
+    ));
+
+    width = width.max(x + UNIT + PADDING);
+    height = height.max(y + UNIT + PADDING);
+  }
+
+  let tint_name = match tint { Tint::Usage => "usage", Tint::Effort => "effort" };
+
+  format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\">\n  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n  <text x=\"{}\" y=\"26\" font-size=\"16\">{}, {}</text>\n{}\n</svg>\n",
+    width, height, width, height, PADDING, escape(&keyboard.name), tint_name, keys.join("\n")
+  )
+}
+
+// the templates space the keys out differently, the tightest gap is a key width
+fn key_width_for(offsets: &HashMap<Position, usize>) -> f64 {
+  offsets.iter()
+    .filter_map(|(&(row, col), offset)| Some(offset - offsets.get(&(row, col.checked_sub(1)?))?))
+    .min()
+    .unwrap_or(2) as f64
+}
+
+// the per key shares of every corpus, averaged by the corpora weights,
+// so a long text doesn't outweigh a short one with the same weight
+fn weighted<F: Fn(&Corpus) -> HashMap<Position, usize>>(config: &Config, values_for: F) -> HashMap<Position, f64> {
+  let weights = config.corpora.iter().map(|corpus| corpus.weight).sum::<usize>().max(1) as f64;
+  let mut shares = HashMap::new();
+
+  for corpus in &config.corpora {
+    let values = values_for(corpus);
+    let total = values.values().sum::<usize>().max(1) as f64;
+
+    for (position, value) in values {
+      *shares.entry(position).or_insert(0.0) += value as f64 / total * corpus.weight as f64 / weights;
+    }
+  }
+
+  shares
+}
+
+// how many times the text hits every key
+fn usage_for(keyboard: &Keyboard, frequencies: &SymbolFrequencies) -> HashMap<Position, usize> {
+  let mut usage = HashMap::new();
+
+  for (symbol, count) in frequencies {
+    if let Some(key) = keyboard.key_for(symbol) {
+      *usage.entry(key.position).or_insert(0) += count;
+    }
+  }
+
+  usage
+}
+
+// the normal and shifted symbols on every key, the specials show their own symbol
+fn legends_for(keyboard: &Keyboard) -> HashMap<Position, (String, String)> {
+  let geometry = keyboard.geometry;
+  let mut legends = HashMap::new();
+
+  for (position, symbol) in geometry.positions() {
+    if geometry.special_at(position).is_some() {
+      legends.insert(position, (symbol, "".to_string()));
+    }
+  }
+
+  for entry in keyboard.layout.entries() {
+    if let Some(key) = geometry.key_for_layout(entry.position) {
+      legends.insert(key.position, (entry.normal, entry.shifted));
+    }
+  }
+
+  legends
+}
+
+// goes from white for the idle keys to red for the busiest ones
+fn color_for(share: f64) -> String {
+  let channel = |from: f64, to: f64| (from + (to - from) * share).round() as u8;
+
+  format!("#{:02x}{:02x}{:02x}", channel(255.0, 230.0), channel(255.0, 60.0), channel(255.0, 40.0))
+}
+
+fn escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::layout::*;
+  use crate::geometry::*;
+
+  fn keys(svg: &str) -> Vec<&str> {
+    svg.split("<g ").skip(1).collect()
+  }
+
+  #[test]
+  fn tints_from_white_to_red() {
+    assert_eq!(color_for(0.0), "#ffffff");
+    assert_eq!(color_for(1.0), "#e63c28");
+  }
+
+  #[test]
+  fn measures_the_key_width() {
+    assert_eq!(key_width_for(&US_PC_KEYBOARD.offsets()), 2.0);
+    assert_eq!(key_width_for(&HashMap::new()), 2.0);
+  }
+
+  #[test]
+  fn weighs_the_corpora() {
+    let mut config = Config::defaults().unwrap();
+    config.corpora = vec![Corpus::new("prose", 3, "aa".to_string()), Corpus::new("code", 1, "bbbbbbbb".to_string())];
+
+    let values = weighted(&config, |corpus| corpus.frequencies().iter().map(|(symbol, count)| ((0, *symbol as usize - 'a' as usize), *count)).collect());
+
+    assert_eq!(values[&(0, 0)], 0.75);
+    assert_eq!(values[&(0, 1)], 0.25);
+  }
+
+  #[test]
+  fn counts_the_usage() {
+    let layout = QWERTY.clone();
+    let keyboard = Keyboard::from(&layout, &US_PC_KEYBOARD);
+    let usage = usage_for(&keyboard, &vec![('a', 1), ('A', 1), (' ', 1), ('b', 1)]);
+
+    assert_eq!(usage[&(2, 0)], 2);
+    assert_eq!(usage[&(3, 5)], 1);
+    assert_eq!(usage.len(), 3);
+  }
+
+  #[test]
+  fn draws_every_key() {
+    let layout = COLEMAK.clone();
+    let keyboard = Keyboard::from(&layout, &FULL_ORTHO);
+    let mut config = Config::defaults().unwrap();
+    config.corpora = vec![Corpus::new("test", 1, "ffff t".to_string())];
+
+    let svg = render(&keyboard, &config, Tint::Usage);
+    let keys = keys(&svg);
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains(">QWFPGJ, usage</text>"));
+    assert_eq!(keys.len(), 52);
+
+    let f_key = keys.iter().find(|key| key.contains(">f</text>")).unwrap();
+    assert!(f_key.contains("<title>66.67%</title>"));
+    assert!(f_key.contains("fill=\"#e63c28\""));
+    assert!(f_key.contains(">F</text>"));
+
+    let space_key = keys.iter().find(|key| key.contains(">︺</text>")).unwrap();
+    assert!(space_key.contains("<title>16.67%</title>"));
+
+    assert!(keys.iter().any(|key| key.contains(">&lt;</text>")));
+  }
+
+  #[test]
+  fn tints_by_effort() {
+    let layout = QWERTY.clone();
+    let keyboard = Keyboard::from(&layout, &US_PC_KEYBOARD);
+    let mut config = Config::defaults().unwrap();
+    config.corpora = vec![Corpus::new("test", 1, "qwq".to_string())];
+
+    let svg = render(&keyboard, &config, Tint::Effort);
+    let q_key = keys(&svg).into_iter().find(|key| key.contains(">q</text>")).unwrap();
+
+    assert!(svg.contains(">QWERTY, effort</text>"));
+    let efforts = Calculator::from(&keyboard, &config.penalties).efforts(&calculator::ngrams_for(&keyboard, &config.corpora[0]));
+    let share = (6 + 6 + config.penalties.same_hand) as f64 / efforts.values().sum::<usize>() as f64;
+
+    assert!(q_key.contains(&format!("<title>{:.2}%</title>", share * 100.0)));
+  }
+}
@@ -0,0 +1,77 @@
+/**
+ * Tells letters, digits and the rest of the symbols apart. The letters and
+ * digits are the Unicode ones, so Cyrillic and Greek layouts work the same
+ * way the Latin ones do, and the config can move symbols between the classes
+ * for the alphabets that need it, the apostrophe in Ukrainian say
+ */
+use serde::Deserialize;
+
+#[derive(Debug,PartialEq,Clone,Copy)]
+pub enum Class {
+  Letter,
+  Digit,
+  Other
+}
+
+/// The symbols that go to a class other than the Unicode one
+#[derive(Deserialize,Debug,Clone,Default,PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Classes {
+  #[serde(default)]
+  pub letters: String,
+  #[serde(default)]
+  pub digits: String,
+  #[serde(default)]
+  pub others: String
+}
+
+impl Classes {
+  pub fn class_of(&self, symbol: char) -> Class {
+    if self.letters.contains(symbol) { return Class::Letter; }
+    if self.digits.contains(symbol) { return Class::Digit; }
+    if self.others.contains(symbol) { return Class::Other; }
+
+    if symbol.is_alphabetic() {
+      Class::Letter
+    } else if symbol.is_numeric() {
+      Class::Digit
+    } else {
+      Class::Other
+    }
+  }
+
+  pub fn is_letter(&self, symbol: char) -> bool {
+    self.class_of(symbol) == Class::Letter
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn classifies_any_alphabet() {
+    let classes = Classes::default();
+
+    for symbol in ['a', 'Z', 'ж', 'Ж', 'λ', 'é', 'ß'].iter() {
+      assert_eq!(classes.class_of(*symbol), Class::Letter, "{}", symbol);
+    }
+
+    for symbol in ['0', '9', '٣', '²'].iter() {
+      assert_eq!(classes.class_of(*symbol), Class::Digit, "{}", symbol);
+    }
+
+    for symbol in [';', '«', '—', '€', ' '].iter() {
+      assert_eq!(classes.class_of(*symbol), Class::Other, "{}", symbol);
+    }
+  }
+
+  #[test]
+  fn takes_the_config_classes_first() {
+    let classes = Classes { letters: "'".to_string(), digits: "".to_string(), others: "ª".to_string() };
+
+    assert_eq!(classes.class_of('\''), Class::Letter);
+    assert_eq!(classes.class_of('ª'), Class::Other);
+    assert_eq!(classes.class_of('ї'), Class::Letter);
+  }
+}
@@ -9,16 +9,19 @@ use tui::symbols::{Marker};
 use tui::widgets::{Block, Borders, Cell, Row, Table, Dataset, Chart, Axis, GraphType, Paragraph};
 use tui::layout::{Layout, Constraint, Direction};
 
+use rusty_key::Config;
+
 use crate::events::{self,Event};
 use crate::model::ViewModel;
 
-pub fn render() -> Result<(), Box<dyn std::error::Error>> {
+pub fn render(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
   let stdout = io::stdout().into_raw_mode()?;
   let backend = TermionBackend::new(stdout);
   let mut terminal = Terminal::new(backend)?;
 
-  let mut model = ViewModel::new();
+  let mut model = ViewModel::new(config.progress_window_size, config.weights.clone());
 
+  events::inst().listen_to_terminal(events::Config::default());
   terminal.clear()?;
 
   loop {
@@ -78,8 +81,13 @@ pub fn render() -> Result<(), Box<dyn std::error::Error>> {
         Some(outcome) => outcome.generation_number,
         None => 0
       };
+      let title = match (&model.finish, &model.warning) {
+        (Some(finish), _) => format!(" Progress (gen {}, stopped: {}, press q to quit) ", generation_number, finish.reason),
+        (None, Some(warning)) => format!(" Progress (gen {}, {}) ", generation_number, warning),
+        (None, None) => format!(" Progress (gen {}) ", generation_number)
+      };
       let chart_block = Block::default()
-            .title(format!(" Progress (gen {}) ", generation_number))
+            .title(title)
             .borders(Borders::ALL);
       let top_scores = model.top_scores();
       let best_scores = model.best_scores();
@@ -158,6 +166,12 @@ pub fn render() -> Result<(), Box<dyn std::error::Error>> {
       Event::Result(outcome) => { // calculation result
         model.record(outcome);
       },
+      Event::Finished(finish) => { // the evolution has stopped
+        model.finish(finish);
+      },
+      Event::Warning(message) => { // the evolution goes on, but the user should know
+        model.warn(message);
+      },
       Event::Input(key) => {  // any key
         if key == Key::Char('q') {
           terminal.clear()?;
@@ -0,0 +1,220 @@
+/**
+ * Writes layouts as XKB symbols files for Linux and reads them back. Put
+ * the result into `~/.config/xkb/symbols/<name>` and switch with `setxkbmap <name>`
+ */
+use hashbrown::HashMap;
+use once_cell::sync::Lazy;
+
+use crate::layout::*;
+use crate::importer::*;
+use crate::keysyms::KEYSYMS;
+use crate::parser::Position;
+
+// every position in the layout
+static ROWS: Lazy<Vec<Position>> = Lazy::new(|| {
+  ROW_SIZES.iter().enumerate().flat_map(|(row, size)| (0..*size).map(move |col| (row, col))).collect()
+});
+
+/// Reads the keys of the first `xkb_symbols` block in a symbols file
+pub fn import(data: &str) -> Result<Import, String> {
+  let start = data.find("xkb_symbols").ok_or("no xkb_symbols block in there")?;
+  let block = &data[start + 1..];
+  let block = &block[..block.find("xkb_symbols").unwrap_or(block.len())];
+
+  let mut symbols = HashMap::new();
+  let mut warnings = vec![];
+
+  for line in block.lines().map(str::trim).filter(|line| line.starts_with("key ")) {
+    let keycode = between(line, '<', '>').unwrap_or_default();
+    let levels: Vec<&str> = between(line, '[', ']').unwrap_or_default().split(',').map(str::trim).collect();
+    let position = ROWS.iter().find(|position| keycode_for(**position).as_deref() == Some(keycode));
+
+    match (position, levels.as_slice()) {
+      (None, _) => warnings.push(format!("skipped <{}>, it's not one of the 47 keys", keycode)),
+      (Some(position), [normal, shifted, rest @ ..]) => {
+        if !rest.is_empty() {
+          warnings.push(format!("skipped {} on <{}>", rest.join(", "), keycode));
+        }
+
+        match (symbol_for(normal), symbol_for(shifted)) {
+          (Some(normal), Some(shifted)) => { symbols.insert(*position, (normal, shifted)); },
+          _ => warnings.push(format!("can't read the {}, {} keysyms on <{}>", normal, shifted, keycode))
+        }
+      },
+      (Some(_), _) => warnings.push(format!("<{}> needs both the normal and the shifted symbols", keycode))
+    }
+  }
+
+  complete(symbols, warnings)
+}
+
+fn between(line: &str, open: char, close: char) -> Option<&str> {
+  let start = line.find(open)? + 1;
+  let end = start + line[start..].find(close)?;
+
+  Some(&line[start..end])
+}
+
+/// The XKB `symbols` file for the layout, with `name` as its description
+pub fn export(layout: &Layout, name: &str) -> String {
+  let mut lines: Vec<String> = layout.metadata.notes().iter().map(|note| format!("// {}", note)).collect();
+
+  lines.extend(vec![
+    "default partial alphanumeric_keys".to_string(),
+    "xkb_symbols \"basic\" {".to_string(),
+    "    include \"us(basic)\"".to_string(),
+    format!("    name[Group1] = \"{}\";", name.replace('"', "'")),
+    "".to_string()
+  ]);
+
+  for entry in layout.entries() {
+    if let Some(keycode) = keycode_for(entry.position) {
+      lines.push(format!(
+        "    key <{}> {{ [ {}, {} ] }};",
+        keycode, keysym_for(&entry.normal), keysym_for(&entry.shifted)
+      ));
+    }
+  }
+
+  lines.push("};".to_string());
+  lines.join("\n") + "\n"
+}
+
+// the layout rows are the number row, then the top, home and bottom letter rows
+fn keycode_for(position: Position) -> Option<String> {
+  match position {
+    (0, 0) => Some("TLDE".to_string()),
+    (0, col @ 1..=12) => Some(format!("AE{:02}", col)),
+    (1, 12) => Some("BKSL".to_string()),
+    (1, col @ 0..=11) => Some(format!("AD{:02}", col + 1)),
+    (2, col @ 0..=10) => Some(format!("AC{:02}", col + 1)),
+    (3, col @ 0..=9) => Some(format!("AB{:02}", col + 1)),
+    _ => None
+  }
+}
+
+fn keysym_for(symbol: &str) -> String {
+  let char = symbol.chars().next().unwrap_or(' ');
+
+  match KEYSYMS.iter().find(|(_, _, c)| *c == char) {
+    _ if char.is_ascii_alphanumeric() => char.to_string(),
+    Some((name, _, _)) if char.is_ascii() => name.to_string(),
+    // anything else goes as a unicode keysym
+    _ => format!("U{:04X}", char as u32)
+  }
+}
+
+// the keysyms go by name, as `U` and the code point, or as their `0x` code
+fn symbol_for(keysym: &str) -> Option<String> {
+  let mut chars = keysym.chars();
+
+  if let (Some(char), None) = (chars.next(), chars.next()) {
+    return Some(char.to_string()).filter(|_| char.is_ascii_alphanumeric());
+  }
+
+  let hex = |hex: &str| u32::from_str_radix(hex, 16).ok();
+  let char = match keysym.strip_prefix("0x").and_then(hex) {
+    // the codes from 0x01000000 up are unicode, the latin-1 ones are the code points as they are
+    Some(code) if code & 0xFF000000 == 0x01000000 => std::char::from_u32(code & 0xFFFFFF),
+    Some(code) if code < 0x100 => std::char::from_u32(code).filter(|char| !char.is_control()),
+    Some(code) => KEYSYMS.iter().find(|(_, value, _)| *value == code).map(|(_, _, char)| *char),
+    None => keysym.strip_prefix('U').and_then(hex).and_then(std::char::from_u32)
+      .or_else(|| KEYSYMS.iter().find(|(name, _, _)| *name == keysym).map(|(_, _, char)| *char))
+  };
+
+  char.map(|char| char.to_string())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn maps_positions_to_keycodes() {
+    assert_eq!(keycode_for((0, 0)), Some("TLDE".to_string()));
+    assert_eq!(keycode_for((0, 12)), Some("AE12".to_string()));
+    assert_eq!(keycode_for((1, 0)), Some("AD01".to_string()));
+    assert_eq!(keycode_for((1, 12)), Some("BKSL".to_string()));
+    assert_eq!(keycode_for((2, 10)), Some("AC11".to_string()));
+    assert_eq!(keycode_for((3, 9)), Some("AB10".to_string()));
+    assert_eq!(keycode_for((3, 10)), None);
+  }
+
+  #[test]
+  fn maps_symbols_to_keysyms() {
+    assert_eq!(keysym_for("q"), "q");
+    assert_eq!(keysym_for("Q"), "Q");
+    assert_eq!(keysym_for("7"), "7");
+    assert_eq!(keysym_for(";"), "semicolon");
+    assert_eq!(keysym_for("\""), "quotedbl");
+    assert_eq!(keysym_for("é"), "U00E9");
+  }
+
+  #[test]
+  fn exports_layouts() {
+    let symbols = export(&COLEMAK, "Colemak");
+    let lines: Vec<&str> = symbols.lines().collect();
+
+    assert_eq!(&lines[0..4], &[
+      "default partial alphanumeric_keys",
+      "xkb_symbols \"basic\" {",
+      "    include \"us(basic)\"",
+      "    name[Group1] = \"Colemak\";"
+    ]);
+    assert_eq!(lines.len(), 5 + 47 + 1);
+    assert!(lines.contains(&"    key <TLDE> { [ grave, asciitilde ] };"));
+    assert!(lines.contains(&"    key <AD03> { [ f, F ] };"));
+    assert!(lines.contains(&"    key <AC04> { [ t, T ] };"));
+    assert!(lines.contains(&"    key <AB10> { [ slash, question ] };"));
+    assert_eq!(lines.last(), Some(&"};"));
+  }
+
+  #[test]
+  fn reads_keysyms() {
+    assert_eq!(symbol_for("q"), Some("q".to_string()));
+    assert_eq!(symbol_for("semicolon"), Some(";".to_string()));
+    assert_eq!(symbol_for("U00E9"), Some("é".to_string()));
+    assert_eq!(symbol_for("0x10000E9"), Some("é".to_string()));
+    assert_eq!(symbol_for("0xe9"), Some("é".to_string()));
+    assert_eq!(symbol_for("0x6c1"), Some("а".to_string()));
+    assert_eq!(symbol_for("0x20000E9"), None);
+    assert_eq!(symbol_for("eacute"), Some("é".to_string()));
+    assert_eq!(symbol_for("adiaeresis"), Some("ä".to_string()));
+    assert_eq!(symbol_for("scaron"), Some("š".to_string()));
+    assert_eq!(symbol_for("Cyrillic_zhe"), Some("ж".to_string()));
+    assert_eq!(symbol_for("Greek_lambda"), Some("λ".to_string()));
+    assert_eq!(symbol_for("NoSymbol"), None);
+  }
+
+  #[test]
+  fn imports_what_it_exports() {
+    let imported = import(&export(&COLEMAK, "Colemak")).unwrap();
+
+    assert_eq!(imported.layout.entries(), COLEMAK.entries());
+    assert_eq!(imported.warnings, Vec::<String>::new());
+  }
+
+  #[test]
+  fn reports_what_does_not_fit() {
+    let symbols = "
+      partial alphanumeric_keys
+      xkb_symbols \"basic\" {
+          key <AD01> { [ q, Q, adiaeresis, Adiaeresis ] };
+          key <AD02> { [ eacute, XF86AudioPlay ] };
+          key <LSGT> { [ less, greater ] };
+      };
+      xkb_symbols \"other\" {
+          key <AD03> { [ x, X ] };
+      };
+    ";
+    let imported = import(symbols).unwrap();
+
+    assert_eq!(imported.layout.entries()[15].normal, "e");
+    assert_eq!(&imported.warnings[0..3], &[
+      "skipped adiaeresis, Adiaeresis on <AD01>".to_string(),
+      "can't read the eacute, XF86AudioPlay keysyms on <AD02>".to_string(),
+      "skipped <LSGT>, it's not one of the 47 keys".to_string()
+    ]);
+    assert_eq!(import("blah"), Err("no xkb_symbols block in there".to_string()));
+  }
+}